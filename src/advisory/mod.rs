@@ -0,0 +1,231 @@
+//! Check the project's dependencies against a local database of
+//! security advisories
+
+pub mod osv;
+pub mod report;
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::build::requirements::{Line, RequirementsFile};
+use crate::deps::find_declarations;
+use crate::lock::LOCKFILE;
+use crate::package::installed::{self, required_distributions};
+use crate::package::marker::Environment;
+use crate::package::requirement::Requirement;
+use crate::package::specifier::Operator;
+use crate::report::notes;
+use crate::package::version::Version;
+
+use self::osv::{Advisory, Database, Hit, Severity};
+
+/// Source of the versions when there is no lockfile
+pub const INSTALLED: &str = "installed";
+
+/// Options of the audit subcommand
+pub struct AuditOptions {
+    /// Directory or zip of OSV advisories
+    pub database: PathBuf,
+    pub format: String,
+    /// Lockfile to check, defaults to requirements.lock if it exists
+    pub lockfile: Option<String>,
+    pub output: Option<String>,
+    /// Lowest severity which fails the audit
+    pub fail_on: Severity,
+    /// Whether advisories without a known severity fail the audit
+    pub fail_on_unknown: bool,
+}
+
+/// A dependency affected by an advisory
+pub struct Finding<'a> {
+    pub package: String,
+    pub version: String,
+    /// Where the version comes from (the lockfile, or `installed`)
+    pub source: String,
+    /// Line of the lockfile pinning it
+    pub line: Option<usize>,
+    pub advisory: &'a Advisory,
+    pub hit: Hit,
+}
+
+/// A package version to check
+struct Package {
+    name: String,
+    version: String,
+    source: String,
+    line: Option<usize>,
+}
+
+/// Check the locked (or installed) versions of the project's dependencies
+/// against the advisories in a database
+///
+/// Returns false if a dependency is affected by an advisory at or above
+/// the `fail_on` severity, or by one without a known severity unless
+/// `fail_on_unknown` is off.
+pub fn audit(options: &AuditOptions) -> bool {
+    let note = notes(&options.format, options.output.as_deref());
+
+    let packages = match find_packages(options.lockfile.as_deref(), &note) {
+        Ok(x) => x,
+        Err(e) => {
+            note(e);
+            return false;
+        }
+    };
+
+    let database = match Database::load(&options.database) {
+        Ok(x) => x,
+        Err(e) => {
+            note(e);
+            return false;
+        }
+    };
+
+    let source = match packages.first().map(|x| x.source.as_str()) {
+        Some(INSTALLED) | None => "the installed packages",
+        Some(x) => x,
+    };
+    note(format!(
+        "Checking {} package(s) from {} against {} advisories..",
+        packages.len(), source, database.advisories.len()
+    ));
+
+    let mut findings: Vec<Finding> = Vec::new();
+
+    for package in &packages {
+        let version = match Version::parse(&package.version) {
+            Some(x) => x,
+            None => {
+                note(format!("WARNING: Skipped {}, {} isn't a valid version", package.name, package.version));
+                continue;
+            }
+        };
+
+        for (advisory, hit) in database.find(&package.name, &version) {
+            findings.push(Finding {
+                package: package.name.clone(),
+                version: package.version.clone(),
+                source: package.source.clone(),
+                line: package.line,
+                advisory,
+                hit,
+            });
+        }
+    }
+
+    // Most severe first
+    findings.sort_by(|a, b| {
+        b.advisory
+            .severity
+            .partial_cmp(&a.advisory.severity)
+            .unwrap()
+            .then_with(|| a.package.to_lowercase().cmp(&b.package.to_lowercase()))
+            .then_with(|| a.advisory.id.cmp(&b.advisory.id))
+    });
+
+    let report = report::export(&findings, &options.format).unwrap();
+
+    match &options.output {
+        Some(file) => match fs::write(file, &report) {
+            Ok(_) => println!("Successfully wrote the audit report to {}", file),
+            Err(e) => {
+                println!("Error while writing to {}: {}", file, e);
+                return false;
+            }
+        },
+        None => print!("{}", report),
+    };
+
+    let fails = |severity: Severity| match severity {
+        Severity::Unknown => options.fail_on_unknown,
+        x => x >= options.fail_on,
+    };
+    let failing = findings.iter().filter(|x| fails(x.advisory.severity)).count();
+
+    if failing > 0 {
+        note(format!(
+            "Found {} vulnerability(ies), {} at or above {} severity{}",
+            findings.len(),
+            failing,
+            options.fail_on,
+            if options.fail_on_unknown { " or of unknown severity" } else { "" }
+        ));
+    }
+
+    failing == 0
+}
+
+/// Versions pinned in a lockfile, or else the installed versions of the
+/// distributions the project's requirements (runtime and dev) need
+fn find_packages(lockfile: Option<&str>, note: &dyn Fn(String)) -> Result<Vec<Package>, String> {
+    let lockfile = match lockfile {
+        Some(x) => Some(x.to_string()),
+        None if Path::new(LOCKFILE).exists() => Some(String::from(LOCKFILE)),
+        None => None,
+    };
+
+    let file = match lockfile {
+        Some(x) => x,
+        None => return find_required(note),
+    };
+
+    let requirements = RequirementsFile::load(Path::new(&file))
+        .map_err(|e| format!("Error while reading {}: {}", file, e))?;
+
+    let mut packages: Vec<Package> = Vec::new();
+    let mut line = 1;
+
+    for entry in &requirements.lines {
+        if let Line::Requirement { requirement, .. } = entry {
+            let specifiers = &requirement.specifier.specifiers;
+            let pinned = match specifiers.as_slice() {
+                [x] if [Operator::Equal, Operator::Arbitrary].contains(&x.operator) && !x.wildcard => Some(x),
+                _ => None,
+            };
+
+            match pinned {
+                Some(x) => packages.push(Package {
+                    name: requirement.name.clone(),
+                    version: x.version.clone(),
+                    source: file.clone(),
+                    line: Some(line),
+                }),
+                None => note(format!("WARNING: Skipped {} in {}, it isn't pinned to a version", requirement, file)),
+            }
+        }
+
+        let raw = match entry {
+            Line::Requirement { raw, .. } | Line::Other(raw) => raw,
+        };
+        line += raw.matches('\n').count() + 1;
+    }
+
+    Ok(packages)
+}
+
+/// Installed versions of the distributions the project's requirements
+/// need (and everything they require)
+fn find_required(note: &dyn Fn(String)) -> Result<Vec<Package>, String> {
+    let requirements: Vec<Requirement> = find_declarations()?
+        .into_iter()
+        .map(|x| x.requirement)
+        .collect();
+
+    let distributions = installed::find_distributions();
+    let environment = Environment::current();
+    let (required, missing) = required_distributions(&distributions, &requirements, &environment);
+
+    for requirement in &missing {
+        note(format!("WARNING: {} isn't installed, so it isn't checked", requirement.name));
+    }
+
+    Ok(required
+        .into_iter()
+        .map(|(x, _)| Package {
+            name: x.name.clone(),
+            version: x.version.clone(),
+            source: String::from(INSTALLED),
+            line: None,
+        })
+        .collect())
+}
@@ -0,0 +1,525 @@
+//! Read advisories in the OSV format (https://ossf.github.io/osv-schema/)
+//! from a directory or zip of JSON files
+
+use std::fmt;
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use serde_json::Value;
+
+use crate::package::name::normalise_name;
+use crate::package::version::Version;
+
+/// Severity of an advisory
+#[derive(Clone, Copy, PartialEq, PartialOrd, Debug)]
+pub enum Severity {
+    /// The advisory has no severity pyproj can read
+    Unknown,
+    Low,
+    Medium,
+    High,
+    Critical,
+}
+
+/// An advisory for PyPI packages
+pub struct Advisory {
+    pub id: String,
+    pub aliases: Vec<String>,
+    pub summary: String,
+    pub severity: Severity,
+    /// CVSS base score, if the advisory has a CVSS v3 vector
+    pub score: Option<f64>,
+    affected: Vec<Affected>,
+}
+
+/// Versions of a package affected by an advisory
+struct Affected {
+    /// Normalised package name
+    package: String,
+    /// Each range is a list of events (introduced, fixed, ...)
+    ranges: Vec<Vec<Event>>,
+    /// Versions listed one by one
+    versions: Vec<String>,
+}
+
+#[derive(Clone, PartialEq)]
+enum Event {
+    Introduced(Version),
+    Fixed(Version),
+    LastAffected(Version),
+    Limit(Version),
+}
+
+/// How a version is affected by an advisory
+pub struct Hit {
+    /// The affected range (`>=1.0,<1.2.3`)
+    pub range: String,
+    /// First version fixing it, if there is one
+    pub fixed: Option<String>,
+}
+
+/// Every PyPI advisory in a database
+pub struct Database {
+    pub advisories: Vec<Advisory>,
+}
+
+impl Database {
+    /// Load the advisories of a directory (searched recursively) or zip file
+    pub fn load(path: &Path) -> Result<Database, String> {
+        let mut files: Vec<(String, String)> = Vec::new();
+
+        if path.is_dir() {
+            read_dir(path, &mut files)?;
+        } else {
+            let file = fs::File::open(path).map_err(|e| format!("Error while reading {}: {}", path.display(), e))?;
+            let mut archive = zip::ZipArchive::new(file)
+                .map_err(|e| format!("Error while reading {}: {}", path.display(), e))?;
+
+            for i in 0..archive.len() {
+                let mut entry = archive.by_index(i).map_err(|e| e.to_string())?;
+                if !entry.name().ends_with(".json") {
+                    continue;
+                }
+
+                let name = entry.name().to_string();
+                let mut content = String::new();
+                entry
+                    .read_to_string(&mut content)
+                    .map_err(|e| format!("Error while reading {} in {}: {}", name, path.display(), e))?;
+                files.push((name, content));
+            }
+        }
+
+        let mut advisories: Vec<Advisory> = Vec::new();
+
+        for (name, content) in files {
+            let json: Value = match serde_json::from_str(&content) {
+                Ok(x) => x,
+                Err(e) => {
+                    eprintln!("WARNING: Skipped {}: {}", name, e);
+                    continue;
+                }
+            };
+
+            if let Some(advisory) = Advisory::from_json(&json) {
+                advisories.push(advisory);
+            }
+        }
+
+        Ok(Database { advisories })
+    }
+
+    /// Advisories affecting a version of a package
+    pub fn find(&self, package: &str, version: &Version) -> Vec<(&Advisory, Hit)> {
+        let package = normalise_name(package);
+
+        self.advisories
+            .iter()
+            .filter_map(|advisory| {
+                advisory
+                    .affected
+                    .iter()
+                    .filter(|x| x.package == package)
+                    .find_map(|x| x.hit(version))
+                    .map(|hit| (advisory, hit))
+            })
+            .collect()
+    }
+}
+
+impl Advisory {
+    /// Read an advisory, skipping withdrawn ones and those without PyPI packages
+    fn from_json(json: &Value) -> Option<Advisory> {
+        if json.get("withdrawn").is_some() {
+            return None;
+        }
+
+        let affected: Vec<Affected> = json
+            .get("affected")?
+            .as_array()?
+            .iter()
+            .filter(|x| x["package"]["ecosystem"].as_str() == Some("PyPI"))
+            .filter_map(Affected::from_json)
+            .collect();
+
+        if affected.is_empty() {
+            return None;
+        }
+
+        let strings = |value: &Value| -> Vec<String> {
+            value
+                .as_array()
+                .map(|x| x.iter().filter_map(|x| x.as_str()).map(|x| x.to_string()).collect())
+                .unwrap_or_default()
+        };
+
+        // A CVSS v3 vector gives a score, else use the database's own rating
+        let score = json["severity"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter(|x| x["type"].as_str().map(|t| t.starts_with("CVSS_V3")).unwrap_or(false))
+            .find_map(|x| x["score"].as_str().and_then(cvss3_score));
+
+        let rating = [&json["database_specific"], &json["affected"][0]["database_specific"], &json["affected"][0]["ecosystem_specific"]]
+            .iter()
+            .find_map(|x| x["severity"].as_str().map(Severity::parse));
+
+        let severity = match (score, rating) {
+            (Some(score), _) => Severity::from_score(score),
+            (None, Some(rating)) => rating,
+            (None, None) => Severity::Unknown,
+        };
+
+        Some(Advisory {
+            id: json["id"].as_str()?.to_string(),
+            aliases: strings(&json["aliases"]),
+            summary: json["summary"]
+                .as_str()
+                .or_else(|| json["details"].as_str().and_then(|x| x.lines().next()))
+                .unwrap_or("")
+                .to_string(),
+            severity,
+            score,
+            affected,
+        })
+    }
+}
+
+impl Affected {
+    fn from_json(json: &Value) -> Option<Affected> {
+        let package = normalise_name(json["package"]["name"].as_str()?);
+
+        let ranges = json["ranges"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            // Git ranges are commits, not versions
+            .filter(|x| x["type"].as_str() != Some("GIT"))
+            .map(|range| {
+                let mut events: Vec<Event> = range["events"]
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|event| {
+                        let (kind, value) = event.as_object()?.iter().next()?;
+                        let version = Version::parse(value.as_str()?)?;
+
+                        match kind.as_str() {
+                            "introduced" => Some(Event::Introduced(version)),
+                            "fixed" => Some(Event::Fixed(version)),
+                            "last_affected" => Some(Event::LastAffected(version)),
+                            "limit" => Some(Event::Limit(version)),
+                            _ => None,
+                        }
+                    })
+                    .collect();
+
+                events.sort_by(|a, b| a.version().cmp(b.version()));
+                events
+            })
+            .collect();
+
+        let versions = json["versions"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|x| x.as_str())
+            .map(|x| x.to_string())
+            .collect();
+
+        Some(Affected { package, ranges, versions })
+    }
+
+    /// Check whether a version is affected
+    fn hit(&self, version: &Version) -> Option<Hit> {
+        for events in &self.ranges {
+            // Walk the events in order, as the OSV schema describes
+            let mut affected = false;
+            let mut introduced: Option<&Version> = None;
+            let mut end: Option<&Event> = None;
+
+            for event in events {
+                match event {
+                    Event::Introduced(x) if version >= x => {
+                        affected = true;
+                        introduced = Some(x);
+                        end = None;
+                    },
+                    Event::Fixed(x) | Event::Limit(x) if version >= x => affected = false,
+                    Event::LastAffected(x) if version > x => affected = false,
+                    Event::Introduced(_) => {},
+                    _ => {
+                        if affected && end.is_none() {
+                            end = Some(event);
+                        }
+                    },
+                }
+            }
+
+            if !affected {
+                continue;
+            }
+
+            let mut bounds: Vec<String> = Vec::new();
+            if let Some(x) = introduced.filter(|x| x.release.iter().any(|n| *n != 0)) {
+                bounds.push(format!(">={}", x));
+            }
+            match end {
+                Some(Event::Fixed(x)) | Some(Event::Limit(x)) => bounds.push(format!("<{}", x)),
+                Some(Event::LastAffected(x)) => bounds.push(format!("<={}", x)),
+                _ => {},
+            }
+
+            let fixed = match end {
+                Some(Event::Fixed(x)) => Some(x.to_string()),
+                _ => None,
+            };
+
+            return Some(Hit {
+                range: match bounds.is_empty() {
+                    true => String::from("all versions"),
+                    false => bounds.join(","),
+                },
+                fixed,
+            });
+        }
+
+        let listed = self
+            .versions
+            .iter()
+            .any(|x| Version::parse(x).map(|x| x == *version).unwrap_or(false));
+
+        match listed {
+            true => Some(Hit {
+                range: format!("=={}", version),
+                fixed: None,
+            }),
+            false => None,
+        }
+    }
+}
+
+impl Event {
+    fn version(&self) -> &Version {
+        match self {
+            Event::Introduced(x) | Event::Fixed(x) | Event::LastAffected(x) | Event::Limit(x) => x,
+        }
+    }
+}
+
+impl Severity {
+    /// Parse a rating (`LOW`, `MODERATE`, `high`, ...)
+    pub fn parse(rating: &str) -> Severity {
+        match rating.to_lowercase().as_str() {
+            "low" => Severity::Low,
+            "medium" | "moderate" => Severity::Medium,
+            "high" | "important" => Severity::High,
+            "critical" => Severity::Critical,
+            _ => Severity::Unknown,
+        }
+    }
+
+    /// Rating of a CVSS score
+    fn from_score(score: f64) -> Severity {
+        match score {
+            x if x >= 9.0 => Severity::Critical,
+            x if x >= 7.0 => Severity::High,
+            x if x >= 4.0 => Severity::Medium,
+            x if x > 0.0 => Severity::Low,
+            _ => Severity::Unknown,
+        }
+    }
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Severity::Unknown => "unknown",
+            Severity::Low => "low",
+            Severity::Medium => "medium",
+            Severity::High => "high",
+            Severity::Critical => "critical",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Compute the base score of a CVSS v3 vector (`CVSS:3.1/AV:N/AC:L/...`)
+fn cvss3_score(vector: &str) -> Option<f64> {
+    let metric = |name: &str| -> Option<&str> {
+        vector
+            .split('/')
+            .filter_map(|x| x.split_once(':'))
+            .find(|(key, _)| *key == name)
+            .map(|(_, value)| value)
+    };
+
+    let changed = metric("S")? == "C";
+
+    let attack_vector = match metric("AV")? {
+        "N" => 0.85,
+        "A" => 0.62,
+        "L" => 0.55,
+        "P" => 0.2,
+        _ => return None,
+    };
+    let complexity = match metric("AC")? {
+        "L" => 0.77,
+        "H" => 0.44,
+        _ => return None,
+    };
+    let privileges = match (metric("PR")?, changed) {
+        ("N", _) => 0.85,
+        ("L", false) => 0.62,
+        ("L", true) => 0.68,
+        ("H", false) => 0.27,
+        ("H", true) => 0.5,
+        _ => return None,
+    };
+    let interaction = match metric("UI")? {
+        "N" => 0.85,
+        "R" => 0.62,
+        _ => return None,
+    };
+    let impact = |name: &str| -> Option<f64> {
+        match metric(name)? {
+            "H" => Some(0.56),
+            "L" => Some(0.22),
+            "N" => Some(0.0),
+            _ => None,
+        }
+    };
+
+    let iss = 1.0 - (1.0 - impact("C")?) * (1.0 - impact("I")?) * (1.0 - impact("A")?);
+    let impact = match changed {
+        true => 7.52 * (iss - 0.029) - 3.25 * (iss - 0.02).powi(15),
+        false => 6.42 * iss,
+    };
+    let exploitability = 8.22 * attack_vector * complexity * privileges * interaction;
+
+    if impact <= 0.0 {
+        return Some(0.0);
+    }
+
+    let score = match changed {
+        true => (1.08 * (impact + exploitability)).min(10.0),
+        false => (impact + exploitability).min(10.0),
+    };
+
+    // Round up to one decimal, as the specification does
+    let scaled = (score * 100000.0).round() as i64;
+    match scaled % 10000 {
+        0 => Some(scaled as f64 / 100000.0),
+        _ => Some(((scaled / 10000) + 1) as f64 / 10.0),
+    }
+}
+
+/// Read every JSON file in a directory and its subdirectories
+fn read_dir(dir: &Path, files: &mut Vec<(String, String)>) -> Result<(), String> {
+    let entries = fs::read_dir(dir).map_err(|e| format!("Error while reading {}: {}", dir.display(), e))?;
+
+    let mut paths: Vec<PathBuf> = entries.filter_map(|x| x.ok()).map(|x| x.path()).collect();
+    paths.sort();
+
+    for path in paths {
+        if path.is_dir() {
+            read_dir(&path, files)?;
+        } else if path.extension().map(|x| x == "json").unwrap_or(false) {
+            let content = fs::read_to_string(&path)
+                .map_err(|e| format!("Error while reading {}: {}", path.display(), e))?;
+            files.push((path.display().to_string(), content));
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn database(affected: Value) -> Database {
+        let advisory = json!({
+            "id": "GHSA-test",
+            "summary": "Something bad",
+            "affected": [affected],
+        });
+
+        Database { advisories: Advisory::from_json(&advisory).into_iter().collect() }
+    }
+
+    fn hit(database: &Database, version: &str) -> Option<(String, Option<String>)> {
+        database
+            .find("Some_Package", &Version::parse(version).unwrap())
+            .into_iter()
+            .next()
+            .map(|(_, x)| (x.range, x.fixed))
+    }
+
+    #[test]
+    fn ranges_affect_versions_between_their_events() {
+        let database = database(json!({
+            "package": { "ecosystem": "PyPI", "name": "some-package" },
+            "ranges": [{ "type": "ECOSYSTEM", "events": [
+                { "introduced": "0" }, { "fixed": "1.2.3" },
+                { "introduced": "2.0" }, { "last_affected": "2.1" },
+            ]}],
+        }));
+
+        assert_eq!(hit(&database, "1.0"), Some((String::from("<1.2.3"), Some(String::from("1.2.3")))));
+        assert_eq!(hit(&database, "1.2.3"), None);
+        assert_eq!(hit(&database, "1.5"), None);
+        assert_eq!(hit(&database, "2.1"), Some((String::from(">=2.0,<=2.1"), None)));
+        assert_eq!(hit(&database, "2.1.1"), None);
+    }
+
+    #[test]
+    fn listed_versions_are_affected() {
+        let database = database(json!({
+            "package": { "ecosystem": "PyPI", "name": "some-package" },
+            "versions": ["1.0", "1.1"],
+        }));
+
+        assert_eq!(hit(&database, "1.1.0"), Some((String::from("==1.1.0"), None)));
+        assert_eq!(hit(&database, "1.2"), None);
+    }
+
+    #[test]
+    fn other_ecosystems_and_withdrawn_advisories_are_skipped() {
+        let npm = database(json!({
+            "package": { "ecosystem": "npm", "name": "some-package" },
+            "versions": ["1.0"],
+        }));
+        assert!(npm.advisories.is_empty());
+
+        let withdrawn = json!({
+            "id": "GHSA-old",
+            "withdrawn": "2021-01-01T00:00:00Z",
+            "affected": [{ "package": { "ecosystem": "PyPI", "name": "x" }, "versions": ["1.0"] }],
+        });
+        assert!(Advisory::from_json(&withdrawn).is_none());
+    }
+
+    #[test]
+    fn cvss_vectors_are_scored() {
+        let cases = [
+            ("CVSS:3.1/AV:N/AC:L/PR:N/UI:N/S:U/C:H/I:H/A:H", Some(9.8)),
+            ("CVSS:3.1/AV:N/AC:L/PR:N/UI:R/S:C/C:L/I:L/A:N", Some(6.1)),
+            ("CVSS:3.1/AV:N/AC:H/PR:N/UI:N/S:U/C:H/I:N/A:N", Some(5.9)),
+            ("CVSS:3.1/AV:L/AC:L/PR:L/UI:N/S:U/C:N/I:N/A:N", Some(0.0)),
+            ("CVSS:3.1/AV:X/AC:L/PR:N/UI:N/S:U/C:H/I:H/A:H", None),
+        ];
+
+        for (vector, score) in cases {
+            assert_eq!(cvss3_score(vector), score, "{}", vector);
+        }
+
+        assert_eq!(Severity::from_score(9.8), Severity::Critical);
+        assert_eq!(Severity::from_score(6.1), Severity::Medium);
+        assert_eq!(Severity::parse("MODERATE"), Severity::Medium);
+    }
+}
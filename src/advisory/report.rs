@@ -0,0 +1,143 @@
+//! Format the advisories found as a table, JSON or SARIF
+
+use std::collections::HashSet;
+
+use serde_json::{json, Value};
+
+use crate::build::REQUIREMENTS;
+use crate::report::table;
+
+use super::osv::Severity;
+use super::{Finding, INSTALLED};
+
+pub const FORMATS: [&str; 3] = ["table", "json", "sarif"];
+
+/// Format the findings, or None if the format is unknown
+pub fn export(findings: &[Finding], format: &str) -> Option<String> {
+    match format {
+        "table" => Some(to_table(findings)),
+        "json" => Some(serde_json::to_string_pretty(&to_json(findings)).unwrap()),
+        "sarif" => Some(serde_json::to_string_pretty(&to_sarif(findings)).unwrap()),
+        _ => None,
+    }
+}
+
+/// A table with a row for every finding
+pub fn to_table(findings: &[Finding]) -> String {
+    if findings.is_empty() {
+        return String::from("No known vulnerabilities found\n");
+    }
+
+    let rows: Vec<[String; 6]> = findings
+        .iter()
+        .map(|finding| [
+            finding.package.clone(),
+            finding.version.clone(),
+            finding.advisory.id.clone(),
+            finding.advisory.severity.to_string(),
+            finding.hit.range.clone(),
+            finding.hit.fixed.clone().unwrap_or_else(|| String::from("-")),
+        ])
+        .collect();
+
+    table(["Package", "Version", "Advisory", "Severity", "Affected", "Fixed in"], &rows)
+}
+
+pub fn to_json(findings: &[Finding]) -> Value {
+    let findings: Vec<Value> = findings
+        .iter()
+        .map(|x| json!({
+            "package": x.package,
+            "version": x.version,
+            "source": x.source,
+            "id": x.advisory.id,
+            "aliases": x.advisory.aliases,
+            "summary": x.advisory.summary,
+            "severity": x.advisory.severity.to_string(),
+            "score": x.advisory.score,
+            "affected": x.hit.range,
+            "fixed": x.hit.fixed,
+        }))
+        .collect();
+
+    json!({ "vulnerabilities": findings })
+}
+
+/// A SARIF 2.1.0 log, with a rule for every advisory
+pub fn to_sarif(findings: &[Finding]) -> Value {
+    let mut seen: HashSet<&str> = HashSet::new();
+    let mut rules: Vec<Value> = Vec::new();
+
+    for finding in findings {
+        let advisory = finding.advisory;
+        if !seen.insert(&advisory.id) {
+            continue;
+        }
+
+        let mut properties = json!({ "tags": ["security", "vulnerability"] });
+        if let Some(score) = advisory.score {
+            properties["security-severity"] = json!(format!("{:.1}", score));
+        }
+
+        rules.push(json!({
+            "id": advisory.id,
+            "shortDescription": { "text": match advisory.summary.is_empty() {
+                true => advisory.id.clone(),
+                false => advisory.summary.clone(),
+            }},
+            "helpUri": format!("https://osv.dev/vulnerability/{}", advisory.id),
+            "properties": properties,
+        }));
+    }
+
+    let results: Vec<Value> = findings
+        .iter()
+        .map(|x| {
+            let level = match x.advisory.severity {
+                Severity::Critical | Severity::High => "error",
+                Severity::Medium => "warning",
+                Severity::Low | Severity::Unknown => "note",
+            };
+
+            let fix = match &x.hit.fixed {
+                Some(fixed) => format!(", fixed in {}", fixed),
+                None => String::from(", no fix available"),
+            };
+
+            // Installed versions are reported against the requirements they come from
+            let uri = match x.source.as_str() {
+                INSTALLED => REQUIREMENTS,
+                source => source,
+            };
+
+            let mut location = json!({ "physicalLocation": { "artifactLocation": { "uri": uri } } });
+            if let Some(line) = x.line {
+                location["physicalLocation"]["region"] = json!({ "startLine": line });
+            }
+
+            json!({
+                "ruleId": x.advisory.id,
+                "level": level,
+                "message": { "text": format!(
+                    "{} {} is affected by {} ({} severity, affected {}{})",
+                    x.package, x.version, x.advisory.id, x.advisory.severity, x.hit.range, fix
+                )},
+                "locations": [location],
+            })
+        })
+        .collect();
+
+    json!({
+        "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+        "version": "2.1.0",
+        "runs": [{
+            "tool": { "driver": {
+                "name": "pyproj",
+                "version": env!("CARGO_PKG_VERSION"),
+                "informationUri": "https://github.com/Cyclip/pyproj/",
+                "rules": rules,
+            }},
+            "results": results,
+        }],
+    })
+}
@@ -0,0 +1,142 @@
+//! Compare committed requirements with what `pyproj build` would write
+
+use crate::package::name::normalise_name;
+
+use super::requirements::RequirementsFile;
+
+/// Differences between a committed requirements file and the built one
+#[derive(Default)]
+pub struct Drift {
+    /// Requirements the file should list but doesn't
+    pub missing: Vec<String>,
+    /// Requirements the file lists which are no longer imported
+    /// (only found when checking strictly)
+    pub extra: Vec<String>,
+    /// Requirements with a different version (committed, expected)
+    pub mismatched: Vec<(String, String)>,
+    /// Imported packages the file doesn't list, which aren't installed
+    pub not_installed: Vec<String>,
+}
+
+impl Drift {
+    /// Compare a committed file with the file `pyproj build` would write,
+    /// ignoring the order and formatting of requirements
+    ///
+    /// `unused` are the packages listed but no longer imported, and
+    /// `unknown` the imported packages with no installed version. Like
+    /// `pyproj build`, listed packages which aren't imported (tools such as
+    /// black or pytest-cov) are kept unless `strict` is set.
+    pub fn find(
+        committed: &RequirementsFile,
+        expected: &RequirementsFile,
+        unused: &[String],
+        unknown: &[String],
+        strict: bool,
+    ) -> Drift {
+        let mut drift = Drift::default();
+        let committed: Vec<(String, String)> = committed
+            .requirements()
+            .iter()
+            .map(|x| (x.key(), x.normalised().to_string()))
+            .collect();
+
+        for requirement in expected.requirements() {
+            let (name, normalised) = (requirement.key(), requirement.normalised().to_string());
+
+            match committed.iter().find(|(x, _)| x == &name) {
+                Some((_, old)) if old != &normalised => {
+                    drift.mismatched.push((old.clone(), normalised));
+                },
+                Some(_) => {},
+                None => drift.missing.push(normalised),
+            }
+        }
+
+        // Imports which aren't installed are still used, even if `merge`
+        // couldn't match them to a version
+        let unknown_names: Vec<String> = unknown.iter().map(|x| normalise_name(x)).collect();
+
+        for (name, requirement) in &committed {
+            if strict && unused.contains(name) && !unknown_names.contains(name) {
+                drift.extra.push(requirement.clone());
+            }
+        }
+
+        for package in unknown {
+            let name = normalise_name(package);
+            if !committed.iter().any(|(x, _)| x == &name) {
+                drift.not_installed.push(name);
+            }
+        }
+
+        drift
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.missing.is_empty()
+            && self.extra.is_empty()
+            && self.mismatched.is_empty()
+            && self.not_installed.is_empty()
+    }
+
+    /// Print the differences as a diff
+    pub fn print(&self, file: &str) {
+        if self.is_empty() {
+            println!("{} is up to date", file);
+            return;
+        }
+
+        println!("{} is out of date:", file);
+
+        for requirement in &self.missing {
+            println!("\t+ {}\t(missing)", requirement);
+        }
+
+        for package in &self.not_installed {
+            println!("\t+ {}\t(missing, not installed so the version is unknown)", package);
+        }
+
+        for requirement in &self.extra {
+            println!("\t- {}\t(no longer imported)", requirement);
+        }
+
+        for (committed, expected) in &self.mismatched {
+            println!("\t~ {} -> {}", committed, expected);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::policy::VersionPolicy;
+    use super::*;
+
+    #[test]
+    fn imports_which_are_not_installed_are_not_extra() {
+        let committed = RequirementsFile::parse("requests==2.31.0\nsix==1.16.0\n");
+        let unused = vec![String::from("requests"), String::from("six")];
+
+        let drift = Drift::find(&committed, &committed, &unused, &[String::from("Requests")], true);
+
+        assert_eq!(drift.extra, vec![String::from("six==1.16.0")]);
+        assert!(drift.not_installed.is_empty());
+    }
+
+    #[test]
+    fn declared_tools_are_only_extra_when_strict() {
+        // requirements-dev.txt in CI: pytest is imported by the tests, the
+        // other tools are only run from the command line
+        let committed = RequirementsFile::parse("-r requirements.txt\npytest==7.4.0\npytest-cov==4.1.0\nblack==23.12.1\n");
+        let mut expected = RequirementsFile::parse(&committed.content());
+        let report = expected.merge(&[(String::from("pytest"), String::from("7.4.0"))], &VersionPolicy::default(), false);
+
+        assert_eq!(report.unused, ["pytest-cov", "black"]);
+
+        let drift = Drift::find(&committed, &expected, &report.unused, &[], false);
+        assert!(drift.is_empty());
+
+        let drift = Drift::find(&committed, &expected, &report.unused, &[], true);
+        assert_eq!(drift.extra, ["pytest-cov==4.1.0", "black==23.12.1"]);
+        assert!(drift.missing.is_empty() && drift.mismatched.is_empty());
+    }
+}
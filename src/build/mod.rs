@@ -0,0 +1,366 @@
+//! Build requirements files from the imports used in a project
+
+pub mod drift;
+pub mod policy;
+pub mod requirements;
+
+use std::collections::{BTreeSet, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::config::SetupCfg;
+use crate::explorer::Explorer;
+use crate::interpreter::Parser;
+use crate::interpreter::source::Diagnostic;
+use crate::package::name::normalise_name;
+use crate::cache;
+
+use drift::Drift;
+use policy::{Policy, VersionPolicy};
+use requirements::{MergeReport, RequirementsFile};
+
+/// Group a dependency belongs to
+#[derive(Clone, Copy, PartialEq)]
+pub enum Group {
+    /// Needed to run the project (requirements.txt)
+    Runtime,
+    /// Only needed to develop, test or document it (requirements-dev.txt)
+    Dev,
+}
+
+/// Folders scanned for imports and the group their imports belong to
+pub const SCAN_DIRS: [(&str, Group); 4] = [
+    ("src", Group::Runtime),
+    ("tests", Group::Dev),
+    ("examples", Group::Dev),
+    ("docs", Group::Dev),
+];
+
+pub const REQUIREMENTS: &str = "requirements.txt";
+pub const REQUIREMENTS_DEV: &str = "requirements-dev.txt";
+pub const SETUP_CFG: &str = "setup.cfg";
+
+/// Third-party modules imported by the project
+pub struct Dependencies {
+    pub runtime: Vec<String>,
+    pub dev: Vec<String>,
+    /// Files which couldn't be read
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+/// Build requirements.txt, requirements-dev.txt and the
+/// matching setup.cfg entries (if setup.cfg exists)
+///
+/// Discovered dependencies are merged into the existing files, so
+/// pins are only downgraded with `force`. Versions are written following
+/// the project's version policy, or `policy` if given.
+///
+/// With `check`, nothing is written and the differences between the
+/// files and what would be written are shown instead, also showing the
+/// requirements which aren't imported with `strict`. Returns false
+/// if any file is out of date.
+pub fn build_requirements(force: bool, policy: Option<Policy>, check: bool, strict: bool) -> Result<bool, String> {
+    let setup_cfg = Path::new(SETUP_CFG);
+
+    let cfg = match setup_cfg.exists() {
+        true => Some(SetupCfg::load(setup_cfg)
+            .map_err(|e| format!("Error while reading {}: {}", SETUP_CFG, e))?),
+        false => None,
+    };
+
+    let mut version_policy = match &cfg {
+        Some(cfg) => VersionPolicy::from_config(cfg)?,
+        None => VersionPolicy::default(),
+    };
+
+    // --policy replaces the default, per-package policies still apply
+    if let Some(policy) = policy {
+        version_policy.default = policy;
+    }
+
+    let deps = find_dependencies();
+
+    for diagnostic in &deps.diagnostics {
+        println!("WARNING: Skipped {}", diagnostic);
+    }
+
+    let runtime_versions = Parser::with_versions(&deps.runtime);
+    let dev_versions = Parser::with_versions(&deps.dev);
+
+    let runtime = discovered(&runtime_versions);
+    let dev = discovered(&dev_versions);
+
+    // Packages which aren't installed can't be written, but
+    // are still missing when checking
+    let runtime_unknown = not_installed(&deps.runtime, &runtime_versions);
+    let dev_unknown = not_installed(&deps.dev, &dev_versions);
+
+    let mut up_to_date = true;
+
+    up_to_date &= merge_file(REQUIREMENTS, (&runtime, &runtime_unknown), &version_policy, force, (check, strict), None)?;
+    up_to_date &= merge_file(
+        REQUIREMENTS_DEV,
+        (&dev, &dev_unknown),
+        &version_policy,
+        force,
+        (check, strict),
+        Some(format!("-r {}", REQUIREMENTS)),
+    )?;
+
+    if let Some(mut cfg) = cfg {
+        let lists = [
+            ("options", "install_requires", &runtime, &runtime_unknown),
+            ("options.extras_require", "dev", &dev, &dev_unknown),
+        ];
+
+        for (section, key, deps, unknown) in lists {
+            let label = format!("{} {}", SETUP_CFG, key);
+
+            // Comments are kept, as they are in the requirements files
+            let committed = RequirementsFile::parse(&cfg.get_lines(section, key).join("\n"));
+            print_errors(&label, &committed);
+
+            let mut requirements = RequirementsFile::parse(&committed.content());
+            let mut report = requirements.merge(deps, &version_policy, force);
+            keep_imported(&mut report, unknown);
+
+            if check {
+                up_to_date &= check_drift(&label, &committed, &requirements, &report, unknown, strict);
+                continue;
+            }
+
+            print_report(&label, &report);
+
+            let lines: Vec<String> = requirements.content().lines().map(|x| x.to_string()).collect();
+            cfg.set_list(section, key, &lines);
+        }
+
+        if !check {
+            cfg.save(setup_cfg)
+                .map_err(|e| format!("Error while writing to {}: {}", SETUP_CFG, e))?;
+            println!("Successfully updated {}", SETUP_CFG);
+        }
+    }
+
+    Ok(up_to_date)
+}
+
+/// Merge discovered dependencies into a requirements file,
+/// creating it (starting with `header`) if needed
+///
+/// With `check`, the file is compared instead of written (reporting
+/// requirements which aren't imported with `strict`). Returns false
+/// if it is out of date.
+fn merge_file(
+    file: &str,
+    (deps, unknown): (&[(String, String)], &[String]),
+    policy: &VersionPolicy,
+    force: bool,
+    (check, strict): (bool, bool),
+    header: Option<String>,
+) -> Result<bool, String> {
+    let path = Path::new(file);
+
+    let committed = RequirementsFile::load(path)
+        .map_err(|e| format!("Error while reading {}: {}", file, e))?;
+    print_errors(file, &committed);
+
+    let mut requirements = match (path.exists(), header) {
+        (false, Some(header)) => RequirementsFile::parse(&header),
+        _ => RequirementsFile::parse(&committed.content()),
+    };
+
+    let mut report = requirements.merge(deps, policy, force);
+    keep_imported(&mut report, unknown);
+
+    if check {
+        return Ok(check_drift(file, &committed, &requirements, &report, unknown, strict));
+    }
+
+    print_report(file, &report);
+    write(file, &requirements.content())?;
+    Ok(true)
+}
+
+/// Show the differences between a committed file and what would be
+/// written, returning false if there are any
+fn check_drift(
+    file: &str,
+    committed: &RequirementsFile,
+    requirements: &RequirementsFile,
+    report: &MergeReport,
+    unknown: &[String],
+    strict: bool,
+) -> bool {
+    let drift = Drift::find(committed, requirements, &report.unused, unknown, strict);
+    drift.print(file);
+
+    // Requirements which aren't imported are kept, but still worth a mention
+    if !strict {
+        for package in &report.unused {
+            println!("NOTE: {} lists `{}` which isn't imported (use --strict to report it)", file, package);
+        }
+    }
+
+    drift.is_empty()
+}
+
+/// Packages which are imported but not installed have no version, so
+/// `merge` can't tell they're still used: don't report them as unused
+fn keep_imported(report: &mut MergeReport, unknown: &[String]) {
+    let unknown: Vec<String> = unknown.iter().map(|x| normalise_name(x)).collect();
+    report.unused.retain(|x| !unknown.contains(&normalise_name(x)));
+}
+
+/// Show the lines of a file which aren't valid requirements
+fn print_errors(file: &str, requirements: &RequirementsFile) {
+    for error in &requirements.errors {
+        println!("WARNING: {}: {}", file, error);
+    }
+}
+
+/// Show what changed while merging a file
+fn print_report(file: &str, report: &MergeReport) {
+    for requirement in &report.added {
+        println!("{}: added {}", file, requirement);
+    }
+
+    for requirement in &report.updated {
+        println!("{}: updated {}", file, requirement);
+    }
+
+    for (package, pinned, installed) in &report.not_downgraded {
+        println!(
+            "WARNING: {} requires {} {} but {} is installed, keeping it (use --force to downgrade)",
+            file, package, pinned, installed
+        );
+    }
+
+    for package in &report.unused {
+        println!("WARNING: {} lists `{}` which is no longer imported", file, package);
+    }
+}
+
+/// Find all third-party modules imported in each scanned folder
+pub fn find_dependencies() -> Dependencies {
+    // Get all python files in every scanned folder
+    let mut files: Vec<(PathBuf, Group)> = Vec::new();
+    let mut explorer = Explorer::new();
+
+    for (dir, group) in SCAN_DIRS.iter() {
+        explorer.explore(Path::new(dir), &is_source);
+
+        for file in explorer.results.drain(..) {
+            files.push((file, *group));
+        }
+    }
+
+    let local = local_names(&files);
+
+    let mut runtime: BTreeSet<String> = BTreeSet::new();
+    let mut dev: BTreeSet<String> = BTreeSet::new();
+    let mut diagnostics: Vec<Diagnostic> = explorer.errors;
+
+    // Files are parsed in parallel (unless cached), then merged in order
+    let paths: Vec<PathBuf> = files.iter().map(|(file, _)| file.clone()).collect();
+    let parsed = cache::cached_imports(&paths);
+
+    for ((_, group), imports) in files.iter().zip(parsed) {
+        let imports = match imports {
+            Ok(x) => x,
+            Err(e) => {
+                diagnostics.push(e);
+                continue;
+            }
+        };
+
+        for import in imports {
+            for module in import.modules {
+                if local.iter().any(|x| x == &module) || Parser::is_built_in(&module) {
+                    continue;
+                }
+
+                // Imports only used for type checking are never needed at runtime
+                match (group, import.type_checking) {
+                    (Group::Runtime, false) => {runtime.insert(module);},
+                    _ => {dev.insert(module);},
+                }
+            }
+        }
+    }
+
+    let dev = dev
+        .into_iter()
+        .filter(|x| !runtime.contains(x))
+        .collect();
+
+    Dependencies {
+        runtime: runtime.into_iter().collect(),
+        dev,
+        diagnostics,
+    }
+}
+
+/// Convert modules and their versions into sorted (package, version) pairs
+pub fn discovered(versions: &HashMap<String, String>) -> Vec<(String, String)> {
+    let mut discovered: Vec<(String, String)> = versions
+        .iter()
+        .map(|(module, version)| (module.clone(), version.clone()))
+        .collect();
+
+    discovered.sort_by_key(|(module, _)| module.to_lowercase());
+    discovered
+}
+
+/// Packages of modules which have no installed version
+fn not_installed(modules: &[String], versions: &HashMap<String, String>) -> Vec<String> {
+    modules
+        .iter()
+        .map(|x| Parser::convert_common_mods(x))
+        .filter(|x| !versions.contains_key(x))
+        .collect()
+}
+
+/// Check whether a path is a python file
+pub fn is_py(x: &Path) -> bool {
+    match x.extension() {
+        Some(ext) => ext == "py",
+        None => false,
+    }
+}
+
+/// Check whether a path is a python file or a Jupyter notebook,
+/// ignoring Jupyter's checkpoint copies
+pub fn is_source(x: &Path) -> bool {
+    let checkpoint = x.iter().any(|c| c == ".ipynb_checkpoints");
+
+    !checkpoint && (is_py(x) || Parser::is_notebook(x))
+}
+
+/// Names of every local file and folder, which can be imported
+/// without being installed
+fn local_names(files: &[(PathBuf, Group)]) -> Vec<String> {
+    let mut names: BTreeSet<String> = BTreeSet::new();
+
+    for (path, _) in files {
+        for component in path.parent().into_iter().flat_map(|x| x.iter()) {
+            names.insert(component.to_string_lossy().to_string());
+        }
+
+        if let Some(stem) = path.file_stem() {
+            names.insert(stem.to_string_lossy().to_string());
+        }
+    }
+
+    names.into_iter().collect()
+}
+
+fn write(file: &str, content: &str) -> Result<(), String> {
+    match fs::write(file, content) {
+        Ok(_) => {
+            println!("Successfully updated {}", file);
+            Ok(())
+        },
+        Err(e) => Err(format!("Error while writing to {}: {}", file, e)),
+    }
+}
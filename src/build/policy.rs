@@ -0,0 +1,148 @@
+//! Version specifier policies used when writing requirements
+
+use std::collections::HashMap;
+
+use crate::config::SetupCfg;
+use crate::package::name::normalise_name;
+use crate::package::requirement::Requirement;
+use crate::package::specifier::{Operator, Specifier, SpecifierSet};
+use crate::package::version::Version;
+
+/// setup.cfg section holding pyproj's settings
+pub const SECTION: &str = "tool:pyproj";
+/// setup.cfg section holding per-package policies
+pub const PACKAGE_SECTION: &str = "tool:pyproj.version_policy";
+
+/// How the installed version of a package is written as a requirement
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Policy {
+    /// `name==1.2.3`
+    Exact,
+    /// `name~=1.2.3`
+    Compatible,
+    /// `name>=1.2.3`
+    Minimum,
+    /// `name>=1.2.3,<2`
+    MajorBound,
+    /// `name`
+    None,
+}
+
+/// Names accepted for each policy
+pub const POLICIES: [(&str, Policy); 5] = [
+    ("exact", Policy::Exact),
+    ("compatible", Policy::Compatible),
+    ("minimum", Policy::Minimum),
+    ("major-bound", Policy::MajorBound),
+    ("none", Policy::None),
+];
+
+impl Policy {
+    pub fn from_name(name: &str) -> Option<Policy> {
+        POLICIES
+            .iter()
+            .find(|(x, _)| *x == name.trim())
+            .map(|(_, policy)| *policy)
+    }
+
+    /// Build the specifier for an installed version of a package
+    pub fn specifier(&self, version: &str) -> SpecifierSet {
+        let specifier = |operator: Operator, version: String| Specifier {
+            operator,
+            version,
+            wildcard: false,
+        };
+
+        let parsed = match Version::parse(version) {
+            Some(x) => x.public(),
+            // Versions pyproj can't reason about are pinned as they are
+            None => return SpecifierSet {
+                specifiers: match self {
+                    Policy::None => Vec::new(),
+                    _ => vec![specifier(Operator::Equal, version.to_string())],
+                },
+            },
+        };
+
+        let specifiers = match self {
+            Policy::Exact => vec![specifier(Operator::Equal, version.to_string())],
+            Policy::Compatible => {
+                // `~=` needs at least two release parts
+                let mut compatible = parsed;
+                if compatible.release.len() < 2 {
+                    compatible.release.push(0);
+                }
+                vec![specifier(Operator::Compatible, compatible.to_string())]
+            },
+            Policy::Minimum => vec![specifier(Operator::GreaterEqual, parsed.to_string())],
+            Policy::MajorBound => vec![
+                specifier(Operator::GreaterEqual, parsed.to_string()),
+                specifier(Operator::Less, (parsed.major() + 1).to_string()),
+            ],
+            Policy::None => Vec::new(),
+        };
+
+        SpecifierSet { specifiers }
+    }
+
+    /// Build the requirement for an installed version of a package
+    pub fn requirement(&self, package: &str, version: &str) -> Requirement {
+        let mut requirement = Requirement::new(package);
+        requirement.specifier = self.specifier(version);
+        requirement
+    }
+}
+
+/// The project's default policy and any per-package policies
+pub struct VersionPolicy {
+    pub default: Policy,
+    pub packages: HashMap<String, Policy>,
+}
+
+impl VersionPolicy {
+    /// Read the policies from setup.cfg
+    ///
+    /// ```ini
+    /// [tool:pyproj]
+    /// version_policy = compatible
+    ///
+    /// [tool:pyproj.version_policy]
+    /// numpy = major-bound
+    /// ```
+    pub fn from_config(cfg: &SetupCfg) -> Result<VersionPolicy, String> {
+        let default = match cfg.get(SECTION, "version_policy") {
+            Some(name) => Policy::from_name(&name)
+                .ok_or_else(|| format!("Unknown version_policy '{}' in [{}]", name, SECTION))?,
+            None => Policy::Exact,
+        };
+
+        let mut packages: HashMap<String, Policy> = HashMap::new();
+
+        for package in cfg.keys(PACKAGE_SECTION) {
+            let name = cfg.get(PACKAGE_SECTION, &package).unwrap_or_default();
+            let policy = Policy::from_name(&name)
+                .ok_or_else(|| format!("Unknown version policy '{}' for {} in [{}]", name, package, PACKAGE_SECTION))?;
+
+            packages.insert(normalise_name(&package), policy);
+        }
+
+        Ok(VersionPolicy { default, packages })
+    }
+
+    /// Get the policy of a package
+    pub fn for_package(&self, package: &str) -> Policy {
+        match self.packages.get(&normalise_name(package)) {
+            Some(policy) => *policy,
+            None => self.default,
+        }
+    }
+}
+
+impl Default for VersionPolicy {
+    fn default() -> VersionPolicy {
+        VersionPolicy {
+            default: Policy::Exact,
+            packages: HashMap::new(),
+        }
+    }
+}
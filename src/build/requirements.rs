@@ -0,0 +1,374 @@
+//! Read, merge and write requirements files while keeping
+//! the user's own lines (comments, includes, options, pins)
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::package::name::normalise_name;
+use crate::package::requirement::Requirement;
+use crate::package::specifier::Operator;
+use crate::package::version::Version;
+
+use super::policy::{Policy, VersionPolicy};
+
+/// A single (logical) line of a requirements file
+pub enum Line {
+    /// A package requirement
+    Requirement {
+        requirement: Requirement,
+        /// Version of requirements written in one of the forms pyproj
+        /// writes (`==1.2`, `~=1.2`, `>=1.2`, `>=1.2,<2`)
+        version: Option<String>,
+        /// The line as it was written
+        raw: String,
+    },
+    /// Anything else (comments, blank lines, `-r`/`-c` includes, options)
+    Other(String),
+}
+
+/// Changes made while merging discovered dependencies into a file
+#[derive(Default)]
+pub struct MergeReport {
+    /// Requirements added to the file
+    pub added: Vec<String>,
+    /// Requirements changed to the installed version or policy
+    pub updated: Vec<String>,
+    /// Requirements kept because the installed version is older (name, written, installed)
+    pub not_downgraded: Vec<(String, String, String)>,
+    /// Requirements in the file which are no longer imported
+    pub unused: Vec<String>,
+}
+
+/// A requirements file
+pub struct RequirementsFile {
+    pub lines: Vec<Line>,
+    /// Lines which aren't valid requirements (kept as they are)
+    pub errors: Vec<String>,
+}
+
+impl RequirementsFile {
+    /// Load a requirements file, or an empty one if it doesn't exist
+    pub fn load(path: &Path) -> io::Result<RequirementsFile> {
+        match fs::read_to_string(path) {
+            Ok(content) => Ok(RequirementsFile::parse(&content)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(RequirementsFile::parse("")),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Parse the contents of a requirements file
+    pub fn parse(content: &str) -> RequirementsFile {
+        let mut file = RequirementsFile {
+            lines: Vec::new(),
+            errors: Vec::new(),
+        };
+        let mut iter = content.lines();
+
+        while let Some(line) = iter.next() {
+            // Join lines continued with a backslash
+            let mut raw = line.to_string();
+            while raw.ends_with('\\') {
+                match iter.next() {
+                    Some(next) => {
+                        raw.push('\n');
+                        raw.push_str(next);
+                    },
+                    None => break,
+                }
+            }
+
+            let line = file.parse_line(raw);
+            file.lines.push(line);
+        }
+
+        file
+    }
+
+    /// Parse a single logical line
+    fn parse_line(&mut self, raw: String) -> Line {
+        let code = strip_comment(&raw.replace("\\\n", " ")).trim().to_string();
+
+        if code.is_empty() || code.starts_with('-') {
+            return Line::Other(raw);
+        }
+
+        // VCS, URL or local path requirement (`git+https://...#egg=name`)
+        let first = code.split_whitespace().next().unwrap_or("");
+        let link = first.contains("://")
+            || first.starts_with(['.', '/'])
+            || ["git+", "hg+", "svn+", "bzr+"].iter().any(|x| first.starts_with(x));
+
+        if link {
+            let name = code
+                .split("#egg=")
+                .nth(1)
+                .map(|x| x.split(['&', ' ']).next().unwrap_or(""))
+                .unwrap_or("");
+
+            if name.is_empty() {
+                return Line::Other(raw);
+            }
+
+            let mut requirement = Requirement::new(name);
+            requirement.url = Some(first.to_string());
+
+            return Line::Requirement {
+                requirement,
+                version: None,
+                raw,
+            };
+        }
+
+        match Requirement::parse(&code) {
+            Ok(requirement) => Line::Requirement {
+                version: managed_version(&requirement),
+                requirement,
+                raw,
+            },
+            Err(e) => {
+                self.errors.push(e);
+
+                // Keep the package's name so it isn't added again
+                let end = code
+                    .find(|c: char| !(c.is_alphanumeric() || c == '-' || c == '_' || c == '.'))
+                    .unwrap_or(code.len());
+
+                match end {
+                    0 => Line::Other(raw),
+                    _ => Line::Requirement {
+                        requirement: Requirement::new(&code[..end]),
+                        version: None,
+                        raw,
+                    },
+                }
+            }
+        }
+    }
+
+    /// Names of every requirement in the file
+    pub fn names(&self) -> Vec<String> {
+        self.requirements().iter().map(|x| x.key()).collect()
+    }
+
+    /// Every requirement in the file
+    pub fn requirements(&self) -> Vec<&Requirement> {
+        self.lines
+            .iter()
+            .filter_map(|x| match x {
+                Line::Requirement { requirement, .. } => Some(requirement),
+                Line::Other(_) => None,
+            })
+            .collect()
+    }
+
+    /// Merge discovered `(package, installed version)` pairs into the file
+    ///
+    /// New packages are added at the end. Requirements exactly as the
+    /// package's policy writes them, or pinned with `==`, are moved to the
+    /// installed version unless that would downgrade them (allowed with
+    /// `force`), and every other line is kept as it was written.
+    pub fn merge(&mut self, discovered: &[(String, String)], policy: &VersionPolicy, force: bool) -> MergeReport {
+        let mut report = MergeReport::default();
+
+        for line in self.lines.iter_mut() {
+            let (requirement, written, raw) = match line {
+                Line::Requirement { requirement, version, raw } => (requirement, version, raw),
+                Line::Other(_) => continue,
+            };
+
+            let installed = discovered
+                .iter()
+                .find(|(package, _)| normalise_name(package) == requirement.key());
+
+            let (package, version) = match installed {
+                Some(x) => x,
+                None => {
+                    report.unused.push(requirement.key());
+                    continue;
+                }
+            };
+
+            let old = match written {
+                Some(old) => old.clone(),
+                None => continue,
+            };
+
+            // Ranges written by hand are kept, only lines matching what the
+            // package's policy writes (or bare pins) are moved
+            let policy = policy.for_package(package);
+            if !is_pin(requirement) && !written_by(requirement, policy, &old) {
+                continue;
+            }
+
+            // Only the version changes, the name, extras and marker are kept
+            let mut updated = requirement.clone();
+            updated.specifier = policy.specifier(version);
+
+            if updated.normalised().to_string() == requirement.normalised().to_string() {
+                continue;
+            }
+
+            let downgrade = match (Version::parse(version), Version::parse(&old)) {
+                (Some(new), Some(old)) => new < old,
+                _ => false,
+            };
+
+            if downgrade && !force {
+                report.not_downgraded.push((package.clone(), old, version.clone()));
+                continue;
+            }
+
+            // Keep any comment on the line
+            let comment = comment_of(raw).map(|x| format!("  {}", x)).unwrap_or_default();
+            *raw = format!("{}{}", updated, comment);
+            *written = managed_version(&updated);
+            report.updated.push(updated.to_string());
+            *requirement = updated;
+        }
+
+        let existing = self.names();
+
+        for (package, version) in discovered {
+            if existing.contains(&normalise_name(package)) {
+                continue;
+            }
+
+            let requirement = policy.for_package(package).requirement(package, version).to_string();
+            let line = self.parse_line(requirement.clone());
+            self.lines.push(line);
+            report.added.push(requirement);
+        }
+
+        report
+    }
+
+    /// Build the file's contents
+    pub fn content(&self) -> String {
+        let mut content = String::new();
+
+        for line in &self.lines {
+            match line {
+                Line::Requirement { raw, .. } | Line::Other(raw) => content.push_str(raw),
+            }
+            content.push('\n');
+        }
+
+        content
+    }
+}
+
+/// Get the version of a requirement if it is in one of the
+/// forms pyproj writes (`==1.2`, `~=1.2`, `>=1.2`, `>=1.2,<2`)
+fn managed_version(requirement: &Requirement) -> Option<String> {
+    if requirement.url.is_some() {
+        return None;
+    }
+
+    let specifiers = &requirement.specifier.specifiers;
+    let single = |operators: &[Operator]| {
+        specifiers
+            .iter()
+            .find(|x| operators.contains(&x.operator) && !x.wildcard)
+            .map(|x| x.version.clone())
+    };
+
+    match specifiers.len() {
+        1 => single(&[Operator::Equal, Operator::Compatible, Operator::GreaterEqual]),
+        2 => {
+            // `>=1.2,<2`, with a major version as the upper bound
+            let bound = specifiers
+                .iter()
+                .any(|x| x.operator == Operator::Less && x.version.chars().all(|c| c.is_ascii_digit()));
+
+            match bound {
+                true => single(&[Operator::GreaterEqual]),
+                false => None,
+            }
+        },
+        _ => None,
+    }
+}
+
+/// Whether a requirement is a bare `==` pin
+fn is_pin(requirement: &Requirement) -> bool {
+    match requirement.specifier.specifiers.as_slice() {
+        [x] => x.operator == Operator::Equal && !x.wildcard,
+        _ => false,
+    }
+}
+
+/// Whether a requirement's specifier is exactly what a policy writes for
+/// the version it names
+fn written_by(requirement: &Requirement, policy: Policy, version: &str) -> bool {
+    let mut expected = requirement.clone();
+    expected.specifier = policy.specifier(version);
+
+    expected.normalised().to_string() == requirement.normalised().to_string()
+}
+
+/// Remove a trailing ` # comment` from a line
+fn strip_comment(line: &str) -> &str {
+    match comment_start(line) {
+        Some(at) => &line[..at],
+        None => line,
+    }
+}
+
+/// Get the trailing ` # comment` of a line
+fn comment_of(line: &str) -> Option<&str> {
+    comment_start(line).map(|at| line[at..].trim())
+}
+
+/// Comments start at a `#` at the start of the line or after whitespace
+fn comment_start(line: &str) -> Option<usize> {
+    line.char_indices()
+        .find(|&(i, c)| c == '#' && (i == 0 || line[..i].ends_with([' ', '\t'])))
+        .map(|(i, _)| i)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn installed() -> Vec<(String, String)> {
+        vec![
+            (String::from("requests"), String::from("2.31.0")),
+            (String::from("numpy"), String::from("1.26.4")),
+            (String::from("six"), String::from("1.16.0")),
+            (String::from("attrs"), String::from("23.1.0")),
+        ]
+    }
+
+    #[test]
+    fn merge_keeps_hand_written_ranges() {
+        let mut file = RequirementsFile::parse("requests>=2.0  # user range\nnumpy~=1.20\nsix==1.0  # pinned\n");
+        let report = file.merge(&installed(), &VersionPolicy::default(), false);
+
+        assert_eq!(
+            file.content(),
+            "requests>=2.0  # user range\nnumpy~=1.20\nsix==1.16.0  # pinned\nattrs==23.1.0\n"
+        );
+        assert_eq!(report.updated, vec![String::from("six==1.16.0")]);
+        assert_eq!(report.added, vec![String::from("attrs==23.1.0")]);
+    }
+
+    #[test]
+    fn merge_moves_lines_written_by_the_policy() {
+        let policy = VersionPolicy { default: Policy::Compatible, packages: Default::default() };
+
+        let mut file = RequirementsFile::parse("numpy~=1.20\nrequests>=2.0\n");
+        file.merge(&installed(), &policy, false);
+
+        assert_eq!(file.content(), "numpy~=1.26.4\nrequests>=2.0\nsix~=1.16.0\nattrs~=23.1.0\n");
+    }
+
+    #[test]
+    fn merge_does_not_downgrade_pins() {
+        let mut file = RequirementsFile::parse("six==2.0\n");
+        let report = file.merge(&installed()[2..3], &VersionPolicy::default(), false);
+
+        assert_eq!(file.content(), "six==2.0\n");
+        assert_eq!(report.not_downgraded.len(), 1);
+    }
+}
@@ -0,0 +1,342 @@
+//! Cache the imports found in each file, so unchanged files aren't
+//! parsed again
+
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use serde_json::{json, Value};
+use sha1::{Digest, Sha1};
+
+use crate::digest::hex;
+use crate::interpreter::source::Diagnostic;
+use crate::interpreter::{Import, Parser};
+use crate::pool;
+
+/// Folder (from the project root) holding the cache
+pub const CACHE_DIR: &str = ".pyproj/cache";
+
+/// File holding the imports of every file
+const IMPORTS_FILE: &str = "imports";
+
+/// First line of the cache file, followed by pyproj's version
+const HEADER: &str = "pyproj-import-cache";
+
+/// Version of the parser's results, bumped whenever the imports found in
+/// a file change so entries cached by older builds are thrown away
+const PARSER_VERSION: u32 = 2;
+
+/// What parsing a file found
+type Parsed = Result<Vec<Import>, Diagnostic>;
+
+/// Imports found in the project's files by previous runs
+pub struct ImportCache {
+    entries: HashMap<String, Entry>,
+    /// Whether anything changed since it was loaded
+    changed: bool,
+}
+
+/// A file as it was when it was parsed
+#[derive(Clone)]
+struct Entry {
+    /// Modification time (nanoseconds since the epoch)
+    modified: u64,
+    size: u64,
+    /// SHA-1 of the content
+    hash: String,
+    parsed: Parsed,
+}
+
+impl ImportCache {
+    /// Load the cache, starting afresh if it doesn't exist or was written
+    /// by another version of pyproj
+    pub fn load() -> ImportCache {
+        let entries = fs::read_to_string(Path::new(CACHE_DIR).join(IMPORTS_FILE))
+            .ok()
+            .and_then(|x| parse(&x))
+            .unwrap_or_default();
+
+        ImportCache { entries, changed: false }
+    }
+
+    /// Find the imports of every file (in parallel), only parsing the
+    /// files which changed since they were cached
+    ///
+    /// Files which no longer exist are dropped from the cache.
+    pub fn imports(&mut self, files: &[PathBuf]) -> Vec<Parsed> {
+        let entries = pool::map(files, |file| self.entry(file));
+
+        let count = self.entries.len();
+        self.entries.retain(|path, _| Path::new(path).is_file());
+        self.changed |= self.entries.len() != count;
+
+        let mut parsed: Vec<Parsed> = Vec::new();
+
+        for (file, entry) in files.iter().zip(entries) {
+            match entry {
+                Some((entry, false)) => parsed.push(entry.parsed),
+                Some((entry, true)) => {
+                    parsed.push(entry.parsed.clone());
+                    self.entries.insert(key(file), entry);
+                    self.changed = true;
+                },
+                // Files which couldn't be read aren't cached
+                None => parsed.push(Parser::new(file).imports()),
+            }
+        }
+
+        parsed
+    }
+
+    /// The cached entry of a file, parsing it if it changed, and whether
+    /// the entry needs to be updated
+    fn entry(&self, file: &Path) -> Option<(Entry, bool)> {
+        let metadata = fs::metadata(file).ok()?;
+        let modified = metadata
+            .modified()
+            .ok()
+            .and_then(|x| x.duration_since(UNIX_EPOCH).ok())
+            .map(|x| x.as_nanos() as u64)
+            .unwrap_or(0);
+        let size = metadata.len();
+
+        let cached = self.entries.get(&key(file));
+
+        if let Some(x) = cached {
+            if x.modified == modified && x.size == size {
+                return Some((x.clone(), false));
+            }
+        }
+
+        // The file was touched, but may not have changed
+        let hash = hex(&Sha1::digest(fs::read(file).ok()?));
+
+        match cached {
+            Some(x) if x.hash == hash => Some((Entry { modified, size, ..x.clone() }, true)),
+            _ => Some((Entry { modified, size, hash, parsed: Parser::new(file).imports() }, true)),
+        }
+    }
+
+    /// Write the cache to disk, if anything changed
+    pub fn save(&self) -> io::Result<()> {
+        if !self.changed {
+            return Ok(());
+        }
+
+        let content = serialise(&self.entries);
+
+        fs::create_dir_all(CACHE_DIR)?;
+
+        // Write then rename, so an interrupted run can't leave half a cache
+        let path = Path::new(CACHE_DIR).join(IMPORTS_FILE);
+        let temporary = path.with_extension("tmp");
+        fs::write(&temporary, content)?;
+        fs::rename(&temporary, &path)
+    }
+}
+
+/// Find the imports of every file through the cache, saving it afterwards
+pub fn cached_imports(files: &[PathBuf]) -> Vec<Parsed> {
+    let mut cache = ImportCache::load();
+    let parsed = cache.imports(files);
+
+    if let Err(e) = cache.save() {
+        eprintln!("WARNING: Couldn't write the cache to {}: {}", CACHE_DIR, e);
+    }
+
+    parsed
+}
+
+/// Remove the cache, returning its size in bytes (0 if there wasn't one)
+pub fn clear() -> io::Result<u64> {
+    let dir = Path::new(CACHE_DIR);
+
+    if !dir.exists() {
+        return Ok(0);
+    }
+
+    let size = fs::read_dir(dir)?
+        .filter_map(|x| x.ok())
+        .filter_map(|x| x.metadata().ok())
+        .map(|x| x.len())
+        .sum();
+
+    fs::remove_dir_all(dir)?;
+    Ok(size)
+}
+
+/// Key of a file in the cache, its path separated by `/`
+fn key(file: &Path) -> String {
+    file.to_string_lossy().replace('\\', "/")
+}
+
+/// Content of the cache file: the header, then every entry sorted by path
+fn serialise(entries: &HashMap<String, Entry>) -> String {
+    let mut paths: Vec<&String> = entries.keys().collect();
+    paths.sort();
+
+    let mut content = format!("{} {}.{}\n", HEADER, env!("CARGO_PKG_VERSION"), PARSER_VERSION);
+    for path in paths {
+        write_entry(&mut content, path, &entries[path]);
+    }
+
+    content
+}
+
+/// Write an entry as one line of JSON
+///
+/// ```text
+/// {"path": ..., "modified": ..., "size": ..., "hash": ..., "imports": [...]}
+/// {"path": ..., "modified": ..., "size": ..., "hash": ..., "error": {"line": ..., "message": ...}}
+/// ```
+fn write_entry(content: &mut String, path: &str, entry: &Entry) {
+    let mut value = json!({
+        "path": path,
+        "modified": entry.modified,
+        "size": entry.size,
+        "hash": entry.hash,
+    });
+
+    match &entry.parsed {
+        Ok(imports) => {
+            value["imports"] = imports
+                .iter()
+                .map(|x| json!({
+                    "line": x.line,
+                    "level": x.level,
+                    "type_checking": x.type_checking,
+                    "deferred": x.deferred,
+                    "modules": x.modules,
+                    "paths": x.paths,
+                    "names": x.names,
+                }))
+                .collect();
+        },
+        Err(e) => value["error"] = json!({ "line": e.line, "message": e.message }),
+    }
+
+    // serde_json escapes newlines, so an entry always fits on a line
+    content.push_str(&value.to_string());
+    content.push('\n');
+}
+
+/// Parse a cache file, or None if it's from another version of pyproj
+fn parse(content: &str) -> Option<HashMap<String, Entry>> {
+    let mut lines = content.lines();
+
+    if lines.next()? != format!("{} {}.{}", HEADER, env!("CARGO_PKG_VERSION"), PARSER_VERSION) {
+        return None;
+    }
+
+    let list = |x: &Value| -> Option<Vec<String>> {
+        x.as_array()?.iter().map(|x| x.as_str().map(|x| x.to_string())).collect()
+    };
+
+    let mut entries: HashMap<String, Entry> = HashMap::new();
+
+    // A corrupt cache is thrown away
+    for line in lines {
+        let value: Value = serde_json::from_str(line).ok()?;
+        let path = value["path"].as_str()?.to_string();
+
+        let parsed = match value.get("error") {
+            Some(error) => Err(Diagnostic {
+                path: PathBuf::from(&path),
+                line: error["line"].as_u64().map(|x| x as usize),
+                message: error["message"].as_str()?.to_string(),
+            }),
+            None => value["imports"]
+                .as_array()?
+                .iter()
+                .map(|x| Some(Import {
+                    line: x["line"].as_u64()? as usize,
+                    level: x["level"].as_u64()? as usize,
+                    type_checking: x["type_checking"].as_bool()?,
+                    deferred: x["deferred"].as_bool()?,
+                    modules: list(&x["modules"])?,
+                    paths: list(&x["paths"])?,
+                    names: list(&x["names"])?,
+                }))
+                .collect::<Option<Vec<Import>>>()
+                .map(Ok)?,
+        };
+
+        entries.insert(path, Entry {
+            modified: value["modified"].as_u64()?,
+            size: value["size"].as_u64()?,
+            hash: value["hash"].as_str()?.to_string(),
+            parsed,
+        });
+    }
+
+    Some(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn import(paths: &[&str], names: &[&str], level: usize) -> Import {
+        Import {
+            modules: Vec::new(),
+            paths: paths.iter().map(|x| x.to_string()).collect(),
+            names: names.iter().map(|x| x.to_string()).collect(),
+            level,
+            line: 3,
+            type_checking: true,
+            deferred: false,
+        }
+    }
+
+    #[test]
+    fn round_trip_keeps_relative_imports() {
+        let mut entries: HashMap<String, Entry> = HashMap::new();
+        entries.insert(String::from("src/pkg/a.py"), Entry {
+            modified: 12,
+            size: 34,
+            hash: String::from("abc"),
+            // `from . import b`, `from .. import c, d` and `import x.y`
+            parsed: Ok(vec![import(&[""], &["b"], 1), import(&[""], &["c", "d"], 2), import(&["x.y"], &[], 0)]),
+        });
+        entries.insert(String::from("src/pkg/tab\tnew\nline.py"), Entry {
+            modified: 1,
+            size: 2,
+            hash: String::from("def"),
+            parsed: Err(Diagnostic {
+                path: PathBuf::from("src/pkg/tab\tnew\nline.py"),
+                line: Some(4),
+                message: String::from("invalid\tbyte\n"),
+            }),
+        });
+
+        let parsed = parse(&serialise(&entries)).expect("the cache is read back");
+        assert_eq!(parsed.len(), 2);
+
+        let entry = &parsed["src/pkg/a.py"];
+        assert_eq!((entry.modified, entry.size, entry.hash.as_str()), (12, 34, "abc"));
+
+        let imports = entry.parsed.as_ref().ok().unwrap();
+        let original = entries["src/pkg/a.py"].parsed.as_ref().ok().unwrap();
+        assert_eq!(imports.len(), original.len());
+
+        for (read, written) in imports.iter().zip(original) {
+            assert_eq!(read.paths, written.paths);
+            assert_eq!(read.names, written.names);
+            assert_eq!(read.modules, written.modules);
+            assert_eq!((read.level, read.line), (written.level, written.line));
+            assert_eq!((read.type_checking, read.deferred), (written.type_checking, written.deferred));
+        }
+
+        let error = parsed["src/pkg/tab\tnew\nline.py"].parsed.as_ref().err().unwrap();
+        assert_eq!(error.line, Some(4));
+        assert_eq!(error.message, "invalid\tbyte\n");
+    }
+
+    #[test]
+    fn other_versions_are_ignored() {
+        assert!(parse("pyproj-import-cache 0.0.0\n").is_none());
+        assert!(parse(&format!("{} {}.{}\nnot json\n", HEADER, env!("CARGO_PKG_VERSION"), PARSER_VERSION)).is_none());
+    }
+}
@@ -0,0 +1,57 @@
+//! Check a project for problems
+
+pub mod python;
+
+use std::path::Path;
+
+use crate::graph::{cycles, ModuleGraph};
+
+/// Report circular imports between the project's modules
+///
+/// Returns false if a module-level cycle was found.
+pub fn check_cycles() -> bool {
+    let module_graph = ModuleGraph::build(Path::new("src")).without_third_party();
+    let cycles = cycles::find_cycles(&module_graph);
+
+    for diagnostic in &module_graph.diagnostics {
+        println!("WARNING: Skipped {}", diagnostic);
+    }
+
+    if cycles.is_empty() {
+        println!("No circular imports found");
+        return true;
+    }
+
+    let unsafe_cycles = cycles.iter().filter(|x| !x.safe).count();
+    println!(
+        "Found {} import cycle(s), {} at module level\n",
+        cycles.len(),
+        unsafe_cycles
+    );
+
+    for (i, cycle) in cycles.iter().enumerate() {
+        let kind = match cycle.safe {
+            true => "deferred only, usually safe",
+            false => "module level",
+        };
+        println!("Cycle {}: {} [{}]", i + 1, cycle.modules.join(", "), kind);
+
+        for edge in &cycle.edges {
+            let note = if edge.type_checking {
+                " (type checking only)"
+            } else if edge.deferred {
+                " (deferred, inside a function)"
+            } else {
+                ""
+            };
+
+            println!(
+                "\t{}:{}\t{} -> {}{}",
+                edge.file.display(), edge.line, edge.from, edge.to, note
+            );
+        }
+        println!();
+    }
+
+    unsafe_cycles == 0
+}
@@ -0,0 +1,323 @@
+//! Infer the minimum Python version a project needs and check it
+//! against setup.cfg's `python_requires` and classifiers
+
+use std::path::{Path, PathBuf};
+
+use crate::build::{is_source, SETUP_CFG};
+use crate::config::SetupCfg;
+use crate::explorer::Explorer;
+use crate::interpreter::Parser;
+use crate::interpreter::syntax::Feature;
+use crate::package::specifier::{Operator, SpecifierSet};
+use crate::pool;
+
+/// Python versions pyproj knows about, oldest first
+pub const PYTHON_VERSIONS: [(u32, u32); 9] = [
+    (3, 6), (3, 7), (3, 8), (3, 9), (3, 10), (3, 11), (3, 12), (3, 13), (3, 14),
+];
+
+/// Prefix of version classifiers
+const CLASSIFIER: &str = "Programming Language :: Python :: ";
+
+/// Find the features used in the project's source which need
+/// a minimum version of Python (skipping files which can't be read)
+pub fn find_features() -> Vec<(PathBuf, Feature)> {
+    let mut explorer = Explorer::new();
+    explorer.explore(Path::new("src"), &is_source);
+
+    for e in &explorer.errors {
+        println!("WARNING: Skipped {}", e);
+    }
+
+    let mut features: Vec<(PathBuf, Feature)> = Vec::new();
+
+    let parsed = pool::map(&explorer.results, |path| Parser::new(path).features());
+
+    for (path, parsed) in explorer.results.iter().zip(parsed) {
+        match parsed {
+            Ok(found) => {
+                for feature in found {
+                    features.push((path.clone(), feature));
+                }
+            },
+            Err(e) => println!("WARNING: Skipped {}", e),
+        }
+    }
+
+    features
+}
+
+/// Get the minimum Python version needed by a set of features
+pub fn minimum_version(features: &[(PathBuf, Feature)]) -> (u32, u32) {
+    features
+        .iter()
+        .map(|(_, x)| x.version)
+        .max()
+        .unwrap_or(PYTHON_VERSIONS[0])
+        .max(PYTHON_VERSIONS[0])
+}
+
+/// Infer the minimum Python version and verify (or write) setup.cfg,
+/// creating it if it doesn't exist when writing
+///
+/// Returns false if setup.cfg doesn't match the inferred version.
+pub fn check_python(write: bool) -> bool {
+    let features = find_features();
+    let minimum = minimum_version(&features);
+
+    println!("Minimum Python version: {}", format_version(minimum));
+
+    // Show the first use of each feature needing more than the oldest version
+    let mut shown: Vec<&str> = Vec::new();
+    let mut needed: Vec<&(PathBuf, Feature)> = features
+        .iter()
+        .filter(|(_, x)| x.version > PYTHON_VERSIONS[0])
+        .collect();
+    needed.sort_by_key(|x| std::cmp::Reverse(x.1.version));
+
+    for (file, feature) in needed {
+        if shown.contains(&feature.name.as_str()) {
+            continue;
+        }
+        shown.push(&feature.name);
+
+        println!(
+            "\t{}:{}\t{} ({})",
+            file.display(), feature.line, feature.name, format_version(feature.version)
+        );
+    }
+    println!();
+
+    check_setup_cfg(Path::new(SETUP_CFG), minimum, write)
+}
+
+/// Verify (or write) a setup.cfg's python_requires and classifiers against
+/// the minimum Python version, creating the file if it doesn't exist when writing
+fn check_setup_cfg(setup_cfg: &Path, minimum: (u32, u32), write: bool) -> bool {
+    let exists = setup_cfg.exists();
+
+    if !exists && !write {
+        println!("ERROR: {} doesn't exist\n\nRun `pyproj check python --write` to create it", SETUP_CFG);
+        return false;
+    }
+
+    let mut cfg = match exists {
+        true => match SetupCfg::load(setup_cfg) {
+            Ok(x) => x,
+            Err(e) => {
+                println!("Error while reading {}: {}", SETUP_CFG, e);
+                return false;
+            }
+        },
+        false => SetupCfg::parse(""),
+    };
+
+    if write {
+        write_version(&mut cfg, minimum);
+
+        return match cfg.save(setup_cfg) {
+            Ok(_) => {
+                match exists {
+                    true => println!("Successfully updated python_requires and classifiers in {}", SETUP_CFG),
+                    false => println!("Successfully created {} with python_requires and classifiers", SETUP_CFG),
+                };
+                true
+            },
+            Err(e) => {
+                println!("Error while writing to {}: {}", SETUP_CFG, e);
+                false
+            }
+        };
+    }
+
+    let problems = verify_version(&cfg, minimum);
+
+    for problem in &problems {
+        println!("ERROR: {}", problem);
+    }
+
+    match problems.is_empty() {
+        true => println!("{} matches the code", SETUP_CFG),
+        false => println!("\nRun `pyproj check python --write` to update {}", SETUP_CFG),
+    };
+
+    problems.is_empty()
+}
+
+/// Find problems with setup.cfg's declared Python versions
+fn verify_version(cfg: &SetupCfg, minimum: (u32, u32)) -> Vec<String> {
+    let mut problems: Vec<String> = Vec::new();
+
+    match cfg.get("options", "python_requires") {
+        Some(requires) => match lower_bound(&requires) {
+            Some(declared) if declared < minimum => problems.push(format!(
+                "python_requires `{}` allows Python {} but the code needs {}",
+                requires, format_version(declared), format_version(minimum)
+            )),
+            Some(_) => {},
+            None => problems.push(format!(
+                "python_requires `{}` has no lower bound (the code needs {})",
+                requires, format_version(minimum)
+            )),
+        },
+        None => problems.push(format!(
+            "python_requires is missing (the code needs {})",
+            format_version(minimum)
+        )),
+    };
+
+    for classifier in cfg.get_list("metadata", "classifiers") {
+        // Major-only classifiers (`Python :: 3`) are always fine
+        let version = match classifier.strip_prefix(CLASSIFIER) {
+            Some(x) if x.contains('.') => x,
+            _ => continue,
+        };
+
+        match parse_version(version) {
+            Some(v) if v < minimum => problems.push(format!(
+                "classifier `{}` lists a version the code doesn't support",
+                classifier
+            )),
+            Some(_) if version.split('.').count() > 2 => problems.push(format!(
+                "classifier `{}` should only contain the major and minor version",
+                classifier
+            )),
+            _ => {},
+        }
+    }
+
+    problems
+}
+
+/// Write python_requires and version classifiers to setup.cfg
+fn write_version(cfg: &mut SetupCfg, minimum: (u32, u32)) {
+    cfg.set("options", "python_requires", &format!(">={}", format_version(minimum)));
+
+    // Replace every version classifier, keeping the others
+    let mut classifiers: Vec<String> = cfg
+        .get_list("metadata", "classifiers")
+        .into_iter()
+        .filter(|x| !x.starts_with(CLASSIFIER) || parse_version(&x[CLASSIFIER.len()..]).is_none())
+        .collect();
+
+    classifiers.push(format!("{}3", CLASSIFIER));
+    for version in PYTHON_VERSIONS.iter().filter(|&&x| x >= minimum) {
+        classifiers.push(format!("{}{}", CLASSIFIER, format_version(*version)));
+    }
+
+    cfg.set_list("metadata", "classifiers", &classifiers);
+}
+
+/// Get the lowest version allowed by a python_requires specifier
+fn lower_bound(requires: &str) -> Option<(u32, u32)> {
+    SpecifierSet::parse(requires)?
+        .specifiers
+        .iter()
+        .filter(|x| matches!(
+            x.operator,
+            Operator::GreaterEqual | Operator::Greater | Operator::Compatible | Operator::Equal
+        ))
+        .filter_map(|x| parse_version(&x.version))
+        .max()
+}
+
+/// Parse the major and minor parts of a version (3.8, 3.10.2)
+pub fn parse_version(version: &str) -> Option<(u32, u32)> {
+    let mut parts = version.trim().split('.');
+
+    let major = parts.next()?.parse().ok()?;
+    let minor = parts.next().unwrap_or("0").parse().ok()?;
+
+    Some((major, minor))
+}
+
+pub fn format_version(version: (u32, u32)) -> String {
+    format!("{}.{}", version.0, version.1)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    /// Path to a setup.cfg in a fresh temporary folder
+    fn temp_setup_cfg(name: &str, content: Option<&str>) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("pyproj-check-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+
+        let path = dir.join(SETUP_CFG);
+        if let Some(content) = content {
+            fs::write(&path, content).unwrap();
+        }
+        path
+    }
+
+    #[test]
+    fn verify_finds_old_versions() {
+        let content = "[metadata]\nclassifiers =\n    Programming Language :: Python :: 3.6\n    Programming Language :: Python :: 3.8\n\n[options]\npython_requires = >=3.6\n";
+        let path = temp_setup_cfg("verify-old", Some(content));
+
+        assert!(!check_setup_cfg(&path, (3, 8), false));
+        assert_eq!(fs::read_to_string(&path).unwrap(), content);
+
+        let problems = verify_version(&SetupCfg::parse(content), (3, 8));
+        assert_eq!(problems.len(), 2);
+        assert!(problems[0].contains("allows Python 3.6"));
+        assert!(problems[1].contains("3.6"));
+
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn verify_accepts_matching_versions() {
+        let cfg = SetupCfg::parse("[metadata]\nclassifiers =\n    Programming Language :: Python :: 3\n    Programming Language :: Python :: 3.9\n\n[options]\npython_requires = >=3.9, <4\n");
+
+        assert!(verify_version(&cfg, (3, 8)).is_empty());
+        assert!(verify_version(&cfg, (3, 9)).is_empty());
+        assert_eq!(verify_version(&cfg, (3, 10)).len(), 2);
+
+        let missing = verify_version(&SetupCfg::parse("[options]\npython_requires = <4\n"), (3, 8));
+        assert_eq!(missing, ["python_requires `<4` has no lower bound (the code needs 3.8)"]);
+    }
+
+    #[test]
+    fn write_replaces_version_classifiers() {
+        let content = "[metadata]\nname = demo\nclassifiers =\n    License :: OSI Approved :: MIT License\n    Programming Language :: Python :: 3.6\n\n[options]\npython_requires = >=3.6\n";
+        let path = temp_setup_cfg("write", Some(content));
+
+        assert!(check_setup_cfg(&path, (3, 12), true));
+
+        let cfg = SetupCfg::load(&path).unwrap();
+        assert_eq!(cfg.get("metadata", "name").as_deref(), Some("demo"));
+        assert_eq!(cfg.get("options", "python_requires").as_deref(), Some(">=3.12"));
+        assert_eq!(cfg.get_list("metadata", "classifiers"), [
+            "License :: OSI Approved :: MIT License",
+            "Programming Language :: Python :: 3",
+            "Programming Language :: Python :: 3.12",
+            "Programming Language :: Python :: 3.13",
+            "Programming Language :: Python :: 3.14",
+        ]);
+        assert!(check_setup_cfg(&path, (3, 12), false));
+
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn write_creates_a_missing_setup_cfg() {
+        let path = temp_setup_cfg("create", None);
+
+        assert!(!check_setup_cfg(&path, (3, 8), false));
+        assert!(!path.exists());
+
+        assert!(check_setup_cfg(&path, (3, 8), true));
+        assert!(check_setup_cfg(&path, (3, 8), false));
+        assert_eq!(
+            SetupCfg::load(&path).unwrap().get("options", "python_requires").as_deref(),
+            Some(">=3.8")
+        );
+
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+}
@@ -0,0 +1,418 @@
+//! Remove caches and build artifacts from the project
+
+pub mod safety;
+pub mod trash;
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::build::SETUP_CFG;
+use crate::config::SetupCfg;
+use crate::explorer::ignore::Rule;
+
+use self::safety::Tracked;
+use self::trash::Trash;
+
+/// setup.cfg section holding the clean patterns
+pub const SECTION: &str = "tool:pyproj.clean";
+
+/// Removed when setup.cfg doesn't list patterns. Patterns are written like
+/// lines of a .gitignore: they match names anywhere in the project, or a
+/// path from the project root if they contain a `/`
+pub const PATTERNS: [&str; 13] = [
+    "__pycache__",
+    "*.pyc",
+    "*.pyo",
+    "*.egg-info",
+    ".pytest_cache",
+    ".mypy_cache",
+    ".ruff_cache",
+    ".tox",
+    "htmlcov",
+    ".coverage",
+    ".coverage.*",
+    "/build",
+    "/dist",
+];
+
+/// Options of the clean subcommand
+pub struct CleanOptions {
+    /// Remove the virtual environments too
+    pub all: bool,
+    /// Move everything to the trash instead of deleting it
+    pub trash: bool,
+}
+
+/// What clean removes from setup.cfg
+///
+/// ```ini
+/// [tool:pyproj.clean]
+/// # Replaces the default patterns
+/// patterns = __pycache__, *.pyc
+/// # Added to the patterns
+/// extra = *.log, /docs/_build
+/// # Removed by --all, with every folder holding a pyvenv.cfg
+/// venv = env
+/// # Always move things to .pyproj/trash instead of deleting them
+/// trash = true
+/// ```
+pub struct CleanConfig {
+    pub patterns: Vec<String>,
+    /// The patterns, parsed like lines of a .gitignore
+    rules: Vec<Rule>,
+    pub venvs: Vec<String>,
+    pub trash: bool,
+}
+
+/// A file or folder to remove
+struct Target {
+    path: PathBuf,
+    /// Path from the project root, separated by `/`
+    relative: String,
+    /// Pattern it matched (`venv` for virtual environments)
+    pattern: String,
+    is_dir: bool,
+    size: u64,
+}
+
+impl CleanConfig {
+    pub fn from_config(cfg: Option<&SetupCfg>) -> CleanConfig {
+        // Lists may be written one per line or separated by commas
+        let list = |key: &str| -> Vec<String> {
+            cfg.map(|x| x.get_list(SECTION, key))
+                .unwrap_or_default()
+                .iter()
+                .flat_map(|x| x.split(','))
+                .map(|x| x.trim())
+                .filter(|x| !x.is_empty())
+                .map(|x| x.to_string())
+                .collect()
+        };
+
+        let mut patterns = list("patterns");
+        if patterns.is_empty() {
+            patterns = PATTERNS.iter().map(|x| x.to_string()).collect();
+        }
+        patterns.extend(list("extra"));
+
+        let trash = cfg
+            .and_then(|x| x.get(SECTION, "trash"))
+            .map(|x| matches!(x.to_lowercase().as_str(), "true" | "yes" | "on" | "1"))
+            .unwrap_or(false);
+
+        // `!` patterns can't un-ignore anything here
+        patterns.retain(|x| Rule::parse(x).map(|x| !x.negated).unwrap_or(false));
+        let rules = patterns.iter().filter_map(|x| Rule::parse(x)).collect();
+
+        CleanConfig { patterns, rules, venvs: list("venv"), trash }
+    }
+}
+
+/// Remove the files and folders matching the clean patterns from the
+/// project, reporting how many were removed and the space reclaimed
+///
+/// Symlinks, anything resolving outside the project root and anything
+/// holding files tracked by git are left alone. Returns false if something
+/// couldn't be removed.
+pub fn clean(options: &CleanOptions) -> bool {
+    let cfg = match Path::new(SETUP_CFG).exists() {
+        true => match SetupCfg::load(Path::new(SETUP_CFG)) {
+            Ok(x) => Some(x),
+            Err(e) => {
+                println!("Error while reading {}: {}", SETUP_CFG, e);
+                return false;
+            }
+        },
+        false => None,
+    };
+    let config = CleanConfig::from_config(cfg.as_ref());
+
+    let tracked = match Tracked::load() {
+        Ok(x) => x,
+        Err(e) => {
+            println!("{}", e);
+            return false;
+        }
+    };
+    let root = match safety::root() {
+        Ok(x) => x,
+        Err(e) => {
+            println!("Error while finding the project root: {}", e);
+            return false;
+        }
+    };
+
+    let project = Path::new(".");
+    let mut targets: Vec<Target> = Vec::new();
+    find(project, project, &config, options.all, &mut targets);
+
+    let mut skipped = 0;
+    targets.retain(|target| {
+        let reason = match () {
+            _ if !safety::is_inside(&root, &target.path) => "it is outside the project",
+            _ if tracked.contains(&target.relative) => "it holds files tracked by git",
+            _ => return true,
+        };
+
+        println!("Skipped {} as {}", target.relative, reason);
+        skipped += 1;
+        false
+    });
+
+    if targets.is_empty() {
+        if skipped == 0 {
+            println!("Nothing to clean");
+        }
+        return true;
+    }
+
+    let trash = match options.trash || config.trash {
+        true => Some(Trash::new()),
+        false => None,
+    };
+
+    let mut removed: Vec<&Target> = Vec::new();
+    let mut failed = false;
+
+    for target in &targets {
+        let result = match (&trash, target.is_dir) {
+            (Some(trash), _) => trash.put(&target.relative),
+            (None, true) => fs::remove_dir_all(&target.path),
+            (None, false) => fs::remove_file(&target.path),
+        };
+
+        match result {
+            Ok(_) => removed.push(target),
+            Err(e) => {
+                println!("Couldn't remove {}: {}", target.relative, e);
+                failed = true;
+            }
+        }
+    }
+
+    // Counts per pattern, in the order of the patterns
+    for pattern in config.patterns.iter().chain(std::iter::once(&String::from("venv"))) {
+        let matched: Vec<&&Target> = removed.iter().filter(|x| &x.pattern == pattern).collect();
+
+        if !matched.is_empty() {
+            let size: u64 = matched.iter().map(|x| x.size).sum();
+            println!("{:<16} {:>5}  {:>10}", pattern, matched.len(), format_size(size));
+        }
+    }
+
+    let folders = removed.iter().filter(|x| x.is_dir).count();
+    let files = removed.len() - folders;
+    let size: u64 = removed.iter().map(|x| x.size).sum();
+
+    match &trash {
+        Some(trash) => println!(
+            "Moved {} folder(s) and {} file(s) ({}) to {}",
+            folders, files, format_size(size), trash.dir.display()
+        ),
+        None => println!(
+            "Removed {} folder(s) and {} file(s), reclaiming {}",
+            folders, files, format_size(size)
+        ),
+    };
+
+    !failed
+}
+
+/// Find the targets in a folder, without looking inside the ones found
+fn find(dir: &Path, root: &Path, config: &CleanConfig, all: bool, targets: &mut Vec<Target>) {
+    let mut entries: Vec<fs::DirEntry> = match fs::read_dir(dir) {
+        Ok(x) => x.filter_map(|x| x.ok()).collect(),
+        Err(_) => return,
+    };
+    entries.sort_by_key(|x| x.file_name());
+
+    for entry in entries {
+        let path = entry.path();
+        let name = entry.file_name().to_string_lossy().to_string();
+        let relative = path.strip_prefix(root).unwrap_or(&path).to_string_lossy().replace('\\', "/");
+        // Symlinks are neither followed nor removed
+        let is_dir = match entry.file_type() {
+            Ok(x) if x.is_symlink() => continue,
+            Ok(x) => x.is_dir(),
+            Err(_) => continue,
+        };
+
+        // Never look into git's or pyproj's own folders (the trash)
+        if is_dir && (name == ".git" || name == ".pyproj") {
+            continue;
+        }
+
+        if is_dir && is_venv(&path, &relative, config) {
+            if all {
+                targets.push(Target { size: size(&path), path, relative, pattern: String::from("venv"), is_dir });
+            }
+            continue;
+        }
+
+        let matched = config.patterns
+            .iter()
+            .zip(&config.rules)
+            .find(|(_, rule)| rule.matches(&relative, is_dir));
+
+        match matched.map(|(pattern, _)| pattern) {
+            Some(pattern) => targets.push(Target { size: size(&path), path, relative, pattern: pattern.clone(), is_dir }),
+            None if is_dir => find(&path, root, config, all, targets),
+            None => {},
+        }
+    }
+}
+
+/// Check whether a folder is a virtual environment
+fn is_venv(path: &Path, relative: &str, config: &CleanConfig) -> bool {
+    path.join("pyvenv.cfg").is_file()
+        || config.venvs.iter().any(|x| x.trim_matches('/') == relative)
+}
+
+/// Size of a file, or of everything in a folder (symlinks aren't followed)
+fn size(path: &Path) -> u64 {
+    let metadata = match fs::symlink_metadata(path) {
+        Ok(x) => x,
+        Err(_) => return 0,
+    };
+
+    if !metadata.is_dir() {
+        return metadata.len();
+    }
+
+    match fs::read_dir(path) {
+        Ok(entries) => entries.filter_map(|x| x.ok()).map(|x| size(&x.path())).sum(),
+        Err(_) => 0,
+    }
+}
+
+/// A size in bytes as `512 B`, `1.5 KB`, `3.2 MB`...
+fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KB", "MB", "GB", "TB"];
+
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+
+    format!("{:.1} {}", size, UNITS[unit])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Create a project of empty files and folders (ending with `/`),
+    /// returning its root
+    fn project(name: &str, paths: &[&str]) -> PathBuf {
+        let root = std::env::temp_dir().join(format!("pyproj-clean-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+
+        for path in paths {
+            let path = root.join(path);
+            match path.to_string_lossy().ends_with('/') {
+                true => fs::create_dir_all(&path).unwrap(),
+                false => {
+                    fs::create_dir_all(path.parent().unwrap()).unwrap();
+                    fs::write(&path, "").unwrap();
+                }
+            }
+        }
+
+        root
+    }
+
+    /// Paths of the targets from the root, with the pattern each matched
+    fn targets(root: &Path, config: &CleanConfig, all: bool) -> Vec<(String, String)> {
+        let mut targets: Vec<Target> = Vec::new();
+        find(root, root, config, all, &mut targets);
+
+        targets.iter().map(|x| (x.relative.clone(), x.pattern.clone())).collect()
+    }
+
+    fn pairs(expected: &[(&str, &str)]) -> Vec<(String, String)> {
+        expected.iter().map(|(a, b)| (a.to_string(), b.to_string())).collect()
+    }
+
+    #[test]
+    fn patterns_match_names_anywhere_or_paths_from_the_root() {
+        let root = project("patterns", &[
+            "build/lib/a.py",
+            "src/build/keep.py",
+            "src/pkg/__pycache__/a.cpython-311.pyc",
+            "src/pkg/module.pyc",
+            "src/pkg/module.py",
+            "pkg.egg-info/PKG-INFO",
+            ".coverage.host.1234",
+            ".git/objects/",
+        ]);
+
+        assert_eq!(targets(&root, &CleanConfig::from_config(None), false), pairs(&[
+            (".coverage.host.1234", ".coverage.*"),
+            ("build", "/build"),
+            ("pkg.egg-info", "*.egg-info"),
+            ("src/pkg/__pycache__", "__pycache__"),
+            ("src/pkg/module.pyc", "*.pyc"),
+        ]));
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn configured_patterns_replace_or_extend_the_defaults() {
+        let root = project("config", &["a.log", "docs/_build/index.html", "src/_build/x", "__pycache__/"]);
+
+        let cfg = SetupCfg::parse("[tool:pyproj.clean]\nextra = *.log, /docs/_build\n");
+        assert_eq!(targets(&root, &CleanConfig::from_config(Some(&cfg)), false), pairs(&[
+            ("__pycache__", "__pycache__"),
+            ("a.log", "*.log"),
+            ("docs/_build", "/docs/_build"),
+        ]));
+
+        let cfg = SetupCfg::parse("[tool:pyproj.clean]\npatterns =\n    *.log\n");
+        assert_eq!(targets(&root, &CleanConfig::from_config(Some(&cfg)), false), pairs(&[("a.log", "*.log")]));
+
+        // Patterns which match nothing find nothing
+        let cfg = SetupCfg::parse("[tool:pyproj.clean]\npatterns = *.tmp, /missing\n");
+        assert!(targets(&root, &CleanConfig::from_config(Some(&cfg)), false).is_empty());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn virtual_environments_are_only_removed_with_all() {
+        let root = project("venvs", &[
+            ".venv/pyvenv.cfg",
+            ".venv/lib/__pycache__/x.pyc",
+            "env/lib/site.py",
+            "src/__pycache__/",
+        ]);
+
+        let cfg = SetupCfg::parse("[tool:pyproj.clean]\nvenv = env/\n");
+        let config = CleanConfig::from_config(Some(&cfg));
+
+        // Nothing inside a virtual environment is touched without --all
+        assert_eq!(targets(&root, &config, false), pairs(&[("src/__pycache__", "__pycache__")]));
+        assert_eq!(targets(&root, &config, true), pairs(&[
+            (".venv", "venv"),
+            ("env", "venv"),
+            ("src/__pycache__", "__pycache__"),
+        ]));
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn negated_patterns_are_dropped() {
+        let cfg = SetupCfg::parse("[tool:pyproj.clean]\npatterns = *.log, !keep.log, docs/_build/\n");
+        let config = CleanConfig::from_config(Some(&cfg));
+
+        assert_eq!(config.patterns, ["*.log", "docs/_build/"]);
+        assert_eq!(config.rules.len(), 2);
+    }
+}
@@ -0,0 +1,159 @@
+//! Checks keeping clean from removing anything it shouldn't
+
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Files tracked by git, relative to the project root
+pub struct Tracked {
+    files: Vec<String>,
+}
+
+impl Tracked {
+    /// List the files git tracks in the project (none if it isn't a repository)
+    ///
+    /// Fails if the project is a repository but git couldn't list its files,
+    /// as anything could be tracked.
+    pub fn load() -> Result<Tracked, String> {
+        let output = Command::new("git")
+            .args(["ls-files", "-z"])
+            .output();
+
+        match output {
+            Ok(x) if x.status.success() => {
+                let mut files: Vec<String> = String::from_utf8_lossy(&x.stdout)
+                    .split('\0')
+                    .filter(|x| !x.is_empty())
+                    .map(|x| x.to_string())
+                    .collect();
+                files.sort();
+
+                Ok(Tracked { files })
+            },
+            _ if !is_repository() => Ok(Tracked { files: Vec::new() }),
+            Ok(x) => Err(format!(
+                "Couldn't list the files tracked by git: {}",
+                String::from_utf8_lossy(&x.stderr).trim()
+            )),
+            Err(e) => Err(format!("Couldn't list the files tracked by git: {}", e)),
+        }
+    }
+
+    /// Check whether a file, or anything in a folder, is tracked
+    pub fn contains(&self, relative: &str) -> bool {
+        let folder = format!("{}/", relative);
+
+        // Anything in the folder sorts right after it
+        let at = self.files.partition_point(|x| x.as_str() < relative);
+        self.files[at..]
+            .iter()
+            .take_while(|x| x.as_str() == relative || x.starts_with(relative))
+            .any(|x| x == relative || x.starts_with(&folder))
+    }
+}
+
+/// Check whether the project is in a git repository, asking git and else
+/// (if git can't run or refuses the repository) looking for a `.git`
+fn is_repository() -> bool {
+    let inside = Command::new("git")
+        .args(["rev-parse", "--is-inside-work-tree"])
+        .output()
+        .map(|x| x.status.success() && String::from_utf8_lossy(&x.stdout).trim() == "true")
+        .unwrap_or(false);
+
+    // Assume there is one if the project root can't be found
+    inside || root().map(|x| has_git_folder(&x)).unwrap_or(true)
+}
+
+/// Check whether a folder or any folder above it has a `.git` (a folder,
+/// or a file for worktrees and submodules)
+fn has_git_folder(dir: &Path) -> bool {
+    dir.ancestors().any(|x| x.join(".git").exists())
+}
+
+/// The project root, with symlinks resolved
+pub fn root() -> io::Result<PathBuf> {
+    Path::new(".").canonicalize()
+}
+
+/// Check whether a path is inside the project root once symlinks are
+/// resolved (the path itself isn't followed if it's a symlink)
+pub fn is_inside(root: &Path, path: &Path) -> bool {
+    let parent = match path.parent() {
+        Some(x) if !x.as_os_str().is_empty() => x,
+        _ => Path::new("."),
+    };
+
+    match (parent.canonicalize(), path.file_name()) {
+        (Ok(parent), Some(name)) => {
+            let resolved = parent.join(name);
+            resolved != root && resolved.starts_with(root)
+        },
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    fn tracked(files: &[&str]) -> Tracked {
+        let mut files: Vec<String> = files.iter().map(|x| x.to_string()).collect();
+        files.sort();
+        Tracked { files }
+    }
+
+    #[test]
+    fn tracked_files_and_folders() {
+        let tracked = tracked(&["build.py", "docs/build/index.md", "src/pkg/__init__.py", "src/pkg-data.txt"]);
+
+        assert!(tracked.contains("build.py"));
+        assert!(tracked.contains("docs/build"));
+        assert!(tracked.contains("docs"));
+        assert!(tracked.contains("src/pkg"));
+        assert!(!tracked.contains("build"));
+        assert!(!tracked.contains("src/pkg/__pycache__"));
+        assert!(!tracked.contains("src/pk"));
+        assert!(!tracked.contains("docs/build/index"));
+    }
+
+    #[test]
+    fn git_folders_are_found_above() {
+        let dir = std::env::temp_dir().join(format!("pyproj-git-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("project/src")).unwrap();
+
+        fs::write(dir.join("project/.git"), "gitdir: ../.git/worktrees/project\n").unwrap();
+        assert!(has_git_folder(&dir.join("project/src")));
+        assert!(has_git_folder(&dir.join("project")));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn paths_must_stay_in_the_project() {
+        let dir = std::env::temp_dir().join(format!("pyproj-inside-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("project/build")).unwrap();
+        fs::create_dir_all(dir.join("outside")).unwrap();
+        let root = dir.join("project").canonicalize().unwrap();
+
+        assert!(is_inside(&root, &root.join("build")));
+        assert!(is_inside(&root, &root.join("missing")));
+        assert!(!is_inside(&root, &root));
+        assert!(!is_inside(&root, &root.join("../outside")));
+        assert!(!is_inside(&root, &root.join("no/such/folder")));
+
+        #[cfg(unix)]
+        {
+            // A link is inside even if it points out, as it's removed and not followed
+            std::os::unix::fs::symlink(dir.join("outside"), root.join("link")).unwrap();
+            assert!(is_inside(&root, &root.join("link")));
+            assert!(!is_inside(&root, &root.join("link/file")));
+        }
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
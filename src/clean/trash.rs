@@ -0,0 +1,45 @@
+//! Move cleaned files to a trash folder they can be recovered from
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Folder (from the project root) holding pyproj's trash
+pub const TRASH_DIR: &str = ".pyproj/trash";
+
+/// A folder in the trash for one run of clean, named after the time it ran
+pub struct Trash {
+    pub dir: PathBuf,
+}
+
+impl Trash {
+    pub fn new() -> Trash {
+        let seconds = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|x| x.as_secs())
+            .unwrap_or(0);
+
+        // Runs in the same second get their own folder
+        let mut dir = Path::new(TRASH_DIR).join(seconds.to_string());
+        let mut n = 1;
+        while dir.exists() {
+            dir = Path::new(TRASH_DIR).join(format!("{}-{}", seconds, n));
+            n += 1;
+        }
+
+        Trash { dir }
+    }
+
+    /// Move a file or folder to the trash, keeping its path from the
+    /// project root
+    pub fn put(&self, relative: &str) -> io::Result<()> {
+        let target = self.dir.join(relative);
+
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        fs::rename(relative, target)
+    }
+}
@@ -0,0 +1,208 @@
+//! Read and edit setup.cfg style configuration files
+//! without losing comments or formatting
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// An INI-style configuration file (setup.cfg)
+pub struct SetupCfg {
+    lines: Vec<String>,
+}
+
+impl SetupCfg {
+    /// Load a configuration file from disk
+    pub fn load(path: &Path) -> io::Result<SetupCfg> {
+        let content = fs::read_to_string(path)?;
+
+        Ok(SetupCfg::parse(&content))
+    }
+
+    /// Parse configuration from a string
+    pub fn parse(content: &str) -> SetupCfg {
+        SetupCfg {
+            lines: content.lines().map(|x| x.to_string()).collect(),
+        }
+    }
+
+    /// Write the configuration back to disk
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let mut content = self.lines.join("\n");
+        content.push('\n');
+
+        fs::write(path, content)
+    }
+
+    /// Get the raw value of a key, with continuation lines joined by newlines
+    pub fn get(&self, section: &str, key: &str) -> Option<String> {
+        let (start, end) = self.find_key(section, key)?;
+
+        let first = SetupCfg::split_key(&self.lines[start])?.1;
+        let mut value: Vec<&str> = vec![first.trim()];
+
+        for line in &self.lines[start + 1..end] {
+            value.push(line.trim());
+        }
+
+        Some(value.join("\n").trim().to_string())
+    }
+
+    /// Get a multi-line value as a list, skipping blank lines and comments
+    pub fn get_list(&self, section: &str, key: &str) -> Vec<String> {
+        match self.get(section, key) {
+            Some(value) => value
+                .lines()
+                .map(|x| x.trim())
+                .filter(|x| !x.is_empty() && !x.starts_with('#'))
+                .map(|x| x.to_string())
+                .collect(),
+            None => Vec::new(),
+        }
+    }
+
+    /// Get a multi-line value line by line, skipping blank lines but
+    /// keeping comments, so it can be edited and written back with them
+    pub fn get_lines(&self, section: &str, key: &str) -> Vec<String> {
+        match self.get(section, key) {
+            Some(value) => value
+                .lines()
+                .map(|x| x.trim())
+                .filter(|x| !x.is_empty())
+                .map(|x| x.to_string())
+                .collect(),
+            None => Vec::new(),
+        }
+    }
+
+    /// Get every key in a section
+    pub fn keys(&self, section: &str) -> Vec<String> {
+        let (start, end) = match self.find_section(section) {
+            Some(x) => x,
+            None => return Vec::new(),
+        };
+
+        self.lines[start + 1..end]
+            .iter()
+            .filter(|x| !x.starts_with([' ', '\t']))
+            .filter_map(|x| SetupCfg::split_key(x))
+            .map(|(k, _)| k.to_string())
+            .collect()
+    }
+
+    /// Set a single-line value, creating the section/key if needed
+    pub fn set(&mut self, section: &str, key: &str, value: &str) {
+        self.replace(section, key, vec![format!("{} = {}", key, value)]);
+    }
+
+    /// Set a multi-line list value, creating the section/key if needed
+    pub fn set_list(&mut self, section: &str, key: &str, values: &[String]) {
+        let mut lines = vec![format!("{} =", key)];
+
+        for value in values {
+            lines.push(format!("    {}", value));
+        }
+
+        self.replace(section, key, lines);
+    }
+
+    /// Replace the lines of a key with new lines
+    fn replace(&mut self, section: &str, key: &str, new_lines: Vec<String>) {
+        match self.find_key(section, key) {
+            Some((start, end)) => {
+                self.lines.splice(start..end, new_lines);
+            },
+            None => {
+                let end = match self.find_section(section) {
+                    Some((_, end)) => end,
+                    None => {
+                        if self.lines.last().map(|x| !x.trim().is_empty()).unwrap_or(false) {
+                            self.lines.push(String::new());
+                        }
+                        self.lines.push(format!("[{}]", section));
+                        self.lines.len()
+                    }
+                };
+
+                // Insert before any trailing blank lines of the section
+                let mut at = end;
+                while at > 0 && self.lines[at - 1].trim().is_empty() {
+                    at -= 1;
+                }
+
+                self.lines.splice(at..at, new_lines);
+            }
+        }
+    }
+
+    /// Find the header line and the end (exclusive) of a section
+    fn find_section(&self, section: &str) -> Option<(usize, usize)> {
+        let start = self.lines
+            .iter()
+            .position(|x| SetupCfg::section_name(x) == Some(section))?;
+
+        let end = self.lines[start + 1..]
+            .iter()
+            .position(|x| SetupCfg::section_name(x).is_some())
+            .map(|x| x + start + 1)
+            .unwrap_or(self.lines.len());
+
+        Some((start, end))
+    }
+
+    /// Find the first line and the end (exclusive) of a key's value
+    fn find_key(&self, section: &str, key: &str) -> Option<(usize, usize)> {
+        let (start, end) = self.find_section(section)?;
+
+        let key_line = (start + 1..end).find(|&i| {
+            let line = &self.lines[i];
+            !line.starts_with([' ', '\t'])
+                && SetupCfg::split_key(line).map(|(k, _)| k == key).unwrap_or(false)
+        })?;
+
+        // Continuation lines are indented
+        let mut key_end = key_line + 1;
+        while key_end < end && self.lines[key_end].starts_with([' ', '\t']) {
+            key_end += 1;
+        }
+
+        Some((key_line, key_end))
+    }
+
+    /// Get the section name of a `[section]` header line
+    fn section_name(line: &str) -> Option<&str> {
+        line.trim()
+            .strip_prefix('[')?
+            .strip_suffix(']')
+            .map(|x| x.trim())
+    }
+
+    /// Split a `key = value` line
+    fn split_key(line: &str) -> Option<(&str, &str)> {
+        if line.trim_start().starts_with(['#', ';']) {
+            return None;
+        }
+
+        let at = line.find(['=', ':'])?;
+
+        Some((line[..at].trim(), &line[at + 1..]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lists_keep_their_comments() {
+        let mut cfg = SetupCfg::parse("[options]\ninstall_requires =\n    # HTTP\n    requests>=2\n    six  # old\nzip_safe = False\n");
+
+        assert_eq!(cfg.get_list("options", "install_requires"), ["requests>=2", "six  # old"]);
+
+        let lines = cfg.get_lines("options", "install_requires");
+        assert_eq!(lines, ["# HTTP", "requests>=2", "six  # old"]);
+
+        cfg.set_list("options", "install_requires", &lines);
+        assert_eq!(cfg.get_lines("options", "install_requires"), lines);
+        assert_eq!(cfg.get("options", "zip_safe").as_deref(), Some("False"));
+    }
+}
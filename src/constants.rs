@@ -15,16 +15,52 @@ lazy_static! {
             "pyproj create <directory>"
         ]),
         ("clean", [
-            "Clean the Python project's files and folders (at the current working directory)", 
-            "pyproj clean"
+            "Clean the Python project's caches and build artifacts (at the current working directory): __pycache__, *.pyc, *.pyo, *.egg-info, .pytest_cache, .mypy_cache, .ruff_cache, .tox, htmlcov, .coverage files, ./build and ./dist, or the patterns in setup.cfg's [tool:pyproj.clean]. --all removes virtual environments too and --trash moves everything to ./.pyproj/trash instead of deleting it. Symlinks, paths resolving outside the project and anything holding files tracked by git are never removed. Reports how many files and folders were removed and the space reclaimed", 
+            "pyproj clean [--all] [--trash]"
         ]),
         ("build", [
-            "Build certain files automatically, such as requirements.txt. [Currently only requirements.txt is supported]", 
-            "pyproj build"
+            "Build requirements.txt from imports in ./src (including notebooks), and requirements-dev.txt from imports in ./tests, ./examples, ./docs and `if TYPE_CHECKING:` blocks. setup.cfg's install_requires and dev extras are updated too. Existing lines are kept and pins are never downgraded unless --force is used. --policy picks how versions are written (setup.cfg's version_policy by default). --check compares the files with what would be written without changing them, exiting with an error if they are out of date. Requirements which aren't imported (tools like black) are kept, use --strict to report them too.", 
+            "pyproj build [--check [--strict]] [--force] [--policy exact|compatible|minimum|major-bound|none]"
+        ]),
+        ("nb", [
+            "Jupyter notebook tools. `export` converts a notebook into a module in the project's package under ./src",
+            "pyproj nb export <notebook.ipynb> [module] [--force]"
+        ]),
+        ("graph", [
+            "Export the import graph of the modules in ./src as Graphviz DOT, Mermaid or JSON. --collapse merges modules into their packages, --third-party includes installed modules and --focus highlights a module's dependencies and dependents",
+            "pyproj graph [--format dot|mermaid|json] [--output <file>] [--collapse] [--third-party] [--focus <module>]"
+        ]),
+        ("check", [
+            "Check the project for problems, exiting with code 1 if any are found. `cycles` lists circular imports between the modules in ./src with the file and line of each import, telling module-level imports apart from deferred (function-local) ones. `python` infers the minimum Python version from the syntax and standard library modules used in ./src and verifies setup.cfg's python_requires and classifiers (--write updates them, creating setup.cfg if needed)",
+            "pyproj check cycles | pyproj check python [--write]"
+        ]),
+        ("deps", [
+            "Inspect the project's dependencies. `audit` compares the imports in ./src, ./tests, ./examples and ./docs with the packages declared in the requirements files and setup.cfg, listing packages declared but never imported, imported but not declared and imported but not installed with a command to fix each (exits with code 1 if any are found). `tree` shows the installed packages required by each of the project's requirements (--dev includes dev requirements), marking version conflicts, and `why` explains which requirements pull in a package",
+            "pyproj deps <audit|tree [--dev]|why <package>>"
+        ]),
+        ("lock", [
+            "Resolve the project's requirements (and dev requirements with --dev) to a consistent set of versions without network access, using a local directory of wheels and sdists (--find-links) or a PEP 503 simple index in a local directory or served from localhost (--index), and write them to requirements.lock. --python and --platform resolve for another environment",
+            "pyproj lock <--find-links <dir>|--index <dir|url>> [--dev] [--python X.Y] [--platform linux|win32|darwin] [--output <file>]"
+        ]),
+        ("audit", [
+            "Check the versions in requirements.lock (or the installed packages the project's requirements need if there is no lockfile) against a local database of advisories in the OSV format, a directory or zip of JSON files. Reports each advisory's ID, severity, affected range and fixed version as a table, JSON or SARIF, and exits with code 1 if any is at or above the --fail-on severity (low by default) or of unknown severity (unless --ignore-unknown)",
+            "pyproj audit --db <dir|zip> [--format table|json|sarif] [--fail-on low|medium|high|critical] [--ignore-unknown] [--lockfile <file>] [--output <file>]"
+        ]),
+        ("licenses", [
+            "Report the licenses of the installed packages the project's requirements need (--dev includes dev requirements), read from their License-Expression, License and classifier metadata or bundled license files and normalised to SPDX. Checks them against the allow/deny lists in setup.cfg's [tool:pyproj.licenses] (exits with code 1 if any fail) and exports a CSV, JSON or Markdown attribution report including the license texts",
+            "pyproj licenses [--dev] [--format table|csv|json|markdown] [--output <file>]"
+        ]),
+        ("cache", [
+            "Manage the cache of the imports found in each file (./.pyproj/cache), used by build, deps and graph so only files which changed are parsed again. `clear` removes it",
+            "pyproj cache clear"
+        ]),
+        ("sbom", [
+            "Export a software bill of materials as CycloneDX 1.5 JSON or SPDX 2.3 JSON, describing the project from setup.cfg's metadata and every installed package its requirements need, directly or transitively (--dev includes dev requirements), with its version, package URL, license, dependencies and the hashes of the files listed in its RECORD",
+            "pyproj sbom [--format cyclonedx|spdx] [--dev] [--output <file>]"
         ]),
         ("test", [
-            "Run unit tests for the project, either a single test or all tests in the ./tests/ folder",
-            "pyproj test <file>"
+            "Run the project's tests (every test in ./tests/ by default) with pytest, unittest or nose2: setup.cfg's [tool:pyproj.test] runner, else the runner configured by the project (pytest.ini, conftest.py, [tool:pytest], nose2.cfg...), else the one installed, else unittest. The target is a file or folder, optionally followed by ::Class::method, or a dotted name (test_file.Class.method), translated to the runner's syntax. -k only runs tests matching an expression (pytest and unittest) and arguments after -- are passed to the runner. Afterwards, a table lists the tests which didn't pass and the slowest tests with the number of tests passed, failed, errored and skipped, and the results are written as JUnit XML and JSON (./.pyproj/test/junit.xml and results.json, or --junit/--json or setup.cfg's junit and json keys). --list shows the tests in ./tests/ without running them (unittest.TestCase subclasses, Test* classes and test_* functions in test*.py and *_test.py files) as a tree with the file and line of each, or with --flat one target per line for shell completion. Every run is recorded in ./.pyproj/test/history: --failed only re-runs the tests which failed last time, --failed-first runs them before the other tests (pytest and unittest), and --history shows the recorded outcomes of a test, file or class, flagging flaky tests which changed between passing and failing more than once",
+            "pyproj test [<target>] [-k <expression>] [--runner pytest|unittest|nose2] [--failed | --failed-first] [--junit <file>] [--json <file>] [-- <args>...] | pyproj test --list [--flat] | pyproj test --history <test>"
         ])
     ].into_iter().collect();
 }
\ No newline at end of file
@@ -0,0 +1,233 @@
+//! Cross-reference the project's imports with its declared dependencies
+
+use crate::build::{find_dependencies, Group, REQUIREMENTS_DEV};
+use crate::interpreter::Parser;
+use crate::package::installed;
+use crate::package::name::normalise_name;
+
+use super::{find_declarations, Declaration};
+
+/// A third-party module imported by the project
+struct Imported {
+    module: String,
+    /// Package installing the module
+    package: String,
+    group: Group,
+}
+
+/// Problems found by an audit, one line each with how to fix it
+struct Findings {
+    unused: Vec<String>,
+    undeclared: Vec<String>,
+    not_installed: Vec<String>,
+}
+
+/// Report declared packages which are never imported, imported packages
+/// which aren't declared and imported packages which aren't installed
+///
+/// Returns false if any problem was found.
+pub fn audit() -> bool {
+    let deps = find_dependencies();
+
+    for diagnostic in &deps.diagnostics {
+        println!("WARNING: Skipped {}", diagnostic);
+    }
+
+    let distributions = installed::find_distributions();
+    let owners = installed::module_owners(&distributions);
+
+    let imports: Vec<Imported> = deps.runtime
+        .iter()
+        .map(|x| (x, Group::Runtime))
+        .chain(deps.dev.iter().map(|x| (x, Group::Dev)))
+        .map(|(module, group)| Imported {
+            module: module.clone(),
+            package: owners
+                .get(module)
+                .cloned()
+                .unwrap_or_else(|| Parser::convert_common_mods(module)),
+            group,
+        })
+        .collect();
+
+    let declarations = match find_declarations() {
+        Ok(x) => x,
+        Err(e) => {
+            println!("{}", e);
+            return false;
+        }
+    };
+
+    let installed_names: Vec<String> = distributions.iter().map(|x| normalise_name(&x.name)).collect();
+    let findings = classify(&imports, &declarations, &installed_names);
+
+    let sections = [
+        ("Declared but never imported", &findings.unused),
+        ("Imported but not declared", &findings.undeclared),
+        ("Imported but not installed", &findings.not_installed),
+    ];
+
+    for (title, problems) in sections.iter() {
+        if problems.is_empty() {
+            continue;
+        }
+
+        println!("{} ({})", title, problems.len());
+        for problem in problems.iter() {
+            println!("\t{}", problem);
+        }
+        println!();
+    }
+
+    let passed = sections.iter().all(|(_, x)| x.is_empty());
+    if passed {
+        println!("Every import is declared and installed, and every declared package is imported");
+    }
+
+    passed
+}
+
+/// Find the declared packages which are never imported, and the imported
+/// packages which aren't declared or installed (`installed` are the
+/// normalised names of the installed distributions)
+fn classify(imports: &[Imported], declarations: &[Declaration], installed: &[String]) -> Findings {
+    let is_imported = |package: &str, group: Group| {
+        imports
+            .iter()
+            .any(|x| normalise_name(&x.package) == package && (group == Group::Dev || x.group == group))
+    };
+
+    let is_declared = |package: &str, group: Group| {
+        declarations
+            .iter()
+            .any(|x| x.package == normalise_name(package) && (group == Group::Dev || x.group == group))
+    };
+
+    let is_installed = |package: &str| installed.contains(&normalise_name(package));
+
+    // Declared but never imported
+    let mut unused: Vec<String> = Vec::new();
+
+    for declaration in declarations {
+        if is_imported(&declaration.package, declaration.group) {
+            continue;
+        }
+
+        let fix = match is_imported(&declaration.package, Group::Dev) {
+            true => format!(
+                "only imported by tests, examples or docs, move it to {}",
+                REQUIREMENTS_DEV
+            ),
+            false => format!("remove `{}` from {}", declaration.package, declaration.source),
+        };
+
+        unused.push(format!("{} ({})\tfix: {}", declaration.package, declaration.source, fix));
+    }
+
+    // Imported but not declared
+    let mut undeclared: Vec<String> = Vec::new();
+
+    for import in imports.iter().filter(|x| !is_declared(&x.package, x.group)) {
+        let fix = match is_installed(&import.package) {
+            true => String::from("pyproj build"),
+            false => format!("pip install {} && pyproj build", import.package),
+        };
+
+        undeclared.push(format!("{}{}\tfix: {}", import.module, describe_package(import), fix));
+    }
+
+    // Imported but not installed
+    let mut not_installed: Vec<String> = Vec::new();
+
+    for import in imports.iter().filter(|x| !is_installed(&x.package)) {
+        let fix = match declarations.iter().find(|x| x.package == normalise_name(&import.package)) {
+            Some(x) if x.source.ends_with(".txt") => format!("pip install -r {}", x.source),
+            _ => format!("pip install {}", import.package),
+        };
+
+        not_installed.push(format!("{}{}\tfix: {}", import.module, describe_package(import), fix));
+    }
+
+    Findings {
+        unused,
+        undeclared,
+        not_installed,
+    }
+}
+
+/// Show the package of an import if its name is different (`yaml (PyYAML)`)
+fn describe_package(import: &Imported) -> String {
+    match normalise_name(&import.module) == normalise_name(&import.package) {
+        true => String::new(),
+        false => format!(" (package {})", import.package),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::package::requirement::Requirement;
+
+    use super::*;
+
+    fn imported(module: &str, package: &str, group: Group) -> Imported {
+        Imported {
+            module: module.to_string(),
+            package: package.to_string(),
+            group,
+        }
+    }
+
+    fn declared(package: &str, group: Group, source: &str) -> Declaration {
+        Declaration {
+            package: normalise_name(package),
+            requirement: Requirement::parse(package).unwrap(),
+            group,
+            source: source.to_string(),
+        }
+    }
+
+    #[test]
+    fn imports_and_declarations_are_cross_referenced() {
+        let imports = [
+            imported("requests", "requests", Group::Runtime),
+            imported("yaml", "PyYAML", Group::Runtime),
+            imported("rich", "rich", Group::Runtime),
+            imported("numpy", "numpy", Group::Runtime),
+            imported("pytest", "pytest", Group::Dev),
+        ];
+        let declarations = [
+            declared("requests", Group::Runtime, "requirements.txt"),
+            declared("pyyaml", Group::Runtime, "setup.cfg install_requires"),
+            declared("six", Group::Runtime, "requirements.txt"),
+            declared("pytest", Group::Runtime, "requirements.txt"),
+            declared("rich", Group::Dev, "requirements-dev.txt"),
+        ];
+        let installed: Vec<String> = ["requests", "pyyaml", "rich", "six", "pytest"]
+            .iter()
+            .map(|x| x.to_string())
+            .collect();
+
+        let findings = classify(&imports, &declarations, &installed);
+
+        assert_eq!(findings.unused, [
+            "six (requirements.txt)\tfix: remove `six` from requirements.txt",
+            "pytest (requirements.txt)\tfix: only imported by tests, examples or docs, move it to requirements-dev.txt",
+        ]);
+        assert_eq!(findings.undeclared, [
+            "rich\tfix: pyproj build",
+            "numpy\tfix: pip install numpy && pyproj build",
+        ]);
+        assert_eq!(findings.not_installed, ["numpy\tfix: pip install numpy"]);
+    }
+
+    #[test]
+    fn uninstalled_declared_packages_are_installed_from_their_file() {
+        let imports = [imported("yaml", "PyYAML", Group::Dev)];
+        let declarations = [declared("PyYAML", Group::Dev, "requirements-dev.txt")];
+
+        let findings = classify(&imports, &declarations, &[]);
+
+        assert!(findings.unused.is_empty() && findings.undeclared.is_empty());
+        assert_eq!(findings.not_installed, ["yaml (package PyYAML)\tfix: pip install -r requirements-dev.txt"]);
+    }
+}
@@ -0,0 +1,70 @@
+//! Inspect the project's dependencies
+
+pub mod audit;
+pub mod tree;
+
+use std::path::Path;
+
+use crate::build::requirements::RequirementsFile;
+use crate::build::{Group, REQUIREMENTS, REQUIREMENTS_DEV, SETUP_CFG};
+use crate::config::SetupCfg;
+use crate::package::requirement::Requirement;
+
+/// A package declared as a dependency
+pub struct Declaration {
+    /// Normalised package name
+    pub package: String,
+    /// The requirement as declared
+    pub requirement: Requirement,
+    pub group: Group,
+    /// Where it is declared (`requirements.txt`, `setup.cfg install_requires`)
+    pub source: String,
+}
+
+/// Read the packages declared in the requirements files and setup.cfg
+pub fn find_declarations() -> Result<Vec<Declaration>, String> {
+    let mut declarations: Vec<Declaration> = Vec::new();
+
+    let mut add = |requirements: &RequirementsFile, group: Group, source: &str| {
+        for error in &requirements.errors {
+            println!("WARNING: {}: {}", source, error);
+        }
+
+        for requirement in requirements.requirements() {
+            let package = requirement.key();
+
+            if !declarations.iter().any(|x| x.package == package && x.group == group) {
+                declarations.push(Declaration {
+                    requirement: requirement.clone(),
+                    package,
+                    group,
+                    source: source.to_string(),
+                });
+            }
+        }
+    };
+
+    for (file, group) in [(REQUIREMENTS, Group::Runtime), (REQUIREMENTS_DEV, Group::Dev)] {
+        let requirements = RequirementsFile::load(Path::new(file))
+            .map_err(|e| format!("Error while reading {}: {}", file, e))?;
+        add(&requirements, group, file);
+    }
+
+    let setup_cfg = Path::new(SETUP_CFG);
+    if setup_cfg.exists() {
+        let cfg = SetupCfg::load(setup_cfg)
+            .map_err(|e| format!("Error while reading {}: {}", SETUP_CFG, e))?;
+
+        let lists = [
+            ("options", "install_requires", Group::Runtime),
+            ("options.extras_require", "dev", Group::Dev),
+        ];
+
+        for (section, key, group) in lists {
+            let requirements = RequirementsFile::parse(&cfg.get_list(section, key).join("\n"));
+            add(&requirements, group, &format!("{} {}", SETUP_CFG, key));
+        }
+    }
+
+    Ok(declarations)
+}
@@ -0,0 +1,364 @@
+//! Show the installed dependency tree of the project's requirements
+
+use crate::build::Group;
+use crate::package::installed::{self, Distribution};
+use crate::package::marker::Environment;
+use crate::package::name::normalise_name;
+use crate::package::requirement::Requirement;
+use crate::package::version::Version;
+
+use super::{find_declarations, Declaration};
+
+/// Print the tree of installed distributions required by each of the
+/// project's requirements (and dev requirements with `dev`)
+///
+/// Returns false if an installed version doesn't match what requires it,
+/// or a required distribution isn't installed.
+pub fn tree(dev: bool) -> bool {
+    let declarations = match direct_requirements(dev) {
+        Ok(x) => x,
+        Err(e) => {
+            println!("{}", e);
+            return false;
+        }
+    };
+
+    let distributions = installed::find_distributions();
+    let environment = Environment::current();
+    let mut lines: Vec<String> = Vec::new();
+    let mut conflicts: Vec<String> = Vec::new();
+
+    for declaration in &declarations {
+        let requirement = &declaration.requirement;
+
+        if !requirement.applies(&environment, &[]) {
+            lines.push(format!(
+                "{} [required: {}, not needed in this environment]",
+                requirement.name, required(requirement)
+            ));
+            continue;
+        }
+
+        let mut path: Vec<String> = vec![declaration.source.clone()];
+        let tree = Tree {
+            distributions: &distributions,
+            environment: &environment,
+        };
+        tree.render(requirement, 0, &mut path, &mut lines, &mut conflicts);
+    }
+
+    for line in &lines {
+        println!("{}", line);
+    }
+
+    if declarations.is_empty() {
+        println!("The project has no requirements");
+    }
+
+    if !conflicts.is_empty() {
+        println!();
+        println!("WARNING: Found {} conflict(s)", conflicts.len());
+        for conflict in &conflicts {
+            println!("\t{}", conflict);
+        }
+    }
+
+    conflicts.is_empty()
+}
+
+/// Explain which of the project's requirements pull in a distribution
+///
+/// Returns false if none of them require it.
+pub fn why(package: &str) -> bool {
+    let declarations = match direct_requirements(true) {
+        Ok(x) => x,
+        Err(e) => {
+            println!("{}", e);
+            return false;
+        }
+    };
+
+    let distributions = installed::find_distributions();
+    let environment = Environment::current();
+    let target = normalise_name(package);
+
+    let installed = match find(&distributions, package) {
+        Some(x) => format!("{} {}", x.name, x.version),
+        None => format!("{} (not installed)", package),
+    };
+
+    let mut chains: Vec<String> = Vec::new();
+
+    for declaration in &declarations {
+        if declaration.package == target {
+            chains.push(format!("{} (declared in {})", declaration.requirement, declaration.source));
+            continue;
+        }
+
+        let root = match find(&distributions, &declaration.package) {
+            Some(x) => x,
+            None => continue,
+        };
+
+        let tree = Tree {
+            distributions: &distributions,
+            environment: &environment,
+        };
+
+        let mut path: Vec<String> = vec![format!("{} {}", root.name, root.version)];
+        let mut visited: Vec<String> = vec![normalise_name(&root.name)];
+        tree.find_chains(root, &declaration.requirement.extras, &target, &mut path, &mut visited, &mut chains);
+    }
+
+    if chains.is_empty() {
+        println!("{} isn't required by any of the project's requirements", installed);
+        return false;
+    }
+
+    println!("{} is required by:", installed);
+    for chain in &chains {
+        println!("\t{}", chain);
+    }
+
+    true
+}
+
+/// The project's own requirements
+fn direct_requirements(dev: bool) -> Result<Vec<Declaration>, String> {
+    let mut declarations = find_declarations()?;
+    declarations.retain(|x| dev || x.group == Group::Runtime);
+
+    // Packages declared in several files are only shown once
+    let mut seen: Vec<String> = Vec::new();
+    declarations.retain(|x| match seen.contains(&x.package) {
+        true => false,
+        false => {
+            seen.push(x.package.clone());
+            true
+        }
+    });
+
+    Ok(declarations)
+}
+
+/// Installed distributions and the environment they are installed in
+struct Tree<'a> {
+    distributions: &'a [Distribution],
+    environment: &'a Environment,
+}
+
+impl<'a> Tree<'a> {
+    /// Render a requirement and everything it requires, a line each
+    fn render(
+        &self,
+        requirement: &Requirement,
+        depth: usize,
+        path: &mut Vec<String>,
+        lines: &mut Vec<String>,
+        conflicts: &mut Vec<String>,
+    ) {
+        let indent = "    ".repeat(depth);
+        let bullet = if depth == 0 { "" } else { "- " };
+        let required = required(requirement);
+
+        let distribution = match find(self.distributions, &requirement.name) {
+            Some(x) => x,
+            None => {
+                lines.push(format!(
+                    "{}{}{} [required: {}, installed: none]  NOT INSTALLED",
+                    indent, bullet, requirement.name, required
+                ));
+                conflicts.push(format!("{}: {} {} isn't installed", path.join(" -> "), requirement.name, required));
+                return;
+            }
+        };
+
+        let matches = match Version::parse(&distribution.version) {
+            Some(version) => requirement.specifier.contains(&version),
+            None => requirement.specifier.is_empty(),
+        };
+
+        let note = match matches {
+            true => "",
+            false => "  CONFLICT",
+        };
+
+        lines.push(format!(
+            "{}{}{} [required: {}, installed: {}]{}",
+            indent, bullet, distribution.name, required, distribution.version, note
+        ));
+
+        if !matches {
+            conflicts.push(format!(
+                "{}: {} {} is installed but {} is required",
+                path.join(" -> "), distribution.name, distribution.version, required
+            ));
+        }
+
+        let name = format!("{} {}", distribution.name, distribution.version);
+        if path.contains(&name) {
+            lines.push(format!("{}    (cycle)", indent));
+            return;
+        }
+
+        path.push(name);
+        for dependency in self.requires(distribution, &requirement.extras) {
+            self.render(dependency, depth + 1, path, lines, conflicts);
+        }
+        path.pop();
+    }
+
+    /// Find every chain of requirements from a distribution to a target
+    fn find_chains(
+        &self,
+        from: &Distribution,
+        extras: &[String],
+        target: &str,
+        path: &mut Vec<String>,
+        visited: &mut Vec<String>,
+        chains: &mut Vec<String>,
+    ) {
+        for requirement in self.requires(from, extras) {
+            let name = requirement.key();
+            if visited.contains(&name) {
+                continue;
+            }
+
+            let step = match requirement.specifier.is_empty() {
+                true => requirement.name.clone(),
+                false => format!("{} ({})", requirement.name, requirement.specifier),
+            };
+
+            if name == target {
+                chains.push(format!("{} -> {}", path.join(" -> "), step));
+                continue;
+            }
+
+            if let Some(next) = find(self.distributions, &requirement.name) {
+                path.push(step);
+                visited.push(name);
+                self.find_chains(next, &requirement.extras, target, path, visited, chains);
+                visited.pop();
+                path.pop();
+            }
+        }
+    }
+
+    /// Requirements of a distribution which apply to the environment,
+    /// with the given extras requested
+    fn requires(&self, distribution: &'a Distribution, extras: &[String]) -> Vec<&'a Requirement> {
+        distribution
+            .requires
+            .iter()
+            .filter(|x| x.applies(self.environment, extras))
+            .collect()
+    }
+}
+
+/// What a requirement allows (`>=1.0,<2`, a URL or any version)
+fn required(requirement: &Requirement) -> String {
+    match (&requirement.url, requirement.specifier.is_empty()) {
+        (Some(url), _) => url.clone(),
+        (None, true) => String::from("any"),
+        (None, false) => requirement.specifier.to_string(),
+    }
+}
+
+fn find<'a>(distributions: &'a [Distribution], name: &str) -> Option<&'a Distribution> {
+    let name = normalise_name(name);
+    distributions.iter().find(|x| normalise_name(&x.name) == name)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::fs;
+    use std::path::Path;
+
+    use super::*;
+
+    /// Write a `.dist-info` folder and read it back
+    fn distribution(root: &Path, name: &str, version: &str, requires: &[&str]) -> Distribution {
+        let path = root.join(format!("{}-{}.dist-info", name, version));
+        fs::create_dir_all(&path).unwrap();
+
+        let mut metadata = format!("Metadata-Version: 2.1\nName: {}\nVersion: {}\n", name, version);
+        for requirement in requires {
+            metadata.push_str(&format!("Requires-Dist: {}\n", requirement));
+        }
+        fs::write(path.join("METADATA"), metadata).unwrap();
+
+        Distribution::load(&path).unwrap()
+    }
+
+    fn render_tree(distributions: &[Distribution], requirement: &str) -> (Vec<String>, Vec<String>) {
+        let environment = Environment { values: HashMap::new() };
+        let tree = Tree { distributions, environment: &environment };
+
+        let mut lines: Vec<String> = Vec::new();
+        let mut conflicts: Vec<String> = Vec::new();
+        let mut path: Vec<String> = vec![String::from("requirements.txt")];
+
+        let requirement = Requirement::parse(requirement).unwrap();
+        tree.render(&requirement, 0, &mut path, &mut lines, &mut conflicts);
+
+        (lines, conflicts)
+    }
+
+    #[test]
+    fn trees_show_versions_and_conflicts() {
+        let root = std::env::temp_dir().join(format!("pyproj-tree-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+
+        let distributions = [
+            distribution(&root, "requests", "2.31.0", &["idna (>=2.5,<4)", "urllib3 (>=2)", "certifi"]),
+            distribution(&root, "idna", "3.6", &[]),
+            distribution(&root, "urllib3", "1.26.18", &[]),
+        ];
+
+        let (lines, conflicts) = render_tree(&distributions, "Requests>=2.0");
+        assert_eq!(lines, [
+            "requests [required: >=2.0, installed: 2.31.0]",
+            "    - idna [required: >=2.5,<4, installed: 3.6]",
+            "    - urllib3 [required: >=2, installed: 1.26.18]  CONFLICT",
+            "    - certifi [required: any, installed: none]  NOT INSTALLED",
+        ]);
+        assert_eq!(conflicts, [
+            "requirements.txt -> requests 2.31.0: urllib3 1.26.18 is installed but >=2 is required",
+            "requirements.txt -> requests 2.31.0: certifi any isn't installed",
+        ]);
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn cycles_are_only_walked_once() {
+        let root = std::env::temp_dir().join(format!("pyproj-tree-cycle-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+
+        let distributions = [
+            distribution(&root, "alpha", "1.0", &["beta"]),
+            distribution(&root, "beta", "2.0", &["alpha"]),
+        ];
+
+        let (lines, conflicts) = render_tree(&distributions, "alpha");
+        assert_eq!(lines, [
+            "alpha [required: any, installed: 1.0]",
+            "    - beta [required: any, installed: 2.0]",
+            "        - alpha [required: any, installed: 1.0]",
+            "            (cycle)",
+        ]);
+        assert!(conflicts.is_empty());
+
+        let environment = Environment { values: HashMap::new() };
+        let tree = Tree { distributions: &distributions, environment: &environment };
+
+        let mut chains: Vec<String> = Vec::new();
+        let mut path: Vec<String> = vec![String::from("alpha 1.0")];
+        let mut visited: Vec<String> = vec![String::from("alpha")];
+        tree.find_chains(&distributions[0], &[], "beta", &mut path, &mut visited, &mut chains);
+        assert_eq!(chains, ["alpha 1.0 -> beta"]);
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
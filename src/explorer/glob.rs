@@ -0,0 +1,134 @@
+//! Glob patterns matched against `/` separated paths
+
+/// A glob pattern: `*` matches anything but `/`, `?` a single character,
+/// `[a-z]`/`[!a-z]` a set of characters and a `**` segment any number of
+/// folders
+#[derive(Clone, Debug)]
+pub struct Glob {
+    segments: Vec<Vec<char>>,
+}
+
+impl Glob {
+    pub fn new(pattern: &str) -> Glob {
+        Glob {
+            segments: pattern
+                .split('/')
+                .filter(|x| !x.is_empty())
+                .map(|x| x.chars().collect())
+                .collect(),
+        }
+    }
+
+    /// Check whether the whole of a path matches
+    pub fn matches(&self, path: &str) -> bool {
+        let path: Vec<&str> = path.split('/').filter(|x| !x.is_empty()).collect();
+        match_segments(&self.segments, &path)
+    }
+}
+
+fn match_segments(pattern: &[Vec<char>], path: &[&str]) -> bool {
+    match pattern.first() {
+        None => path.is_empty(),
+        Some(x) if x.iter().collect::<String>() == "**" => {
+            (0..=path.len()).any(|skip| match_segments(&pattern[1..], &path[skip..]))
+        },
+        Some(x) => match path.first() {
+            Some(name) => {
+                let name: Vec<char> = name.chars().collect();
+                match_name(x, &name) && match_segments(&pattern[1..], &path[1..])
+            },
+            None => false,
+        },
+    }
+}
+
+/// Match a single file or folder name
+fn match_name(pattern: &[char], name: &[char]) -> bool {
+    match pattern.first() {
+        None => name.is_empty(),
+        Some('*') => (0..=name.len()).any(|skip| match_name(&pattern[1..], &name[skip..])),
+        Some('?') => !name.is_empty() && match_name(&pattern[1..], &name[1..]),
+        Some('[') => match (class(&pattern[1..]), name.first()) {
+            (Some((matched, length)), Some(&c)) => matched(c) && match_name(&pattern[length + 1..], &name[1..]),
+            // An unclosed `[` is a literal
+            (None, Some('[')) => match_name(&pattern[1..], &name[1..]),
+            _ => false,
+        },
+        Some('\\') if pattern.len() > 1 => name.first() == Some(&pattern[1]) && match_name(&pattern[2..], &name[1..]),
+        Some(c) => name.first() == Some(c) && match_name(&pattern[1..], &name[1..]),
+    }
+}
+
+/// Checks whether a character is in a character class
+type ClassMatcher = Box<dyn Fn(char) -> bool>;
+
+/// Parse a character class after its `[`, returning a matcher and the
+/// number of characters up to and including the `]`
+fn class(pattern: &[char]) -> Option<(ClassMatcher, usize)> {
+    let negated = matches!(pattern.first(), Some('!') | Some('^'));
+    let start = if negated { 1 } else { 0 };
+
+    // A `]` straight after the `[` is part of the set
+    let end = pattern
+        .iter()
+        .enumerate()
+        .skip(start + 1)
+        .find(|(_, c)| **c == ']')
+        .map(|(i, _)| i)?;
+
+    let set: Vec<char> = pattern[start..end].to_vec();
+    let matched = move |c: char| {
+        let mut found = false;
+        let mut i = 0;
+
+        while i < set.len() {
+            if i + 2 < set.len() && set[i + 1] == '-' {
+                found |= set[i] <= c && c <= set[i + 2];
+                i += 3;
+            } else {
+                found |= set[i] == c;
+                i += 1;
+            }
+        }
+
+        found != negated
+    };
+
+    Some((Box::new(matched), end + 1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wildcards_stay_in_a_segment() {
+        assert!(Glob::new("*.py").matches("setup.py"));
+        assert!(!Glob::new("*.py").matches("src/setup.py"));
+        assert!(Glob::new("src/?.py").matches("src/a.py"));
+        assert!(!Glob::new("src/?.py").matches("src/ab.py"));
+    }
+
+    #[test]
+    fn double_stars_match_any_folders() {
+        let glob = Glob::new("**/build");
+        assert!(glob.matches("build"));
+        assert!(glob.matches("a/b/build"));
+        assert!(!glob.matches("a/build/x"));
+
+        let glob = Glob::new("docs/**/*.md");
+        assert!(glob.matches("docs/index.md"));
+        assert!(glob.matches("docs/a/b/index.md"));
+        assert!(!glob.matches("src/docs/index.md"));
+    }
+
+    #[test]
+    fn character_classes() {
+        assert!(Glob::new("v[0-9].py").matches("v1.py"));
+        assert!(!Glob::new("v[!0-9].py").matches("v1.py"));
+        assert!(Glob::new("[]a].txt").matches("].txt"));
+        assert!(Glob::new("a[.txt").matches("a[.txt"));
+        assert!(Glob::new("\\*.txt").matches("*.txt"));
+        assert!(!Glob::new("\\*.txt").matches("a.txt"));
+    }
+}
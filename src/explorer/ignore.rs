@@ -0,0 +1,227 @@
+//! `.gitignore` style rules deciding which files are skipped
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::glob::Glob;
+
+/// Ignore files read in every folder, the later taking precedence
+pub const IGNORE_FILES: [&str; 2] = [".gitignore", ".ignore"];
+
+/// A line of an ignore file
+#[derive(Clone, Debug)]
+pub struct Rule {
+    glob: Glob,
+    /// `!pattern`, which un-ignores what it matches
+    pub negated: bool,
+    /// `pattern/`, which only matches folders
+    dir_only: bool,
+}
+
+/// Rules read from one file, applying to the paths under a folder
+#[derive(Clone, Debug)]
+pub struct RuleSet {
+    /// Folder from the project root the rules are relative to (empty for the root)
+    base: String,
+    rules: Vec<Rule>,
+}
+
+impl Rule {
+    /// Parse a line of an ignore file, or None if it's blank or a comment
+    ///
+    /// Patterns with a `/` (other than a trailing one) are relative to the
+    /// folder of the file, others match names at any depth.
+    pub fn parse(line: &str) -> Option<Rule> {
+        let line = line.trim_end_matches(['\r', '\n']);
+        // Trailing spaces are ignored unless escaped
+        let line = match line.ends_with("\\ ") {
+            true => line,
+            false => line.trim_end(),
+        };
+
+        if line.is_empty() || line.starts_with('#') {
+            return None;
+        }
+
+        let (negated, line) = match line.strip_prefix('!') {
+            Some(x) => (true, x),
+            None => (false, line.strip_prefix('\\').filter(|x| x.starts_with(['#', '!'])).unwrap_or(line)),
+        };
+
+        let (dir_only, line) = match line.strip_suffix('/') {
+            Some(x) => (true, x),
+            None => (false, line),
+        };
+
+        if line.is_empty() {
+            return None;
+        }
+
+        let glob = match line.contains('/') {
+            true => Glob::new(line.trim_start_matches('/')),
+            false => Glob::new(&format!("**/{}", line)),
+        };
+
+        Some(Rule { glob, negated, dir_only })
+    }
+
+    /// Check whether the rule matches a path (relative to the rule's folder)
+    pub fn matches(&self, relative: &str, is_dir: bool) -> bool {
+        (is_dir || !self.dir_only) && self.glob.matches(relative)
+    }
+}
+
+impl RuleSet {
+    pub fn new(base: &str, rules: Vec<Rule>) -> RuleSet {
+        RuleSet {
+            base: base.trim_matches('/').to_string(),
+            rules,
+        }
+    }
+
+    /// Read an ignore file, or None if it doesn't exist or has no rules
+    pub fn load(path: &Path, base: &str) -> Option<RuleSet> {
+        let content = fs::read_to_string(path).ok()?;
+        let rules: Vec<Rule> = content.lines().filter_map(Rule::parse).collect();
+
+        match rules.is_empty() {
+            true => None,
+            false => Some(RuleSet::new(base, rules)),
+        }
+    }
+
+    /// Whether the last rule matching a path (from the project root)
+    /// ignores it, or None if no rule matches
+    pub fn check(&self, relative: &str, is_dir: bool) -> Option<bool> {
+        let relative = match self.base.is_empty() {
+            true => relative,
+            false => relative.strip_prefix(&self.base)?.strip_prefix('/')?,
+        };
+
+        self.rules
+            .iter()
+            .rev()
+            .find(|x| x.matches(relative, is_dir))
+            .map(|x| !x.negated)
+    }
+}
+
+/// Git's global excludes file (`core.excludesFile`, or `git/ignore` in the
+/// XDG config folder)
+pub fn global_excludes() -> Option<PathBuf> {
+    let home = std::env::var_os("HOME")
+        .or_else(|| std::env::var_os("USERPROFILE"))
+        .map(PathBuf::from);
+    let config = std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| home.as_ref().map(|x| x.join(".config")));
+
+    let configs = [
+        home.as_ref().map(|x| x.join(".gitconfig")),
+        config.as_ref().map(|x| x.join("git").join("config")),
+    ];
+
+    // Later configs take precedence
+    let excludes = configs
+        .iter()
+        .flatten()
+        .rev()
+        .find_map(|x| excludes_file(x))
+        .map(|x| match (x.strip_prefix("~/"), &home) {
+            (Some(rest), Some(home)) => home.join(rest),
+            _ => PathBuf::from(x),
+        });
+
+    excludes.or_else(|| config.map(|x| x.join("git").join("ignore")))
+}
+
+/// Read `excludesFile` from the `[core]` section of a git config file
+fn excludes_file(path: &Path) -> Option<String> {
+    let content = fs::read_to_string(path).ok()?;
+    let mut core = false;
+    let mut found: Option<String> = None;
+
+    for line in content.lines().map(|x| x.trim()) {
+        if line.starts_with('[') {
+            core = line.trim_matches(['[', ']']).trim().eq_ignore_ascii_case("core");
+            continue;
+        }
+
+        if let Some((key, value)) = line.split_once('=') {
+            if core && key.trim().eq_ignore_ascii_case("excludesfile") {
+                found = Some(value.trim().trim_matches('"').to_string());
+            }
+        }
+    }
+
+    found
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rules(base: &str, lines: &str) -> RuleSet {
+        RuleSet::new(base, lines.lines().filter_map(Rule::parse).collect())
+    }
+
+    #[test]
+    fn blank_lines_and_comments_are_skipped() {
+        assert!(Rule::parse("").is_none());
+        assert!(Rule::parse("   ").is_none());
+        assert!(Rule::parse("# comment").is_none());
+        assert!(Rule::parse("\\#file").unwrap().matches("#file", false));
+    }
+
+    #[test]
+    fn negation_un_ignores() {
+        let set = rules("", "*.log\n!keep.log\n");
+
+        assert_eq!(set.check("debug.log", false), Some(true));
+        assert_eq!(set.check("logs/keep.log", false), Some(false));
+        assert_eq!(set.check("main.py", false), None);
+        assert!(Rule::parse("\\!important").unwrap().matches("!important", false));
+    }
+
+    #[test]
+    fn leading_slashes_anchor_to_the_folder() {
+        let set = rules("", "/build\n");
+
+        assert_eq!(set.check("build", true), Some(true));
+        assert_eq!(set.check("src/build", true), None);
+
+        let set = rules("", "docs/_build\n");
+        assert_eq!(set.check("docs/_build", true), Some(true));
+        assert_eq!(set.check("src/docs/_build", true), None);
+    }
+
+    #[test]
+    fn trailing_slashes_only_match_folders() {
+        let set = rules("", "cache/\n");
+
+        assert_eq!(set.check("cache", true), Some(true));
+        assert_eq!(set.check("a/cache", true), Some(true));
+        assert_eq!(set.check("cache", false), None);
+    }
+
+    #[test]
+    fn double_stars_match_any_folders() {
+        let set = rules("", "**/generated/**\nsrc/**/*.tmp\n");
+
+        assert_eq!(set.check("a/generated/b/c.py", false), Some(true));
+        assert_eq!(set.check("src/x.tmp", false), Some(true));
+        assert_eq!(set.check("src/a/b/x.tmp", false), Some(true));
+        assert_eq!(set.check("x.tmp", false), None);
+    }
+
+    #[test]
+    fn nested_rules_are_relative_to_their_folder() {
+        let set = rules("src/pkg", "/local.py\n*.dat\n");
+
+        assert_eq!(set.check("src/pkg/local.py", false), Some(true));
+        assert_eq!(set.check("src/pkg/sub/local.py", false), None);
+        assert_eq!(set.check("src/pkg/sub/x.dat", false), Some(true));
+        assert_eq!(set.check("x.dat", false), None);
+        assert_eq!(set.check("src/pkgs/x.dat", false), None);
+    }
+}
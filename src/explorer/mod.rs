@@ -0,0 +1,278 @@
+//! Walk the project's folders, skipping ignored files
+
+pub mod glob;
+pub mod ignore;
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::build::SETUP_CFG;
+use crate::config::SetupCfg;
+use crate::interpreter::source::Diagnostic;
+use crate::pool;
+
+use self::glob::Glob;
+use self::ignore::{Rule, RuleSet, IGNORE_FILES};
+
+/// setup.cfg section holding the include and exclude patterns
+pub const SECTION: &str = "tool:pyproj.explore";
+
+/// Folders which are never walked into, as well as virtual environments
+pub const SKIPPED: [&str; 3] = [".git", ".pyproj", "node_modules"];
+
+/// Decides which files an explorer finds
+pub type Predicate = dyn Fn(&Path) -> bool + Sync;
+
+/// Files and folders found in a folder, with the rules applying to each folder
+struct Scan {
+    files: Vec<PathBuf>,
+    folders: Vec<(PathBuf, Vec<Arc<RuleSet>>)>,
+    errors: Vec<Diagnostic>,
+}
+
+/// Finds the files in the project's folders, honouring `.gitignore` and
+/// `.ignore` files, git's excludes and the patterns in setup.cfg
+///
+/// ```ini
+/// [tool:pyproj.explore]
+/// # Only these files are found (every file if empty)
+/// include = *.py, *.ipynb
+/// # Skipped, like lines of a .gitignore
+/// exclude =
+///     src/generated/
+///     *_pb2.py
+/// ```
+pub struct Explorer {
+    /// Git's global and repository excludes
+    excludes: Vec<RuleSet>,
+    /// setup.cfg's excludes, which take precedence over every ignore file
+    config_excludes: RuleSet,
+    include: Vec<Glob>,
+    /// Files found, sorted by path
+    pub results: Vec<PathBuf>,
+    /// Folders which couldn't be read
+    pub errors: Vec<Diagnostic>,
+}
+
+impl Explorer {
+    /// An explorer for the project at the current working directory
+    pub fn new() -> Explorer {
+        let cfg = SetupCfg::load(Path::new(SETUP_CFG)).ok();
+        Explorer::from_config(cfg.as_ref())
+    }
+
+    pub fn from_config(cfg: Option<&SetupCfg>) -> Explorer {
+        // Lists may be written one per line or separated by commas
+        let list = |key: &str| -> Vec<String> {
+            cfg.map(|x| x.get_list(SECTION, key))
+                .unwrap_or_default()
+                .iter()
+                .flat_map(|x| x.split(','))
+                .map(|x| x.trim())
+                .filter(|x| !x.is_empty())
+                .map(|x| x.to_string())
+                .collect()
+        };
+
+        let excludes: Vec<RuleSet> = [ignore::global_excludes(), Some(PathBuf::from(".git/info/exclude"))]
+            .iter()
+            .flatten()
+            .filter_map(|x| RuleSet::load(x, ""))
+            .collect();
+
+        let config_excludes = RuleSet::new("", list("exclude").iter().filter_map(|x| Rule::parse(x)).collect());
+        let include = list("include")
+            .iter()
+            .map(|x| match x.contains('/') {
+                true => Glob::new(x),
+                false => Glob::new(&format!("**/{}", x)),
+            })
+            .collect();
+
+        Explorer {
+            excludes,
+            config_excludes,
+            include,
+            results: Vec::new(),
+            errors: Vec::new(),
+        }
+    }
+
+    /// Find the files in a folder (from the project root) matching a predicate
+    ///
+    /// Folders are read in parallel, a level at a time, and the files
+    /// found are sorted by path.
+    pub fn explore(&mut self, path: &Path, predicate: &Predicate) {
+        if !path.is_dir() {
+            return;
+        }
+
+        // Ignore files of the folders above apply too
+        let mut rules: Vec<Arc<RuleSet>> = Vec::new();
+        let mut folder = PathBuf::new();
+        load_rules(&folder, "", &mut rules);
+
+        for component in path.components() {
+            if let std::path::Component::Normal(name) = component {
+                folder.push(name);
+                load_rules(&folder, &relative(&folder), &mut rules);
+            }
+        }
+
+        let mut level: Vec<(PathBuf, Vec<Arc<RuleSet>>)> = vec![(path.to_path_buf(), rules)];
+        let mut results: Vec<PathBuf> = Vec::new();
+
+        while !level.is_empty() {
+            let scanned = pool::map(&level, |(dir, rules)| self.scan(dir, rules, predicate));
+
+            level = Vec::new();
+            for scan in scanned {
+                results.extend(scan.files);
+                level.extend(scan.folders);
+                self.errors.extend(scan.errors);
+            }
+        }
+
+        results.sort();
+        self.results.extend(results);
+    }
+
+    /// Read a folder, finding its files and the folders to walk into next
+    fn scan(&self, dir: &Path, rules: &[Arc<RuleSet>], predicate: &Predicate) -> Scan {
+        let mut scan = Scan {
+            files: Vec::new(),
+            folders: Vec::new(),
+            errors: Vec::new(),
+        };
+
+        let entries = match fs::read_dir(dir) {
+            Ok(x) => x,
+            Err(e) => {
+                scan.errors.push(Diagnostic {
+                    path: dir.to_path_buf(),
+                    line: None,
+                    message: format!("couldn't read folder: {}", e),
+                });
+                return scan;
+            }
+        };
+
+        let mut found: Vec<(PathBuf, bool)> = Vec::new();
+        for entry in entries {
+            // Symlinked folders aren't followed, they may point outside the project
+            let result = entry.and_then(|x| Ok((x.path(), x.file_type()?.is_dir())));
+
+            match result {
+                Ok(x) => found.push(x),
+                Err(e) => scan.errors.push(Diagnostic {
+                    path: dir.to_path_buf(),
+                    line: None,
+                    message: format!("couldn't read folder entry: {}", e),
+                }),
+            }
+        }
+        found.sort();
+
+        for (path, is_dir) in found {
+            let relative = relative(&path);
+
+            if self.is_ignored(rules, &relative, &path, is_dir) {
+                continue;
+            }
+
+            if is_dir {
+                let mut rules = rules.to_vec();
+                load_rules(&path, &relative, &mut rules);
+                scan.folders.push((path, rules));
+            } else if self.is_included(&relative) && predicate(&path) {
+                scan.files.push(path);
+            }
+        }
+
+        scan
+    }
+
+    fn is_ignored(&self, rules: &[Arc<RuleSet>], relative: &str, path: &Path, is_dir: bool) -> bool {
+        let name = path.file_name().map(|x| x.to_string_lossy().to_string()).unwrap_or_default();
+
+        if is_dir && (SKIPPED.contains(&name.as_str()) || path.join("pyvenv.cfg").is_file()) {
+            return true;
+        }
+
+        // The last rule matching wins
+        self.excludes
+            .iter()
+            .chain(rules.iter().map(|x| x.as_ref()))
+            .chain(std::iter::once(&self.config_excludes))
+            .rev()
+            .find_map(|x| x.check(relative, is_dir))
+            .unwrap_or(false)
+    }
+
+    fn is_included(&self, relative: &str) -> bool {
+        self.include.is_empty() || self.include.iter().any(|x| x.matches(relative))
+    }
+}
+
+/// Read the ignore files of a folder
+fn load_rules(folder: &Path, base: &str, rules: &mut Vec<Arc<RuleSet>>) {
+    for file in IGNORE_FILES.iter() {
+        if let Some(x) = RuleSet::load(&folder.join(file), base) {
+            rules.push(Arc::new(x));
+        }
+    }
+}
+
+/// A path from the project root, separated by `/`
+fn relative(path: &Path) -> String {
+    path.components()
+        .filter_map(|x| match x {
+            std::path::Component::Normal(x) => Some(x.to_string_lossy().to_string()),
+            _ => None,
+        })
+        .collect::<Vec<String>>()
+        .join("/")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nested_ignore_files_take_precedence() {
+        let root = std::env::temp_dir().join(format!("pyproj-explore-{}", std::process::id()));
+        let project = root.join("project");
+        let _ = fs::remove_dir_all(&root);
+
+        let files = [
+            ("project/.gitignore", "*.log\nbuild/\n"),
+            ("project/app.log", ""),
+            ("project/main.py", ""),
+            ("project/build/out.py", ""),
+            ("project/sub/.gitignore", "!keep.log\nmain.py\n"),
+            ("project/sub/keep.log", ""),
+            ("project/sub/other.log", ""),
+            ("project/sub/main.py", ""),
+            ("project/sub/deeper/keep.log", ""),
+        ];
+
+        for (path, content) in files.iter() {
+            let path = root.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, content).unwrap();
+        }
+
+        let mut explorer = Explorer::from_config(None);
+        explorer.explore(&root, &|x| !x.ends_with(".gitignore"));
+
+        let found: Vec<String> = explorer
+            .results
+            .iter()
+            .map(|x| x.strip_prefix(&project).unwrap().to_string_lossy().replace('\\', "/"))
+            .collect();
+        assert_eq!(found, ["main.py", "sub/deeper/keep.log", "sub/keep.log"]);
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,197 @@
+//! Find circular imports between a project's modules
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use super::{Edge, ModuleGraph};
+
+/// A group of modules which (indirectly) import each other
+pub struct Cycle<'a> {
+    /// Modules in the cycle
+    pub modules: Vec<String>,
+    /// Imports between the modules of the cycle
+    pub edges: Vec<&'a Edge>,
+    /// Whether the cycle is only made through deferred (function-local)
+    /// or type-checking imports, which usually work fine at runtime
+    pub safe: bool,
+}
+
+/// Find every import cycle in a graph
+pub fn find_cycles(graph: &ModuleGraph) -> Vec<Cycle<'_>> {
+    let edges: Vec<&Edge> = graph.edges.iter().collect();
+    let mut cycles: Vec<Cycle> = Vec::new();
+
+    for modules in strongly_connected(&edges) {
+        let members: BTreeSet<&String> = modules.iter().collect();
+
+        let mut edges: Vec<&Edge> = edges
+            .iter()
+            .filter(|x| members.contains(&x.from) && members.contains(&x.to))
+            .copied()
+            .collect();
+        edges.sort_by(|a, b| (&a.file, a.line).cmp(&(&b.file, b.line)));
+
+        // The cycle is only a problem if it still exists with
+        // the imports which run at import time
+        let module_level: Vec<&Edge> = edges
+            .iter()
+            .filter(|x| x.is_module_level())
+            .copied()
+            .collect();
+
+        let safe = strongly_connected(&module_level).is_empty();
+
+        cycles.push(Cycle {
+            modules,
+            edges,
+            safe,
+        });
+    }
+
+    cycles
+}
+
+/// Find the strongly connected components (with more than one module)
+/// of a set of edges using Tarjan's algorithm
+pub fn strongly_connected(edges: &[&Edge]) -> Vec<Vec<String>> {
+    // Adjacency list of node indexes
+    let mut index_of: BTreeMap<&str, usize> = BTreeMap::new();
+    for edge in edges {
+        let len = index_of.len();
+        index_of.entry(&edge.from).or_insert(len);
+        let len = index_of.len();
+        index_of.entry(&edge.to).or_insert(len);
+    }
+
+    let mut names: Vec<&str> = vec![""; index_of.len()];
+    for (name, &i) in &index_of {
+        names[i] = name;
+    }
+
+    let mut adjacent: Vec<BTreeSet<usize>> = vec![BTreeSet::new(); names.len()];
+    for edge in edges {
+        adjacent[index_of[edge.from.as_str()]].insert(index_of[edge.to.as_str()]);
+    }
+    let adjacent: Vec<Vec<usize>> = adjacent.into_iter().map(|x| x.into_iter().collect()).collect();
+
+    let mut index: Vec<Option<usize>> = vec![None; names.len()];
+    let mut low: Vec<usize> = vec![0; names.len()];
+    let mut on_stack: Vec<bool> = vec![false; names.len()];
+    let mut stack: Vec<usize> = Vec::new();
+    let mut next_index = 0;
+    let mut components: Vec<Vec<String>> = Vec::new();
+
+    for start in 0..names.len() {
+        if index[start].is_some() {
+            continue;
+        }
+
+        // Iterative depth-first search of (node, next neighbour to visit)
+        let mut work: Vec<(usize, usize)> = vec![(start, 0)];
+
+        while let Some((node, child)) = work.pop() {
+            if child == 0 {
+                index[node] = Some(next_index);
+                low[node] = next_index;
+                next_index += 1;
+                stack.push(node);
+                on_stack[node] = true;
+            } else {
+                // Returning from a neighbour
+                let previous = adjacent[node][child - 1];
+                low[node] = low[node].min(low[previous]);
+            }
+
+            let mut recursed = false;
+            for (i, &next) in adjacent[node].iter().enumerate().skip(child) {
+                match index[next] {
+                    None => {
+                        work.push((node, i + 1));
+                        work.push((next, 0));
+                        recursed = true;
+                        break;
+                    },
+                    Some(found) if on_stack[next] => {
+                        low[node] = low[node].min(found);
+                    },
+                    Some(_) => {},
+                }
+            }
+
+            if recursed || Some(low[node]) != index[node] {
+                continue;
+            }
+
+            // Node is the root of a component
+            let mut component: Vec<String> = Vec::new();
+            while let Some(member) = stack.pop() {
+                on_stack[member] = false;
+                component.push(names[member].to_string());
+                if member == node {
+                    break;
+                }
+            }
+
+            if component.len() > 1 {
+                component.sort();
+                components.push(component);
+            }
+        }
+    }
+
+    components.sort();
+    components
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+
+    fn edge(from: &str, to: &str) -> Edge {
+        Edge {
+            from: from.to_string(),
+            to: to.to_string(),
+            file: PathBuf::from(format!("src/{}.py", from)),
+            line: 1,
+            deferred: false,
+            type_checking: false,
+        }
+    }
+
+    fn components(edges: &[Edge]) -> Vec<Vec<String>> {
+        strongly_connected(&edges.iter().collect::<Vec<&Edge>>())
+    }
+
+    #[test]
+    fn two_modules_importing_each_other() {
+        let edges = [edge("a", "b"), edge("b", "a"), edge("b", "c")];
+
+        assert_eq!(components(&edges), [["a", "b"]]);
+    }
+
+    #[test]
+    fn self_imports_arent_cycles() {
+        assert!(components(&[edge("a", "a")]).is_empty());
+        assert_eq!(components(&[edge("a", "a"), edge("a", "b"), edge("b", "a")]), [["a", "b"]]);
+    }
+
+    #[test]
+    fn separate_cycles_are_sorted() {
+        // Listed so the search finds the later cycle first
+        let edges = [
+            edge("z", "y"),
+            edge("y", "x"),
+            edge("x", "z"),
+            edge("x", "c"),
+            edge("c", "b"),
+            edge("b", "c"),
+            edge("m", "n"),
+        ];
+
+        assert_eq!(components(&edges), vec![vec!["b", "c"], vec!["x", "y", "z"]]);
+
+        let reversed: Vec<&Edge> = edges.iter().rev().collect();
+        assert_eq!(strongly_connected(&reversed), components(&edges));
+    }
+}
@@ -0,0 +1,215 @@
+//! Export a module graph as Graphviz DOT, Mermaid or JSON
+
+use std::collections::BTreeSet;
+
+use serde_json::{json, Value};
+
+use super::ModuleGraph;
+
+/// Supported export formats
+pub const FORMATS: [&str; 3] = ["dot", "mermaid", "json"];
+
+/// Highlighted module with its dependencies and dependents
+pub struct Focus {
+    pub module: String,
+    pub dependencies: BTreeSet<String>,
+    pub dependents: BTreeSet<String>,
+}
+
+impl Focus {
+    pub fn new(graph: &ModuleGraph, module: &str) -> Focus {
+        Focus {
+            module: module.to_string(),
+            dependencies: graph.dependencies(module),
+            dependents: graph.dependents(module),
+        }
+    }
+
+    /// Role of a module relative to the focused module
+    fn role(&self, module: &str) -> Option<&'static str> {
+        if module == self.module {
+            Some("module")
+        } else if self.dependencies.contains(module) {
+            Some("dependency")
+        } else if self.dependents.contains(module) {
+            Some("dependent")
+        } else {
+            None
+        }
+    }
+
+    /// Role of an edge, if it is on a path to or from the focused module
+    fn edge_role(&self, from: &str, to: &str) -> Option<&'static str> {
+        match (self.role(from), self.role(to)) {
+            (Some("module"), Some("dependency")) | (Some("dependency"), Some("dependency")) => Some("dependency"),
+            (Some("dependent"), Some("module")) | (Some("dependent"), Some("dependent")) => Some("dependent"),
+            _ => None,
+        }
+    }
+}
+
+/// Export a graph in one of the supported formats
+pub fn export(graph: &ModuleGraph, format: &str, focus: Option<&Focus>) -> Option<String> {
+    match format {
+        "dot" => Some(to_dot(graph, focus)),
+        "mermaid" => Some(to_mermaid(graph, focus)),
+        "json" => Some(serde_json::to_string_pretty(&to_json(graph, focus)).unwrap()),
+        _ => None,
+    }
+}
+
+/// Fill colour of a highlighted module
+fn colour(role: &str) -> &'static str {
+    match role {
+        "module" => "#ffd700",
+        "dependency" => "#add8e6",
+        _ => "#ffb6c1",
+    }
+}
+
+/// Export a graph as Graphviz DOT
+pub fn to_dot(graph: &ModuleGraph, focus: Option<&Focus>) -> String {
+    let mut out = String::from("digraph imports {\n    rankdir=LR;\n    node [shape=box];\n\n");
+
+    for node in graph.nodes.values() {
+        let mut attributes = vec![format!("label=\"{}\"", node.name)];
+
+        if node.third_party {
+            attributes.push(String::from("style=dashed"));
+        }
+
+        if let Some(role) = focus.and_then(|x| x.role(&node.name)) {
+            attributes.push(format!("style=\"filled{}\"", if node.third_party {",dashed"} else {""}));
+            attributes.push(format!("fillcolor=\"{}\"", colour(role)));
+        }
+
+        out.push_str(&format!("    \"{}\" [{}];\n", node.name, attributes.join(", ")));
+    }
+
+    out.push('\n');
+
+    for (from, to) in graph.links().keys() {
+        match focus.and_then(|x| x.edge_role(from, to)) {
+            Some("dependency") => out.push_str(&format!("    \"{}\" -> \"{}\" [color=\"#1f77b4\", penwidth=2];\n", from, to)),
+            Some(_) => out.push_str(&format!("    \"{}\" -> \"{}\" [color=\"#d62728\", penwidth=2];\n", from, to)),
+            None => out.push_str(&format!("    \"{}\" -> \"{}\";\n", from, to)),
+        }
+    }
+
+    out.push_str("}\n");
+    out
+}
+
+/// Export a graph as a Mermaid flowchart
+pub fn to_mermaid(graph: &ModuleGraph, focus: Option<&Focus>) -> String {
+    // Node ids can only hold letters, digits and underscores, so `_`
+    // becomes `__`, `.` becomes `_d` and anything else its code point,
+    // keeping `a.b` and `a_b` apart
+    fn id(name: &str) -> String {
+        name.chars()
+            .map(|c| match c {
+                c if c.is_ascii_alphanumeric() => c.to_string(),
+                '_' => String::from("__"),
+                '.' => String::from("_d"),
+                c => format!("_x{:x}_", c as u32),
+            })
+            .collect()
+    }
+
+    let mut out = String::from("graph LR\n");
+
+    for node in graph.nodes.values() {
+        out.push_str(&format!("    {}[\"{}\"]\n", id(&node.name), node.name));
+    }
+
+    for (from, to) in graph.links().keys() {
+        out.push_str(&format!("    {} --> {}\n", id(from), id(to)));
+    }
+
+    let third_party: Vec<String> = graph.nodes
+        .values()
+        .filter(|x| x.third_party)
+        .map(|x| id(&x.name))
+        .collect();
+
+    if !third_party.is_empty() {
+        out.push_str("    classDef thirdparty stroke-dasharray: 5 5\n");
+        out.push_str(&format!("    class {} thirdparty\n", third_party.join(",")));
+    }
+
+    if let Some(focus) = focus {
+        for role in ["module", "dependency", "dependent"] {
+            let nodes: Vec<String> = graph.nodes
+                .keys()
+                .filter(|x| focus.role(x) == Some(role))
+                .map(|x| id(x))
+                .collect();
+
+            if !nodes.is_empty() {
+                out.push_str(&format!("    classDef {} fill:{}\n", role, colour(role)));
+                out.push_str(&format!("    class {} {}\n", nodes.join(","), role));
+            }
+        }
+    }
+
+    out
+}
+
+/// Export a graph as JSON
+pub fn to_json(graph: &ModuleGraph, focus: Option<&Focus>) -> Value {
+    let nodes: Vec<Value> = graph.nodes
+        .values()
+        .map(|x| json!({
+            "name": x.name,
+            "file": x.file.as_ref().map(|f| f.display().to_string()),
+            "third_party": x.third_party,
+            "focus": focus.and_then(|f| f.role(&x.name)),
+        }))
+        .collect();
+
+    let edges: Vec<Value> = graph.links()
+        .iter()
+        .map(|((from, to), imports)| json!({
+            "from": from,
+            "to": to,
+            "imports": imports
+                .iter()
+                .map(|x| json!({"file": x.file.display().to_string(), "line": x.line}))
+                .collect::<Vec<Value>>(),
+        }))
+        .collect();
+
+    let focus = focus.map(|x| json!({
+        "module": x.module,
+        "dependencies": x.dependencies,
+        "dependents": x.dependents,
+    }));
+
+    json!({
+        "nodes": nodes,
+        "edges": edges,
+        "focus": focus,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::super::Node;
+    use super::*;
+
+    #[test]
+    fn mermaid_ids_are_distinct() {
+        let mut nodes: BTreeMap<String, Node> = BTreeMap::new();
+        for name in ["a.b", "a_b", "a-b"] {
+            nodes.insert(name.to_string(), Node { name: name.to_string(), file: None, third_party: false });
+        }
+        let graph = ModuleGraph { nodes, edges: Vec::new(), diagnostics: Vec::new() };
+
+        assert_eq!(
+            to_mermaid(&graph, None),
+            "graph LR\n    a_x2d_b[\"a-b\"]\n    a_db[\"a.b\"]\n    a__b[\"a_b\"]\n"
+        );
+    }
+}
@@ -0,0 +1,335 @@
+//! Build the import graph of a project's own modules
+
+pub mod cycles;
+pub mod export;
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+
+use crate::build::is_py;
+use crate::explorer::Explorer;
+use crate::interpreter::{Import, Parser};
+use crate::interpreter::source::Diagnostic;
+use crate::cache;
+
+/// A module in the graph
+pub struct Node {
+    /// Dotted module name
+    pub name: String,
+    /// Source file of first-party modules
+    pub file: Option<PathBuf>,
+    /// Whether the module is a third-party (installed) module
+    pub third_party: bool,
+}
+
+/// A single import of one module by another
+pub struct Edge {
+    pub from: String,
+    pub to: String,
+    /// File and line of the import statement
+    pub file: PathBuf,
+    pub line: usize,
+    /// Whether the import is made inside a function
+    pub deferred: bool,
+    /// Whether the import is only made under `if TYPE_CHECKING:`
+    pub type_checking: bool,
+}
+
+impl Edge {
+    /// Whether the import runs as soon as the importing module is imported
+    pub fn is_module_level(&self) -> bool {
+        !self.deferred && !self.type_checking
+    }
+}
+
+/// Import graph of a project
+pub struct ModuleGraph {
+    pub nodes: BTreeMap<String, Node>,
+    pub edges: Vec<Edge>,
+    /// Files which couldn't be read
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl ModuleGraph {
+    /// Build the graph of every module under a source root (./src)
+    pub fn build(root: &Path) -> ModuleGraph {
+        let mut explorer = Explorer::new();
+        explorer.explore(root, &is_py);
+
+        let mut nodes: BTreeMap<String, Node> = BTreeMap::new();
+        let mut files: Vec<(String, PathBuf)> = Vec::new();
+
+        for file in explorer.results {
+            if let Some(name) = module_name(root, &file) {
+                nodes.insert(name.clone(), Node {
+                    name: name.clone(),
+                    file: Some(file.clone()),
+                    third_party: false,
+                });
+                files.push((name, file));
+            }
+        }
+
+        let mut graph = ModuleGraph {
+            nodes,
+            edges: Vec::new(),
+            diagnostics: explorer.errors,
+        };
+
+        // Files are parsed in parallel (unless cached), then added in order
+        let paths: Vec<PathBuf> = files.iter().map(|(_, file)| file.clone()).collect();
+        let parsed = cache::cached_imports(&paths);
+
+        for ((name, file), imports) in files.iter().zip(parsed) {
+            let package = match file.file_stem() {
+                Some(stem) if stem == "__init__" => name.clone(),
+                _ => parent(name).unwrap_or_default(),
+            };
+
+            let imports = match imports {
+                Ok(x) => x,
+                Err(e) => {
+                    graph.diagnostics.push(e);
+                    continue;
+                }
+            };
+
+            for import in imports {
+                for target in graph.resolve(&package, &import) {
+                    if &target == name {
+                        continue;
+                    }
+
+                    if !graph.nodes.contains_key(&target) {
+                        graph.nodes.insert(target.clone(), Node {
+                            name: target.clone(),
+                            file: None,
+                            third_party: true,
+                        });
+                    }
+
+                    graph.edges.push(Edge {
+                        from: name.clone(),
+                        to: target,
+                        file: file.clone(),
+                        line: import.line,
+                        deferred: import.deferred,
+                        type_checking: import.type_checking,
+                    });
+                }
+            }
+        }
+
+        graph
+    }
+
+    /// Get the first-party or third-party modules an import refers to
+    /// (built-in modules are ignored)
+    fn resolve(&self, package: &str, import: &Import) -> Vec<String> {
+        let mut targets: Vec<String> = Vec::new();
+
+        for path in &import.paths {
+            // Relative imports are resolved from the current package
+            let base = match import.level {
+                0 => path.clone(),
+                level => {
+                    let mut base = package.to_string();
+                    for _ in 1..level {
+                        base = parent(&base).unwrap_or_default();
+                    }
+
+                    match (base.is_empty(), path.is_empty()) {
+                        (true, _) => path.clone(),
+                        (false, true) => base,
+                        (false, false) => format!("{}.{}", base, path),
+                    }
+                }
+            };
+
+            if base.is_empty() {
+                continue;
+            }
+
+            // `from package import module` imports a submodule, anything
+            // else is an attribute of the module itself
+            let mut attributes = import.names.is_empty();
+            for name in &import.names {
+                let candidate = format!("{}.{}", base, name);
+                match self.is_first_party(&candidate) {
+                    true => targets.push(candidate),
+                    false => attributes = true,
+                }
+            }
+
+            if !attributes {
+                continue;
+            }
+
+            match self.first_party_prefix(&base) {
+                Some(module) => targets.push(module),
+                None if import.level > 0 => {},
+                None => {
+                    let top = base.split('.').next().unwrap_or("").to_string();
+                    if !Parser::is_built_in(&top) {
+                        targets.push(top);
+                    }
+                }
+            }
+        }
+
+        targets.sort();
+        targets.dedup();
+        targets
+    }
+
+    /// Check whether a module is one of the project's own modules
+    fn is_first_party(&self, module: &str) -> bool {
+        self.nodes.get(module).map(|x| !x.third_party).unwrap_or(false)
+    }
+
+    /// Get the longest first-party module a dotted path starts with
+    fn first_party_prefix(&self, path: &str) -> Option<String> {
+        let mut current = Some(path.to_string());
+
+        while let Some(module) = current {
+            if self.is_first_party(&module) {
+                return Some(module);
+            }
+            current = parent(&module);
+        }
+
+        None
+    }
+
+    /// Merge every module into its package
+    pub fn collapse(self) -> ModuleGraph {
+        let packages: BTreeSet<String> = self.nodes
+            .values()
+            .filter(|x| x.file.as_ref().map(|f| f.ends_with("__init__.py")).unwrap_or(false))
+            .map(|x| x.name.clone())
+            .collect();
+
+        let package_of = |name: &str| -> String {
+            match packages.contains(name) {
+                true => name.to_string(),
+                false => parent(name)
+                    .filter(|x| packages.contains(x))
+                    .unwrap_or_else(|| name.to_string()),
+            }
+        };
+
+        let mut nodes: BTreeMap<String, Node> = BTreeMap::new();
+        for node in self.nodes.into_values() {
+            let name = package_of(&node.name);
+            let file = match node.file {
+                Some(file) if name == node.name => Some(file),
+                Some(file) => file.parent().map(|x| x.join("__init__.py")),
+                None => None,
+            };
+
+            nodes.entry(name.clone()).or_insert(Node {
+                name,
+                file,
+                third_party: node.third_party,
+            });
+        }
+
+        let edges = self.edges
+            .into_iter()
+            .map(|x| Edge {
+                from: package_of(&x.from),
+                to: package_of(&x.to),
+                file: x.file,
+                line: x.line,
+                deferred: x.deferred,
+                type_checking: x.type_checking,
+            })
+            .filter(|x| x.from != x.to)
+            .collect();
+
+        ModuleGraph { nodes, edges, diagnostics: self.diagnostics }
+    }
+
+    /// Remove third-party modules from the graph
+    pub fn without_third_party(self) -> ModuleGraph {
+        let nodes: BTreeMap<String, Node> = self.nodes
+            .into_iter()
+            .filter(|(_, x)| !x.third_party)
+            .collect();
+
+        let edges = self.edges
+            .into_iter()
+            .filter(|x| nodes.contains_key(&x.to))
+            .collect();
+
+        ModuleGraph { nodes, edges, diagnostics: self.diagnostics }
+    }
+
+    /// Unique (from, to) pairs of the graph
+    pub fn links(&self) -> BTreeMap<(String, String), Vec<&Edge>> {
+        let mut links: BTreeMap<(String, String), Vec<&Edge>> = BTreeMap::new();
+
+        for edge in &self.edges {
+            links
+                .entry((edge.from.clone(), edge.to.clone()))
+                .or_default()
+                .push(edge);
+        }
+
+        links
+    }
+
+    /// Every module a module depends on, directly or not
+    pub fn dependencies(&self, module: &str) -> BTreeSet<String> {
+        self.reachable(module, |x| (&x.from, &x.to))
+    }
+
+    /// Every module depending on a module, directly or not
+    pub fn dependents(&self, module: &str) -> BTreeSet<String> {
+        self.reachable(module, |x| (&x.to, &x.from))
+    }
+
+    /// Walk the graph from a module, following edges in one direction
+    fn reachable(&self, module: &str, direction: fn(&Edge) -> (&String, &String)) -> BTreeSet<String> {
+        let mut found: BTreeSet<String> = BTreeSet::new();
+        let mut stack: Vec<String> = vec![module.to_string()];
+
+        while let Some(current) = stack.pop() {
+            for edge in &self.edges {
+                let (from, to) = direction(edge);
+                if from == &current && to != module && found.insert(to.clone()) {
+                    stack.push(to.clone());
+                }
+            }
+        }
+
+        found
+    }
+}
+
+/// Get the dotted module name of a file under the source root
+pub fn module_name(root: &Path, file: &Path) -> Option<String> {
+    let relative = file.strip_prefix(root).ok()?;
+
+    let mut parts: Vec<String> = relative
+        .iter()
+        .map(|x| x.to_string_lossy().to_string())
+        .collect();
+
+    let last = parts.pop()?;
+    let stem = last.strip_suffix(".py")?;
+
+    if stem != "__init__" {
+        parts.push(stem.to_string());
+    }
+
+    match parts.is_empty() {
+        true => None,
+        false => Some(parts.join(".")),
+    }
+}
+
+/// Get the parent of a dotted module name
+pub fn parent(module: &str) -> Option<String> {
+    module.rfind('.').map(|x| module[..x].to_string())
+}
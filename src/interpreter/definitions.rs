@@ -0,0 +1,121 @@
+//! Find the classes and functions defined in a python file
+
+use super::syntax::{bracket_depth, strip_strings};
+
+/// A class or function definition
+pub struct Definition {
+    pub name: String,
+    /// Line number (starting at 1) of the `class` or `def` statement
+    pub line: usize,
+    /// Whether it's a class (else a function or method)
+    pub class: bool,
+    /// Base classes of a class as written (`unittest.TestCase`), without
+    /// keyword arguments like `metaclass=`
+    pub bases: Vec<String>,
+    /// Classes and functions it's defined in, outermost first
+    pub scope: Vec<String>,
+}
+
+/// Find every class and function defined in python source lines
+pub fn find_definitions(lines: &[String]) -> Vec<Definition> {
+    let mut definitions: Vec<Definition> = Vec::new();
+    let mut string: Option<String> = None;
+    // Indents and names of the definitions we are currently in
+    let mut scope: Vec<(usize, String)> = Vec::new();
+
+    let mut i = 0;
+    while i < lines.len() {
+        let line_no = i + 1;
+        let in_string = string.is_some();
+        let (mut code, _) = strip_strings(&lines[i], &mut string);
+        i += 1;
+
+        let trimmed = code.trim_start();
+        // Lines continuing a string don't start statements
+        if in_string || trimmed.is_empty() {
+            continue;
+        }
+
+        let indent = code.len() - trimmed.len();
+        scope.retain(|(x, _)| *x < indent);
+
+        let trimmed = trimmed.trim_end().to_string();
+        let (class, rest) = match trimmed.strip_prefix("class ") {
+            Some(rest) => (true, rest),
+            None => match trimmed.strip_prefix("def ").or_else(|| trimmed.strip_prefix("async def ")) {
+                Some(rest) => (false, rest),
+                None => continue,
+            },
+        };
+
+        let name: String = rest
+            .trim_start()
+            .chars()
+            .take_while(|c| c.is_alphanumeric() || *c == '_')
+            .collect();
+
+        if name.is_empty() {
+            continue;
+        }
+
+        let mut bases: Vec<String> = Vec::new();
+
+        if class {
+            // Join base lists split over several lines
+            while bracket_depth(&code) > 0 && i < lines.len() {
+                code.push(' ');
+                code.push_str(&strip_strings(&lines[i], &mut string).0);
+                i += 1;
+            }
+
+            bases = base_classes(&code);
+        }
+
+        definitions.push(Definition {
+            name: name.clone(),
+            line: line_no,
+            class,
+            bases,
+            scope: scope.iter().map(|(_, x)| x.clone()).collect(),
+        });
+
+        scope.push((indent, name));
+    }
+
+    definitions
+}
+
+/// Base classes listed in a class statement
+fn base_classes(code: &str) -> Vec<String> {
+    let start = match code.find('(') {
+        Some(x) => x + 1,
+        None => return Vec::new(),
+    };
+
+    let mut bases: Vec<String> = Vec::new();
+    let mut current = String::new();
+    let mut depth = 0;
+
+    for c in code[start..].chars() {
+        match c {
+            '(' | '[' | '{' => depth += 1,
+            ')' | ']' | '}' if depth == 0 => break,
+            ')' | ']' | '}' => depth -= 1,
+            ',' if depth == 0 => {
+                bases.push(current.clone());
+                current.clear();
+                continue;
+            },
+            _ => {},
+        }
+
+        current.push(c);
+    }
+    bases.push(current);
+
+    bases
+        .into_iter()
+        .map(|x| x.trim().to_string())
+        .filter(|x| !x.is_empty() && !x.contains('='))
+        .collect()
+}
@@ -1,20 +1,52 @@
 //! Read and interpret Python (.py) files
 
 mod constants;
+pub mod definitions;
+pub mod notebook;
+pub mod source;
+pub mod syntax;
 
 use std::option::Option::{self, Some, None};
 
 use std::collections::HashMap;
 use std::process::Command;
 use std::path::Path;
-use std::io::{self, BufReader, BufRead};
-use std::fs::File;
+
+use crate::package::requirement::Requirement;
+use crate::package::specifier::Operator;
+
+use definitions::Definition;
+use notebook::Notebook;
+use source::Diagnostic;
+use syntax::Feature;
 
 /// Parse python files
 pub struct Parser<'a> {
     path: &'a Path,
 }
 
+/// A single import statement found in a python file
+#[derive(Clone)]
+pub struct Import {
+    /// Top-level modules imported by the statement
+    /// (empty for relative imports)
+    pub modules: Vec<String>,
+    /// Full dotted module paths, i.e `a.b` for `import a.b` and
+    /// `from a.b import c` (without the dots of relative imports)
+    pub paths: Vec<String>,
+    /// Names imported by a `from module import a, b` statement
+    pub names: Vec<String>,
+    /// Number of leading dots of a relative import
+    pub level: usize,
+    /// Line number (starting at 1) of the statement
+    pub line: usize,
+    /// Whether the import is only made under `if TYPE_CHECKING:`
+    pub type_checking: bool,
+    /// Whether the import is made inside a function, so only runs
+    /// when the function is called
+    pub deferred: bool,
+}
+
 impl<'a> Parser<'a> {
     /// Static method to create a new parser for a single file
     pub fn new(path: &Path) -> Parser {
@@ -23,10 +55,146 @@ impl<'a> Parser<'a> {
         }
     }
 
-    /// Create a line iterator for the file
-    pub fn lines(&self) -> io::Lines<io::BufReader<File>> {
-        let file = File::open(self.path).unwrap();
-        BufReader::new(file).lines()
+    /// Read and decode the lines of the file
+    pub fn lines(&self) -> Result<Vec<String>, Diagnostic> {
+        let text = source::read_source(self.path)?;
+
+        Ok(source::split_lines(&text))
+    }
+
+    /// Read the file's python source lines (only the code cells of notebooks)
+    fn source_lines(&self) -> Result<Vec<String>, Diagnostic> {
+        match Parser::is_notebook(self.path) {
+            true => Ok(Notebook::load(self.path)?.code_lines()),
+            false => self.lines(),
+        }
+    }
+
+    /// Find every import statement in the file
+    pub fn imports(&self) -> Result<Vec<Import>, Diagnostic> {
+        let mut imports: Vec<Import> = Vec::new();
+
+        // Indent of the `if TYPE_CHECKING:` block we are currently in
+        let mut type_checking: Option<usize> = None;
+        // Indents of the functions we are currently in
+        let mut functions: Vec<usize> = Vec::new();
+        // Indent of a function whose signature spans several lines
+        let mut signature: Option<usize> = None;
+
+        // Triple-quoted string, brackets and `\` continuing onto the next line
+        let mut string: Option<String> = None;
+        let mut depth = 0;
+        let mut continued = false;
+
+        let mut lines = self.source_lines()?.into_iter().enumerate();
+
+        while let Some((i, line)) = lines.next() {
+            let inside = string.is_some() || depth > 0 || continued;
+            let (code, _) = syntax::strip_strings(&line, &mut string);
+            depth = (depth + syntax::bracket_depth(&code)).max(0);
+            continued = code.trim_end().ends_with('\\');
+
+            // Lines continuing a statement don't start one; a function's
+            // body starts after the `:` closing its signature
+            if inside {
+                if let Some(indent) = signature {
+                    if depth == 0 && !continued && code.trim_end().ends_with(':') {
+                        functions.push(indent);
+                        signature = None;
+                    }
+                }
+                continue;
+            }
+
+            let cleaned = Parser::remove_indent(&line);
+            if cleaned.is_empty() || cleaned.starts_with('#') {
+                continue;
+            }
+
+            let indent = line.len() - cleaned.len();
+
+            // Leaving the block
+            if let Some(block) = type_checking {
+                if indent <= block {
+                    type_checking = None;
+                }
+            }
+
+            functions.retain(|&x| x < indent);
+
+            if Parser::is_type_checking_block(&cleaned) {
+                type_checking = Some(indent);
+                continue;
+            }
+
+            if cleaned.starts_with("def ") || cleaned.starts_with("async def ") {
+                match depth > 0 || continued {
+                    true => signature = Some(indent),
+                    false => functions.push(indent),
+                }
+                continue;
+            }
+
+            if Parser::is_import(&cleaned) {
+                // Join statements split over several lines
+                let mut statement = cleaned.clone();
+                while Parser::is_continued(&statement) {
+                    match lines.next() {
+                        Some((_, next)) => {
+                            let (code, _) = syntax::strip_strings(&next, &mut string);
+                            depth = (depth + syntax::bracket_depth(&code)).max(0);
+                            continued = code.trim_end().ends_with('\\');
+
+                            statement = statement.trim_end().trim_end_matches('\\').to_string();
+                            statement.push(' ');
+                            statement.push_str(next.trim());
+                        },
+                        None => break,
+                    }
+                }
+
+                let modules = Parser::get_import_module(&statement).unwrap_or_default();
+
+                if let Some((paths, names, level)) = Parser::parse_statement(&statement) {
+                    imports.push(Import {
+                        modules,
+                        paths,
+                        names,
+                        level,
+                        line: i + 1,
+                        type_checking: type_checking.is_some(),
+                        deferred: !functions.is_empty(),
+                    });
+                }
+            }
+        }
+
+        Ok(imports)
+    }
+
+    /// Find every syntax and standard library feature used in the file
+    /// which needs a minimum version of Python
+    pub fn features(&self) -> Result<Vec<Feature>, Diagnostic> {
+        let lines = self.source_lines()?;
+
+        let mut features = syntax::syntax_features(&lines);
+        features.append(&mut syntax::stdlib_features(&self.imports()?));
+        features.sort_by_key(|x| x.line);
+
+        Ok(features)
+    }
+
+    /// Find every class and function defined in the file
+    pub fn definitions(&self) -> Result<Vec<Definition>, Diagnostic> {
+        Ok(definitions::find_definitions(&self.source_lines()?))
+    }
+
+    /// Static method to identify whether a path is a Jupyter notebook
+    pub fn is_notebook(path: &Path) -> bool {
+        match path.extension() {
+            Some(ext) => ext == "ipynb",
+            None => false,
+        }
     }
 
     /// Static method to identify whether a module is built-in
@@ -51,19 +219,21 @@ impl<'a> Parser<'a> {
     }
 
     /// Static method to get the module(s) from an import line
+    /// 
+    /// Relative imports (`from . import x`) are local to the project
+    /// and are not returned.
     pub fn get_import_module(line: &String) -> Option<Vec<String>> {
         let line_c = line.clone();
-        let mut iter = line_c.split(" ");
+        let mut iter = line_c.split_whitespace();
 
         match iter.next() {
             Some("import") => {
-                // import module
-                let modules_spaced: String = iter.collect();
-
-                let modules_unspaced = modules_spaced.replace(' ', "");
+                // import module, module2 as alias
+                let modules_spaced: String = iter.collect::<Vec<&str>>().join(" ");
 
-                let module_iter: Vec<String> = modules_unspaced
+                let module_iter: Vec<String> = modules_spaced
                     .split(",")
+                    .filter_map(|x| x.split_whitespace().next())
                     .map(|x| Parser::shorten_module(&x.to_string()))
                     .collect();
                 
@@ -76,6 +246,10 @@ impl<'a> Parser<'a> {
                     None => {return None;},
                 };
 
+                if module.starts_with('.') {
+                    return None;
+                }
+
                 let shortened = Parser::shorten_module(&module);
                 let vec: Vec<String> = vec![shortened];
 
@@ -85,9 +259,57 @@ impl<'a> Parser<'a> {
         }
     }                                
 
+    /// Static method to get the full module paths, imported names and
+    /// relative import level of an import statement
+    fn parse_statement(statement: &str) -> Option<(Vec<String>, Vec<String>, usize)> {
+        let statement = statement.split('#').next().unwrap_or("").replace(['(', ')'], " ");
+        let mut iter = statement.split_whitespace();
+
+        match iter.next() {
+            Some("import") => {
+                let rest = iter.collect::<Vec<&str>>().join(" ");
+
+                let paths = rest
+                    .split(',')
+                    .filter_map(|x| x.split_whitespace().next())
+                    .map(|x| x.to_string())
+                    .collect();
+
+                Some((paths, Vec::new(), 0))
+            },
+            Some("from") => {
+                let module = iter.next()?;
+                let level = module.len() - module.trim_start_matches('.').len();
+
+                if iter.next() != Some("import") {
+                    return None;
+                }
+
+                let rest = iter.collect::<Vec<&str>>().join(" ");
+
+                let names = rest
+                    .split(',')
+                    .filter_map(|x| x.split_whitespace().next())
+                    .map(|x| x.to_string())
+                    .collect();
+
+                Some((vec![module[level..].to_string()], names, level))
+            },
+            _ => None,
+        }
+    }
+
+    /// Static method to identify whether a statement continues on the next line
+    fn is_continued(statement: &str) -> bool {
+        let code = statement.split('#').next().unwrap_or("").trim_end();
+
+        code.ends_with('\\') || (code.contains('(') && !code.contains(')'))
+    }
+
     /// Static method to convert a vector of installed modules into a HashMap with the versions respectively
     pub fn with_versions(modules: &Vec<String>) -> HashMap<String, String> {
-        let pip_freeze = match Command::new("cmd").args(&["/c", "pip freeze -q -q -q"]).output() {
+        // Run pip through the `python` on path so it works without a shell
+        let pip_freeze = match Command::new("python").args(["-m", "pip", "freeze", "-q", "-q", "-q"]).output() {
             Ok(val) => String::from_utf8(val.stdout).unwrap(),
             Err(err) => {
                 println!("WARNING: No installed modules found (pip may not be installed on path): {}", err);
@@ -116,35 +338,33 @@ impl<'a> Parser<'a> {
     }
 
     /// Static method to format raw stdout from pip freeze
+    ///
+    /// Only pinned packages (`name==1.0`) have a version, editable
+    /// (`-e`) and direct URL (`name @ file://...`) installs are skipped.
     fn format_modules(mods: String) -> HashMap<String, String> {
-        let list: Vec<String> = mods
-            .split("\r\n")
-            .map(|x| x.to_string())
-            .collect();
-        
         let mut modules: HashMap<String, String> = HashMap::new();
 
-        for module in &list {
-            let mut split = module.split("==");
-            let mod_name = match split.next() {
-                Some(n) => {
-                    n.to_string()
-                },
-                None => {continue;}
+        for module in mods.lines() {
+            let requirement = match Requirement::parse(module) {
+                Ok(r) => r,
+                Err(_) => {continue;}
             };
 
-            let version = match split.next() {
-                Some(v) => v.to_string(),
-                None => {continue;}
+            let version = match requirement.specifier.specifiers.as_slice() {
+                [pin] if matches!(pin.operator, Operator::Equal | Operator::Arbitrary) && !pin.wildcard => {
+                    pin.version.clone()
+                },
+                _ => {continue;}
             };
 
-            modules.insert(mod_name, version);
+            modules.insert(requirement.name, version);
         }
 
         modules
     }
 
-    fn convert_common_mods(s: &String) -> String {
+    /// Get the package installing a module (`bs4` is installed by `beautifulsoup4`)
+    pub fn convert_common_mods(s: &str) -> String {
         for i in constants::COMMON_MODS {
             if i[0] == s {
                 return i[1].to_owned();
@@ -154,6 +374,17 @@ impl<'a> Parser<'a> {
         return s.to_owned();
     }
 
+    /// Static method to identify the start of an `if TYPE_CHECKING:` block
+    fn is_type_checking_block(line: &str) -> bool {
+        match line.strip_prefix("if ") {
+            Some(condition) => {
+                let condition = condition.trim_end().trim_end_matches(':').trim();
+                condition == "TYPE_CHECKING" || condition.ends_with(".TYPE_CHECKING")
+            },
+            None => false,
+        }
+    }
+
     /// Static method to shorten a module name to its module
     /// i.e "module.attrib" to "module"
     fn shorten_module(module_name: &String) -> String {
@@ -181,4 +412,48 @@ impl<'a> Parser<'a> {
 
         new_s
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    /// Find the imports of python source written to a temporary file
+    fn imports(name: &str, source: &str) -> Vec<Import> {
+        let path = std::env::temp_dir().join(format!("pyproj-{}-{}.py", name, std::process::id()));
+        fs::write(&path, source).unwrap();
+        let imports = Parser::new(&path).imports();
+        fs::remove_file(&path).unwrap();
+
+        imports.ok().unwrap()
+    }
+
+    #[test]
+    fn multi_line_signatures_start_functions() {
+        let found = imports("signature", "def f(\n    x,\n):\n    from pkg import a\n\nimport b\n");
+
+        assert_eq!(found.len(), 2);
+        assert_eq!((found[0].paths[0].as_str(), found[0].deferred), ("pkg", true));
+        assert_eq!((found[1].paths[0].as_str(), found[1].deferred), ("b", false));
+    }
+
+    #[test]
+    fn continuation_lines_dont_start_statements() {
+        let source = "from a import (\n    b,\n    c,\n)\nx = call(\n    1,\n)\n\"\"\"\nimport not_an_import\n\"\"\"\nimport d\n";
+        let found = imports("continuation", source);
+
+        let paths: Vec<&str> = found.iter().map(|x| x.paths[0].as_str()).collect();
+        assert_eq!(paths, ["a", "d"]);
+        assert_eq!(found[0].names, ["b", "c"]);
+        assert!(found.iter().all(|x| !x.deferred));
+    }
+
+    #[test]
+    fn relative_imports_keep_an_empty_path() {
+        let found = imports("relative", "from . import b\n");
+
+        assert_eq!(found[0].paths, [""]);
+        assert_eq!((found[0].level, found[0].names.as_slice()), (1, &[String::from("b")][..]));
+    }
+}
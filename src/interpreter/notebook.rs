@@ -0,0 +1,220 @@
+//! Read Jupyter notebook (.ipynb) files
+
+use std::path::Path;
+
+use serde_json::Value;
+
+use super::source::{self, Diagnostic};
+
+/// Cell magics whose body is still python code
+const PYTHON_CELL_MAGICS: [&str; 4] = ["time", "timeit", "capture", "prun"];
+
+/// A single notebook cell
+pub struct Cell {
+    /// Cell type (code, markdown or raw)
+    pub kind: String,
+    /// Lines of the cell's source, without line endings
+    pub source: Vec<String>,
+}
+
+/// A parsed Jupyter notebook
+pub struct Notebook {
+    pub cells: Vec<Cell>,
+}
+
+impl Notebook {
+    /// Load and parse a notebook file
+    pub fn load(path: &Path) -> Result<Notebook, Diagnostic> {
+        let content = source::read_source(path)?;
+
+        Notebook::parse(&content).map_err(|message| Diagnostic {
+            path: path.to_path_buf(),
+            line: None,
+            message,
+        })
+    }
+
+    /// Parse notebook JSON
+    pub fn parse(content: &str) -> Result<Notebook, String> {
+        let json: Value = serde_json::from_str(content)
+            .map_err(|e| format!("invalid notebook JSON: {}", e))?;
+
+        let cells = match json.get("cells").and_then(|x| x.as_array()) {
+            Some(x) => x,
+            None => return Err(String::from("notebook has no cells")),
+        };
+
+        let cells = cells
+            .iter()
+            .map(|cell| Cell {
+                kind: cell
+                    .get("cell_type")
+                    .and_then(|x| x.as_str())
+                    .unwrap_or("raw")
+                    .to_string(),
+                source: Notebook::source_lines(cell.get("source")),
+            })
+            .collect();
+
+        Ok(Notebook { cells })
+    }
+
+    /// Get the python lines of every code cell, skipping
+    /// shell (`!pip`) and magic (`%magic`) lines
+    pub fn code_lines(&self) -> Vec<String> {
+        let mut lines: Vec<String> = Vec::new();
+
+        for cell in self.code_cells() {
+            for line in &cell.source {
+                if !Notebook::is_magic(line) {
+                    lines.push(line.clone());
+                }
+            }
+        }
+
+        lines
+    }
+
+    /// Convert the notebook into a python module
+    ///
+    /// Cells are separated by `# %%` markers, markdown is kept as comments
+    /// and shell/magic lines are commented out.
+    pub fn to_python(&self) -> String {
+        let mut out = String::new();
+
+        for cell in &self.cells {
+            if cell.source.iter().all(|x| x.trim().is_empty()) {
+                continue;
+            }
+
+            match cell.kind.as_str() {
+                "code" => {
+                    out.push_str("# %%\n");
+                    let python = Notebook::is_python_cell(cell);
+
+                    for line in &cell.source {
+                        if !python || Notebook::is_magic(line) {
+                            out.push_str("# ");
+                        }
+                        out.push_str(line);
+                        out.push('\n');
+                    }
+                },
+                _ => {
+                    out.push_str("# %% [markdown]\n");
+
+                    for line in &cell.source {
+                        match line.is_empty() {
+                            true => out.push_str("#\n"),
+                            false => out.push_str(&format!("# {}\n", line)),
+                        }
+                    }
+                }
+            }
+
+            out.push('\n');
+        }
+
+        out
+    }
+
+    /// Code cells containing python
+    fn code_cells(&self) -> impl Iterator<Item = &Cell> {
+        self.cells
+            .iter()
+            .filter(|x| x.kind == "code" && Notebook::is_python_cell(x))
+    }
+
+    /// Check whether a code cell contains python (and not, say, `%%bash`)
+    fn is_python_cell(cell: &Cell) -> bool {
+        let first = cell
+            .source
+            .iter()
+            .find(|x| !x.trim().is_empty());
+
+        match first.and_then(|x| x.trim_start().strip_prefix("%%")) {
+            Some(magic) => {
+                let name = magic.split_whitespace().next().unwrap_or("");
+                PYTHON_CELL_MAGICS.contains(&name)
+            },
+            None => true,
+        }
+    }
+
+    /// Check whether a line is a shell command or IPython magic
+    fn is_magic(line: &str) -> bool {
+        line.trim_start().starts_with(['!', '%'])
+    }
+
+    /// Notebook sources are either a string or a list of strings
+    fn source_lines(source: Option<&Value>) -> Vec<String> {
+        let text = match source {
+            Some(Value::String(s)) => s.clone(),
+            Some(Value::Array(parts)) => parts
+                .iter()
+                .filter_map(|x| x.as_str())
+                .collect::<Vec<&str>>()
+                .concat(),
+            _ => String::new(),
+        };
+
+        text.lines().map(|x| x.to_string()).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOTEBOOK: &str = r##"{
+        "cells": [
+            {"cell_type": "markdown", "source": ["# Analysis\n", "\n", "Load the data"]},
+            {"cell_type": "code", "source": ["!pip install pandas\n", "%matplotlib inline\n", "import pandas as pd\n", "  %time x = 1"]},
+            {"cell_type": "code", "source": "%%bash\nimport not_python\n"},
+            {"cell_type": "code", "source": "%%timeit\nimport numpy\n"},
+            {"cell_type": "code", "source": ["\n"]}
+        ]
+    }"##;
+
+    #[test]
+    fn code_lines_skip_shell_and_magic() {
+        let notebook = Notebook::parse(NOTEBOOK).unwrap();
+
+        assert_eq!(notebook.cells.len(), 5);
+        assert_eq!(notebook.code_lines(), ["import pandas as pd", "import numpy", ""]);
+    }
+
+    #[test]
+    fn invalid_notebooks_are_errors() {
+        assert!(Notebook::parse("not json").is_err());
+        assert!(Notebook::parse("{\"metadata\": {}}").is_err());
+        assert!(Notebook::parse("{\"cells\": []}").unwrap().code_lines().is_empty());
+    }
+
+    #[test]
+    fn export_comments_out_everything_but_python() {
+        let notebook = Notebook::parse(NOTEBOOK).unwrap();
+
+        assert_eq!(notebook.to_python(), "\
+# %% [markdown]
+# # Analysis
+#
+# Load the data
+
+# %%
+# !pip install pandas
+# %matplotlib inline
+import pandas as pd
+#   %time x = 1
+
+# %%
+# %%bash
+# import not_python
+
+# %%
+# %%timeit
+import numpy
+
+");
+    }
+}
@@ -0,0 +1,293 @@
+//! Read python source files, detecting their encoding the same way
+//! CPython does (a BOM, then a PEP 263 coding cookie in the first two lines)
+
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Characters 0x80 to 0x9F of Windows-1252 (the rest match Latin-1)
+const CP1252: [Option<char>; 32] = [
+    Some('\u{20AC}'), None, Some('\u{201A}'), Some('\u{0192}'),
+    Some('\u{201E}'), Some('\u{2026}'), Some('\u{2020}'), Some('\u{2021}'),
+    Some('\u{02C6}'), Some('\u{2030}'), Some('\u{0160}'), Some('\u{2039}'),
+    Some('\u{0152}'), None, Some('\u{017D}'), None,
+    None, Some('\u{2018}'), Some('\u{2019}'), Some('\u{201C}'),
+    Some('\u{201D}'), Some('\u{2022}'), Some('\u{2013}'), Some('\u{2014}'),
+    Some('\u{02DC}'), Some('\u{2122}'), Some('\u{0161}'), Some('\u{203A}'),
+    Some('\u{0153}'), None, Some('\u{017E}'), Some('\u{0178}'),
+];
+
+/// A problem found while reading or parsing a file
+#[derive(Clone)]
+pub struct Diagnostic {
+    pub path: PathBuf,
+    /// Line number (starting at 1) the problem is on, if known
+    pub line: Option<usize>,
+    pub message: String,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "{}:{}: {}", self.path.display(), line, self.message),
+            None => write!(f, "{}: {}", self.path.display(), self.message),
+        }
+    }
+}
+
+/// Supported source encodings
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum Encoding {
+    Utf8,
+    Utf16Le,
+    Utf16Be,
+    Latin1,
+    Ascii,
+    Cp1252,
+}
+
+/// Read and decode a source file
+pub fn read_source(path: &Path) -> Result<String, Diagnostic> {
+    let diagnostic = |line: Option<usize>, message: String| Diagnostic {
+        path: path.to_path_buf(),
+        line,
+        message,
+    };
+
+    let bytes = fs::read(path).map_err(|e| diagnostic(None, format!("couldn't read file: {}", e)))?;
+
+    let (encoding, bom) = detect_encoding(&bytes).map_err(|e| diagnostic(None, e))?;
+
+    decode(&bytes[bom..], encoding).map_err(|(offset, e)| {
+        // Line number of the undecodable byte, counted in the text
+        // before it (which decodes fine)
+        let line = decode(&bytes[bom..bom + offset], encoding)
+            .ok()
+            .map(|x| line_count(&x));
+        diagnostic(line, e)
+    })
+}
+
+/// Get the line number of the end of some text, treating `\r\n`,
+/// `\r` and `\n` the same
+fn line_count(text: &str) -> usize {
+    text.replace("\r\n", "\n").matches(['\n', '\r']).count() + 1
+}
+
+/// Split text into lines, treating `\r\n`, `\r` and `\n` the same
+pub fn split_lines(text: &str) -> Vec<String> {
+    let mut lines: Vec<String> = text
+        .replace("\r\n", "\n")
+        .split(['\n', '\r'])
+        .map(|x| x.to_string())
+        .collect();
+
+    // A trailing newline doesn't start another line
+    if lines.last().map(|x| x.is_empty()).unwrap_or(false) {
+        lines.pop();
+    }
+
+    lines
+}
+
+/// Detect the encoding of a file and the length of its BOM
+fn detect_encoding(bytes: &[u8]) -> Result<(Encoding, usize), String> {
+    if bytes.starts_with(&[0xFF, 0xFE]) {
+        return Ok((Encoding::Utf16Le, 2));
+    }
+
+    if bytes.starts_with(&[0xFE, 0xFF]) {
+        return Ok((Encoding::Utf16Be, 2));
+    }
+
+    let bom = match bytes.starts_with(&[0xEF, 0xBB, 0xBF]) {
+        true => 3,
+        false => 0,
+    };
+
+    // The cookie may only be on the first line, or the second
+    // line if the first is blank or a comment
+    let mut lines = bytes[bom..].split(|&x| x == b'\n' || x == b'\r');
+    let mut cookie = None;
+
+    if let Some(first) = lines.next() {
+        cookie = coding_cookie(first);
+
+        if cookie.is_none() && is_blank_or_comment(first) {
+            cookie = lines.next().and_then(coding_cookie);
+        }
+    }
+
+    let encoding = match cookie {
+        Some(name) => match encoding_from_name(&name) {
+            Some(x) => x,
+            None => return Err(format!("unknown encoding '{}'", name)),
+        },
+        None => Encoding::Utf8,
+    };
+
+    if bom > 0 && encoding != Encoding::Utf8 {
+        return Err(format!("encoding problem: {:?} with BOM", encoding));
+    }
+
+    Ok((encoding, bom))
+}
+
+/// Find a PEP 263 coding cookie (`# -*- coding: latin-1 -*-`) in a line
+fn coding_cookie(line: &[u8]) -> Option<String> {
+    let line = String::from_utf8_lossy(line);
+    let comment = line.trim_start_matches([' ', '\t', '\x0c']).strip_prefix('#')?;
+
+    let at = comment.find("coding")?;
+    let rest = comment[at + 6..].strip_prefix([':', '='])?;
+
+    let name: String = rest
+        .trim_start_matches([' ', '\t'])
+        .chars()
+        .take_while(|c| c.is_alphanumeric() || *c == '-' || *c == '_' || *c == '.')
+        .collect();
+
+    match name.is_empty() {
+        true => None,
+        false => Some(name),
+    }
+}
+
+fn is_blank_or_comment(line: &[u8]) -> bool {
+    let trimmed: Vec<u8> = line
+        .iter()
+        .skip_while(|&&x| x == b' ' || x == b'\t' || x == 0x0c)
+        .copied()
+        .collect();
+
+    trimmed.is_empty() || trimmed[0] == b'#'
+}
+
+/// Get an encoding from its name (as CPython normalises them)
+fn encoding_from_name(name: &str) -> Option<Encoding> {
+    let name = name.to_lowercase().replace('_', "-");
+
+    if name == "utf-8" || name.starts_with("utf-8-") || name == "utf8" {
+        return Some(Encoding::Utf8);
+    }
+
+    if ["latin-1", "iso-8859-1", "iso-latin-1"]
+        .iter()
+        .any(|x| name == *x || name.starts_with(&format!("{}-", x)))
+        || name == "latin1" || name == "iso8859-1" || name == "l1"
+    {
+        return Some(Encoding::Latin1);
+    }
+
+    match name.as_str() {
+        "ascii" | "us-ascii" => Some(Encoding::Ascii),
+        "cp1252" | "windows-1252" => Some(Encoding::Cp1252),
+        "utf-16" | "utf-16-le" | "utf-16le" => Some(Encoding::Utf16Le),
+        "utf-16-be" | "utf-16be" => Some(Encoding::Utf16Be),
+        _ => None,
+    }
+}
+
+/// Decode bytes, returning the offset of the first undecodable byte on error
+fn decode(bytes: &[u8], encoding: Encoding) -> Result<String, (usize, String)> {
+    match encoding {
+        Encoding::Utf8 => match std::str::from_utf8(bytes) {
+            Ok(x) => Ok(x.to_string()),
+            Err(e) => Err((
+                e.valid_up_to(),
+                format!("invalid utf-8 byte 0x{:02X} (add a `# -*- coding: ... -*-` line if the file isn't utf-8)", bytes[e.valid_up_to()]),
+            )),
+        },
+        Encoding::Latin1 => Ok(bytes.iter().map(|&x| x as char).collect()),
+        Encoding::Ascii => bytes
+            .iter()
+            .enumerate()
+            .map(|(i, &x)| match x.is_ascii() {
+                true => Ok(x as char),
+                false => Err((i, format!("invalid ascii byte 0x{:02X}", x))),
+            })
+            .collect(),
+        Encoding::Cp1252 => bytes
+            .iter()
+            .enumerate()
+            .map(|(i, &x)| match x {
+                0x80..=0x9F => CP1252[(x - 0x80) as usize]
+                    .ok_or((i, format!("invalid cp1252 byte 0x{:02X}", x))),
+                _ => Ok(x as char),
+            })
+            .collect(),
+        Encoding::Utf16Le | Encoding::Utf16Be => {
+            if bytes.len() % 2 != 0 {
+                return Err((bytes.len() - 1, String::from("truncated utf-16 data")));
+            }
+
+            let units: Vec<u16> = bytes
+                .chunks(2)
+                .map(|x| match encoding {
+                    Encoding::Utf16Le => u16::from_le_bytes([x[0], x[1]]),
+                    _ => u16::from_be_bytes([x[0], x[1]]),
+                })
+                .collect();
+
+            let mut out = String::new();
+            // Byte offset of the next unit, characters outside the BMP
+            // taking two units
+            let mut offset = 0;
+            for c in char::decode_utf16(units.iter().copied()) {
+                match c {
+                    Ok(c) => {
+                        out.push(c);
+                        offset += c.len_utf16() * 2;
+                    },
+                    Err(_) => return Err((offset, String::from("invalid utf-16 surrogate"))),
+                }
+            }
+
+            Ok(out)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn utf16_errors_point_at_the_bad_unit() {
+        // `a`, U+1F600 (a surrogate pair), `b`, then a lone low surrogate
+        let units: [u16; 5] = [0x61, 0xD83D, 0xDE00, 0x62, 0xDC00];
+
+        let le: Vec<u8> = units.iter().flat_map(|x| x.to_le_bytes()).collect();
+        assert_eq!(decode(&le, Encoding::Utf16Le).map_err(|(offset, _)| offset), Err(8));
+
+        let be: Vec<u8> = units[..4].iter().flat_map(|x| x.to_be_bytes()).collect();
+        assert_eq!(decode(&be, Encoding::Utf16Be), Ok(String::from("a\u{1F600}b")));
+    }
+
+    #[test]
+    fn utf8_errors_point_at_the_bad_byte() {
+        assert_eq!(decode(b"ok \xC3\xA9 \xFF", Encoding::Utf8).map_err(|(offset, _)| offset), Err(6));
+        assert_eq!(decode(b"\x80", Encoding::Cp1252), Ok(String::from("\u{20AC}")));
+        assert!(decode(b"\x81", Encoding::Cp1252).is_err());
+    }
+
+    #[test]
+    fn errors_are_on_the_right_line() {
+        let path = std::env::temp_dir().join(format!("pyproj-source-{}.py", std::process::id()));
+        let line = |bytes: &[u8]| {
+            fs::write(&path, bytes).unwrap();
+            let line = read_source(&path).err().and_then(|x| x.line);
+            fs::remove_file(&path).unwrap();
+            line
+        };
+
+        assert_eq!(line(b"a = 1\r\nb = 2\r\n\xFF"), Some(3));
+        assert_eq!(line(b"a = 1\rb = 2\r\xFF"), Some(3));
+
+        // U+0A0A is `\n\n` in its bytes but not a line break
+        let units: [u16; 5] = [0x0A0A, 0x0A, 0x61, 0x0D, 0xDC00];
+        let mut le: Vec<u8> = vec![0xFF, 0xFE];
+        le.extend(units.iter().flat_map(|x| x.to_le_bytes()));
+        assert_eq!(line(&le), Some(3));
+    }
+}
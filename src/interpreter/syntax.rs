@@ -0,0 +1,342 @@
+//! Find syntax and standard library features which need
+//! a minimum version of Python
+
+use super::Import;
+
+/// A version-gated feature used in a file
+pub struct Feature {
+    /// Human readable name of the feature
+    pub name: String,
+    /// Python version the feature was added in
+    pub version: (u32, u32),
+    /// Line number (starting at 1) the feature is used at
+    pub line: usize,
+}
+
+/// Standard library modules and the version they were added in
+const STDLIB_MODULES: [(&str, (u32, u32)); 9] = [
+    ("secrets", (3, 6)),
+    ("contextvars", (3, 7)),
+    ("dataclasses", (3, 7)),
+    ("importlib.resources", (3, 7)),
+    ("importlib.metadata", (3, 8)),
+    ("graphlib", (3, 9)),
+    ("zoneinfo", (3, 9)),
+    ("tomllib", (3, 11)),
+    ("wsgiref.types", (3, 11)),
+];
+
+/// Names imported from standard library modules and the version they were added in
+const STDLIB_NAMES: [(&str, &str, (u32, u32)); 16] = [
+    ("typing", "NoReturn", (3, 6)),
+    ("typing", "Final", (3, 8)),
+    ("typing", "Literal", (3, 8)),
+    ("typing", "Protocol", (3, 8)),
+    ("typing", "TypedDict", (3, 8)),
+    ("typing", "Annotated", (3, 9)),
+    ("functools", "cache", (3, 9)),
+    ("typing", "ParamSpec", (3, 10)),
+    ("typing", "TypeAlias", (3, 10)),
+    ("typing", "TypeGuard", (3, 10)),
+    ("itertools", "pairwise", (3, 10)),
+    ("typing", "Self", (3, 11)),
+    ("typing", "LiteralString", (3, 11)),
+    ("typing", "Never", (3, 11)),
+    ("typing", "override", (3, 12)),
+    ("itertools", "batched", (3, 12)),
+];
+
+/// Find every version-gated syntax feature in python source lines
+pub fn syntax_features(lines: &[String]) -> Vec<Feature> {
+    let mut features: Vec<Feature> = Vec::new();
+    let mut string: Option<String> = None;
+    // Line, code and open brackets of a signature spanning several lines
+    let mut signature: Option<(usize, String, i32)> = None;
+
+    for (i, line) in lines.iter().enumerate() {
+        let (code, fstring) = strip_strings(line, &mut string);
+        let line_no = i + 1;
+        let trimmed = code.trim();
+
+        if let Some((start, mut joined, depth)) = signature.take() {
+            joined.push(' ');
+            joined.push_str(&code);
+
+            match depth + bracket_depth(&code) {
+                depth if depth > 0 => signature = Some((start, joined, depth)),
+                _ if has_positional_only(&joined) => features.push(Feature {
+                    name: String::from("positional-only parameters (/)"),
+                    version: (3, 8),
+                    line: start,
+                }),
+                _ => {},
+            }
+        } else if is_def(trimmed) && bracket_depth(&code) > 0 {
+            signature = Some((line_no, code.clone(), bracket_depth(&code)));
+        }
+
+        let mut add = |name: &str, version: (u32, u32)| {
+            features.push(Feature {
+                name: name.to_string(),
+                version,
+                line: line_no,
+            });
+        };
+
+        if fstring {
+            add("f-string", (3, 6));
+        }
+
+        if code.contains(":=") {
+            add("assignment expression (:=)", (3, 8));
+        }
+
+        if is_def(trimmed) && bracket_depth(&code) <= 0 && has_positional_only(&code) {
+            add("positional-only parameters (/)", (3, 8));
+        }
+
+        if trimmed.starts_with("match ") && trimmed.ends_with(':') && next_is_case(lines, i) {
+            add("match statement", (3, 10));
+        }
+
+        if trimmed.starts_with("except*") || trimmed.starts_with("except *") {
+            add("exception groups (except*)", (3, 11));
+        }
+
+        if is_type_alias(trimmed) || has_type_parameters(trimmed) {
+            add("type parameter syntax (PEP 695)", (3, 12));
+        }
+    }
+
+    features
+}
+
+/// Find standard library modules and names used by imports which need
+/// a minimum version of Python
+pub fn stdlib_features(imports: &[Import]) -> Vec<Feature> {
+    let mut features: Vec<Feature> = Vec::new();
+
+    for import in imports.iter().filter(|x| x.level == 0) {
+        for path in &import.paths {
+            // `from importlib import metadata` imports importlib.metadata
+            let mut modules = vec![path.clone()];
+            modules.extend(import.names.iter().map(|x| format!("{}.{}", path, x)));
+
+            for (module, version) in STDLIB_MODULES.iter() {
+                let used = modules
+                    .iter()
+                    .any(|x| x == module || x.starts_with(&format!("{}.", module)));
+
+                if used {
+                    features.push(Feature {
+                        name: format!("{} module", module),
+                        version: *version,
+                        line: import.line,
+                    });
+                }
+            }
+
+            for (module, name, version) in STDLIB_NAMES.iter() {
+                if path == module && import.names.iter().any(|x| x == name) {
+                    features.push(Feature {
+                        name: format!("{}.{}", module, name),
+                        version: *version,
+                        line: import.line,
+                    });
+                }
+            }
+        }
+    }
+
+    features
+}
+
+/// Replace the contents of strings and comments in a line with spaces,
+/// keeping track of triple-quoted strings spanning several lines
+///
+/// Also returns whether an f-string starts in the line.
+pub fn strip_strings(line: &str, string: &mut Option<String>) -> (String, bool) {
+    let chars: Vec<char> = line.chars().collect();
+    let mut code = String::new();
+    let mut fstring = false;
+    let mut i = 0;
+
+    while i < chars.len() {
+        match string {
+            Some(quote) => {
+                if chars[i] == '\\' {
+                    code.push_str("  ");
+                    i += 2;
+                    continue;
+                }
+
+                if line_has(&chars, i, quote) {
+                    code.push_str(quote);
+                    i += quote.len();
+                    *string = None;
+                    continue;
+                }
+
+                code.push(' ');
+                i += 1;
+            },
+            None => {
+                let c = chars[i];
+
+                if c == '#' {
+                    break;
+                }
+
+                if c == '"' || c == '\'' {
+                    let quote = match line_has(&chars, i, &c.to_string().repeat(3)) {
+                        true => c.to_string().repeat(3),
+                        false => c.to_string(),
+                    };
+
+                    if string_prefix(&chars, i).contains(['f', 'F']) {
+                        fstring = true;
+                    }
+
+                    code.push_str(&quote);
+                    i += quote.len();
+                    *string = Some(quote);
+                    continue;
+                }
+
+                code.push(c);
+                i += 1;
+            }
+        }
+    }
+
+    // Single-quoted strings can't span lines
+    if string.as_ref().map(|x| x.len() == 1).unwrap_or(false) {
+        *string = None;
+    }
+
+    (code, fstring)
+}
+
+/// Number of brackets a line of code (with its strings stripped) opens,
+/// negative if it closes more than it opens
+pub fn bracket_depth(code: &str) -> i32 {
+    code.chars().fold(0, |depth, c| match c {
+        '(' | '[' | '{' => depth + 1,
+        ')' | ']' | '}' => depth - 1,
+        _ => depth,
+    })
+}
+
+/// Check whether the characters starting at an index match a string
+fn line_has(chars: &[char], at: usize, s: &str) -> bool {
+    let s: Vec<char> = s.chars().collect();
+
+    chars.len() >= at + s.len() && chars[at..at + s.len()] == s[..]
+}
+
+/// Get the string prefix (r, b, f, ...) right before a quote
+fn string_prefix(chars: &[char], quote: usize) -> String {
+    let mut start = quote;
+
+    while start > 0 && "rRbBuUfF".contains(chars[start - 1]) {
+        start -= 1;
+    }
+
+    // Part of a longer name, not a prefix
+    if start > 0 && (chars[start - 1].is_alphanumeric() || chars[start - 1] == '_') {
+        return String::new();
+    }
+
+    chars[start..quote].iter().collect()
+}
+
+fn is_def(line: &str) -> bool {
+    line.starts_with("def ") || line.starts_with("async def ")
+}
+
+/// Check for a bare `/` parameter in a function definition
+fn has_positional_only(code: &str) -> bool {
+    let params = match code.find('(') {
+        Some(x) => &code[x + 1..],
+        None => return false,
+    };
+
+    params
+        .split([',', ')'])
+        .any(|x| x.trim() == "/")
+}
+
+/// Check whether the next line of code starts a `case` block
+fn next_is_case(lines: &[String], i: usize) -> bool {
+    lines[i + 1..]
+        .iter()
+        .map(|x| x.trim())
+        .find(|x| !x.is_empty() && !x.starts_with('#'))
+        .map(|x| x.starts_with("case "))
+        .unwrap_or(false)
+}
+
+/// Check for a `type Alias = ...` statement
+fn is_type_alias(line: &str) -> bool {
+    let rest = match line.strip_prefix("type ") {
+        Some(x) => x,
+        None => return false,
+    };
+
+    let name: String = rest
+        .chars()
+        .take_while(|c| c.is_alphanumeric() || *c == '_')
+        .collect();
+
+    let after = rest[name.len()..].trim_start();
+
+    !name.is_empty() && (after.starts_with('=') && !after.starts_with("==") || after.starts_with('['))
+}
+
+/// Check for `def f[T](...)` or `class C[T]:`
+fn has_type_parameters(line: &str) -> bool {
+    let rest = match line
+        .strip_prefix("async def ")
+        .or_else(|| line.strip_prefix("def "))
+        .or_else(|| line.strip_prefix("class "))
+    {
+        Some(x) => x.trim_start(),
+        None => return false,
+    };
+
+    let name: String = rest
+        .chars()
+        .take_while(|c| c.is_alphanumeric() || *c == '_')
+        .collect();
+
+    !name.is_empty() && rest[name.len()..].starts_with('[')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn features(source: &str) -> Vec<(String, usize)> {
+        let lines: Vec<String> = source.lines().map(|x| x.to_string()).collect();
+
+        syntax_features(&lines).into_iter().map(|x| (x.name, x.line)).collect()
+    }
+
+    #[test]
+    fn positional_only_parameters_are_found_across_lines() {
+        let positional = String::from("positional-only parameters (/)");
+
+        assert_eq!(features("def f(a, /, b):\n    pass\n"), [(positional.clone(), 1)]);
+        assert_eq!(
+            features("x = 1\n\ndef f(\n    a: int,\n    /,\n    b=(1, 2),\n) -> None:\n    pass\n"),
+            [(positional.clone(), 3)]
+        );
+        assert_eq!(features("async def f(a,\n            /):\n    pass\n"), [(positional, 1)]);
+    }
+
+    #[test]
+    fn divisions_and_strings_arent_positional_only() {
+        assert!(features("def f(\n    a=1 / 2,\n    b='/',\n):\n    return a / b\n").is_empty());
+        assert!(features("x = f(a,\n  /)\n").is_empty());
+    }
+}
@@ -0,0 +1,129 @@
+//! Report the licenses of the project's dependencies and check them
+//! against the project's license policy
+
+pub mod policy;
+pub mod report;
+pub mod spdx;
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::build::{Group, SETUP_CFG};
+use crate::config::SetupCfg;
+use crate::deps::find_declarations;
+use crate::package::installed::{self, required_distributions};
+use crate::package::marker::Environment;
+use crate::package::requirement::Requirement;
+use crate::report::notes;
+
+use self::policy::{LicensePolicy, Status};
+use self::spdx::{Expression, Source};
+
+/// Options of the licenses subcommand
+pub struct LicenseOptions {
+    /// Include the dev requirements (and what they require)
+    pub dev: bool,
+    pub format: String,
+    pub output: Option<String>,
+}
+
+/// License of an installed dependency
+pub struct Entry {
+    pub name: String,
+    pub version: String,
+    pub license: Option<Expression>,
+    /// Where the license was found in the metadata
+    pub source: Option<Source>,
+    pub status: Status,
+    pub author: Option<String>,
+    pub home_page: Option<String>,
+    /// License files bundled with the distribution
+    pub files: Vec<PathBuf>,
+}
+
+/// Report the licenses of the installed distributions the project's
+/// requirements need, checking them against the policy in setup.cfg
+///
+/// Returns false if a license is denied or not allowed (or unknown
+/// when there is an allow list).
+pub fn licenses(options: &LicenseOptions) -> bool {
+    let note = notes(&options.format, options.output.as_deref());
+
+    let declarations = match find_declarations() {
+        Ok(x) => x,
+        Err(e) => {
+            note(e);
+            return false;
+        }
+    };
+
+    let policy = match Path::new(SETUP_CFG).exists() {
+        true => match SetupCfg::load(Path::new(SETUP_CFG)) {
+            Ok(cfg) => LicensePolicy::from_config(&cfg),
+            Err(e) => {
+                note(format!("Error while reading {}: {}", SETUP_CFG, e));
+                return false;
+            }
+        },
+        false => LicensePolicy::default(),
+    };
+
+    let requirements: Vec<Requirement> = declarations
+        .into_iter()
+        .filter(|x| options.dev || x.group == Group::Runtime)
+        .map(|x| x.requirement)
+        .collect();
+
+    let distributions = installed::find_distributions();
+    let environment = Environment::current();
+    let (required, missing) = required_distributions(&distributions, &requirements, &environment);
+
+    for requirement in &missing {
+        note(format!("WARNING: {} isn't installed, so its license can't be read", requirement.name));
+    }
+
+    let entries: Vec<Entry> = required
+        .iter()
+        .map(|(distribution, _)| {
+            let found = spdx::normalise(&distribution.license);
+            let license = found.as_ref().map(|(x, _)| x.clone());
+
+            Entry {
+                status: policy.check(&distribution.name, license.as_ref()),
+                name: distribution.name.clone(),
+                version: distribution.version.clone(),
+                license,
+                source: found.map(|(_, x)| x),
+                author: distribution.author.clone(),
+                home_page: distribution.home_page.clone(),
+                files: distribution.license.files.clone(),
+            }
+        })
+        .collect();
+
+    let report = report::export(&entries, &options.format).unwrap();
+
+    match &options.output {
+        Some(file) => match fs::write(file, &report) {
+            Ok(_) => println!("Successfully wrote the license report to {}", file),
+            Err(e) => {
+                println!("Error while writing to {}: {}", file, e);
+                return false;
+            }
+        },
+        None => print!("{}", report),
+    };
+
+    let failing: Vec<&Entry> = entries.iter().filter(|x| policy.fails(x.status)).collect();
+
+    if !failing.is_empty() {
+        note(String::new());
+        note(format!("WARNING: {} package(s) don't meet the license policy in [{}]", failing.len(), policy::SECTION));
+        for entry in &failing {
+            let license = entry.license.as_ref().map(|x| x.to_string()).unwrap_or_else(|| String::from("unknown license"));
+            note(format!("\t{} {} ({}): {}", entry.name, entry.version, license, entry.status));
+        }
+    }
+
+    failing.is_empty()
+}
@@ -0,0 +1,133 @@
+//! Allow and deny lists of licenses, read from setup.cfg
+
+use std::fmt;
+
+use crate::config::SetupCfg;
+use crate::package::name::normalise_name;
+
+use super::spdx::{self, Expression};
+
+/// setup.cfg section holding the license policy
+pub const SECTION: &str = "tool:pyproj.licenses";
+
+/// Which licenses the project may depend on
+#[derive(Default)]
+pub struct LicensePolicy {
+    /// Licenses allowed (every license is allowed if empty)
+    pub allow: Vec<String>,
+    /// Licenses which are never allowed
+    pub deny: Vec<String>,
+    /// Packages accepted whatever their license (normalised names)
+    pub ignore: Vec<String>,
+}
+
+/// Result of checking a package's license against the policy
+#[derive(Clone, Copy, PartialEq, PartialOrd, Debug)]
+pub enum Status {
+    Denied,
+    NotAllowed,
+    Unknown,
+    Allowed,
+    Ignored,
+}
+
+impl LicensePolicy {
+    /// Read the policy from setup.cfg
+    ///
+    /// ```ini
+    /// [tool:pyproj.licenses]
+    /// allow =
+    ///     MIT
+    ///     BSD-*
+    /// deny = GPL-*
+    /// ignore = internal-package
+    /// ```
+    pub fn from_config(cfg: &SetupCfg) -> LicensePolicy {
+        // Lists may be written one per line or separated by commas
+        let list = |key: &str| -> Vec<String> {
+            cfg.get_list(SECTION, key)
+                .iter()
+                .flat_map(|x| x.split(','))
+                .map(|x| x.trim())
+                .filter(|x| !x.is_empty())
+                .map(|x| match x.ends_with('*') {
+                    true => x.to_string(),
+                    false => spdx::from_name(x).unwrap_or_else(|| x.to_string()),
+                })
+                .collect()
+        };
+
+        LicensePolicy {
+            allow: list("allow"),
+            deny: list("deny"),
+            ignore: list("ignore").iter().map(|x| normalise_name(x)).collect(),
+        }
+    }
+
+    /// Check a package's license
+    pub fn check(&self, package: &str, license: Option<&Expression>) -> Status {
+        if self.ignore.contains(&normalise_name(package)) {
+            return Status::Ignored;
+        }
+
+        match license {
+            Some(expression) => self.check_expression(expression),
+            None => Status::Unknown,
+        }
+    }
+
+    /// Check whether a status fails the policy (unknown licenses only
+    /// fail if there is an allow list)
+    pub fn fails(&self, status: Status) -> bool {
+        match status {
+            Status::Denied | Status::NotAllowed => true,
+            Status::Unknown => !self.allow.is_empty(),
+            Status::Allowed | Status::Ignored => false,
+        }
+    }
+
+    fn check_expression(&self, expression: &Expression) -> Status {
+        match expression {
+            Expression::License(id, _) => {
+                if self.deny.iter().any(|x| matches(x, id)) {
+                    Status::Denied
+                } else if self.allow.is_empty() || self.allow.iter().any(|x| matches(x, id)) {
+                    Status::Allowed
+                } else {
+                    Status::NotAllowed
+                }
+            },
+            // Every license applies
+            Expression::And(terms) => terms
+                .iter()
+                .map(|x| self.check_expression(x))
+                .fold(Status::Allowed, |a, b| if b < a { b } else { a }),
+            // The best license can be chosen
+            Expression::Or(terms) => terms
+                .iter()
+                .map(|x| self.check_expression(x))
+                .fold(Status::Denied, |a, b| if b > a { b } else { a }),
+        }
+    }
+}
+
+/// Match a license against a pattern (`MIT`, `GPL-*`)
+fn matches(pattern: &str, id: &str) -> bool {
+    match pattern.strip_suffix('*') {
+        Some(prefix) => id.to_lowercase().starts_with(&prefix.to_lowercase()),
+        None => id.eq_ignore_ascii_case(pattern),
+    }
+}
+
+impl fmt::Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Status::Denied => "denied",
+            Status::NotAllowed => "not allowed",
+            Status::Unknown => "unknown",
+            Status::Allowed => "allowed",
+            Status::Ignored => "ignored",
+        };
+        write!(f, "{}", name)
+    }
+}
@@ -0,0 +1,146 @@
+//! Format license reports as a table, CSV, JSON or Markdown
+
+use std::fs;
+use std::path::Path;
+
+use serde_json::{json, Value};
+
+use crate::report::table;
+
+use super::Entry;
+
+pub const FORMATS: [&str; 4] = ["table", "csv", "json", "markdown"];
+
+/// Format the entries, or None if the format is unknown
+pub fn export(entries: &[Entry], format: &str) -> Option<String> {
+    match format {
+        "table" => Some(to_table(entries)),
+        "csv" => Some(to_csv(entries)),
+        "json" => Some(serde_json::to_string_pretty(&to_json(entries)).unwrap()),
+        "markdown" => Some(to_markdown(entries)),
+        _ => None,
+    }
+}
+
+/// A table of every package's license and status
+pub fn to_table(entries: &[Entry]) -> String {
+    if entries.is_empty() {
+        return String::from("The project has no installed dependencies\n");
+    }
+
+    let rows: Vec<[String; 5]> = entries
+        .iter()
+        .map(|entry| [
+            entry.name.clone(),
+            entry.version.clone(),
+            license(entry),
+            entry.source.map(|x| x.to_string()).unwrap_or_else(|| String::from("-")),
+            entry.status.to_string(),
+        ])
+        .collect();
+
+    table(["Package", "Version", "License", "From", "Status"], &rows)
+}
+
+pub fn to_csv(entries: &[Entry]) -> String {
+    let mut csv = String::from("package,version,license,license_source,status,author,home_page,license_files\n");
+
+    for entry in entries {
+        let files: Vec<String> = entry.files.iter().map(|x| file_name(x)).collect();
+
+        let fields = [
+            entry.name.clone(),
+            entry.version.clone(),
+            license(entry),
+            entry.source.map(|x| x.to_string()).unwrap_or_default(),
+            entry.status.to_string(),
+            entry.author.clone().unwrap_or_default(),
+            entry.home_page.clone().unwrap_or_default(),
+            files.join(";"),
+        ];
+
+        let fields: Vec<String> = fields.iter().map(|x| escape_csv(x)).collect();
+        csv.push_str(&fields.join(","));
+        csv.push('\n');
+    }
+
+    csv
+}
+
+pub fn to_json(entries: &[Entry]) -> Value {
+    let packages: Vec<Value> = entries
+        .iter()
+        .map(|x| json!({
+            "name": x.name,
+            "version": x.version,
+            "license": x.license.as_ref().map(|x| x.to_string()),
+            "license_source": x.source.map(|x| x.to_string()),
+            "status": x.status.to_string(),
+            "author": x.author,
+            "home_page": x.home_page,
+            "license_files": x.files
+                .iter()
+                .map(|file| json!({ "name": file_name(file), "text": fs::read_to_string(file).ok() }))
+                .collect::<Vec<Value>>(),
+        }))
+        .collect();
+
+    json!({ "packages": packages })
+}
+
+/// An attribution document with a summary table and every bundled license text
+pub fn to_markdown(entries: &[Entry]) -> String {
+    let mut markdown = String::from("# Third-party licenses\n\n");
+    markdown.push_str("| Package | Version | License |\n| --- | --- | --- |\n");
+
+    for entry in entries {
+        markdown.push_str(&format!("| {} | {} | {} |\n", entry.name, entry.version, license(entry)));
+    }
+
+    for entry in entries {
+        markdown.push_str(&format!("\n## {} {}\n\n", entry.name, entry.version));
+        markdown.push_str(&format!("- License: {}\n", license(entry)));
+
+        if let Some(author) = &entry.author {
+            markdown.push_str(&format!("- Author: {}\n", author));
+        }
+        if let Some(home_page) = &entry.home_page {
+            markdown.push_str(&format!("- Home page: {}\n", home_page));
+        }
+
+        for file in &entry.files {
+            if let Ok(text) = fs::read_to_string(file) {
+                markdown.push_str(&format!("\n### {}\n\n```\n{}\n```\n", file_name(file), text.trim_end()));
+            }
+        }
+    }
+
+    markdown
+}
+
+fn license(entry: &Entry) -> String {
+    match &entry.license {
+        Some(x) => x.to_string(),
+        None => String::from("unknown"),
+    }
+}
+
+/// Name of a license file inside its `.dist-info` folder (`LICENSE`, `licenses/NOTICE`)
+fn file_name(path: &Path) -> String {
+    let components: Vec<String> = path
+        .components()
+        .map(|x| x.as_os_str().to_string_lossy().to_string())
+        .collect();
+
+    match components.iter().position(|x| x.ends_with(".dist-info")) {
+        Some(at) => components[at + 1..].join("/"),
+        None => components.last().cloned().unwrap_or_default(),
+    }
+}
+
+fn escape_csv(field: &str) -> String {
+    match field.contains([',', '"', '\n', '\r']) {
+        true => format!("\"{}\"", field.replace('"', "\"\"")),
+        false => field.to_string(),
+    }
+}
@@ -0,0 +1,442 @@
+//! Normalise license metadata to SPDX license expressions
+
+use std::fmt;
+use std::fs;
+
+use crate::package::installed::License;
+
+/// SPDX identifiers written with their usual case
+const IDENTIFIERS: [&str; 44] = [
+    "0BSD", "AFL-3.0", "AGPL-3.0-only", "AGPL-3.0-or-later", "Apache-1.1", "Apache-2.0", "Artistic-2.0",
+    "BSD-2-Clause", "BSD-3-Clause", "BSD-4-Clause", "BSL-1.0", "CC-BY-4.0", "CC0-1.0", "CNRI-Python",
+    "EPL-1.0", "EPL-2.0", "EUPL-1.2", "GPL-1.0-or-later", "GPL-2.0-only", "GPL-2.0-or-later", "GPL-3.0-only",
+    "GPL-3.0-or-later", "HPND", "ISC", "LGPL-2.0-only", "LGPL-2.0-or-later", "LGPL-2.1-only",
+    "LGPL-2.1-or-later", "LGPL-3.0-only", "LGPL-3.0-or-later", "MIT", "MIT-0", "MPL-1.1", "MPL-2.0",
+    "OFL-1.1", "PSF-2.0", "Python-2.0", "Unicode-DFS-2016", "Unlicense", "UPL-1.0", "WTFPL", "X11", "Zlib",
+    "ZPL-2.1",
+];
+
+/// Common license names (lowercase) and their SPDX identifier
+const NAMES: [(&str, &str); 45] = [
+    ("mit", "MIT"), ("mit license", "MIT"), ("the mit license", "MIT"), ("expat", "MIT"),
+    ("mit no attribution license", "MIT-0"),
+    ("apache", "Apache-2.0"), ("apache 2", "Apache-2.0"), ("apache 2.0", "Apache-2.0"), ("apache2", "Apache-2.0"),
+    ("apache license 2.0", "Apache-2.0"), ("apache license, version 2.0", "Apache-2.0"),
+    ("apache license version 2.0", "Apache-2.0"), ("apache software license", "Apache-2.0"),
+    ("apache software license 2.0", "Apache-2.0"), ("asl 2.0", "Apache-2.0"),
+    ("bsd", "BSD-3-Clause"), ("bsd license", "BSD-3-Clause"), ("new bsd", "BSD-3-Clause"),
+    ("new bsd license", "BSD-3-Clause"), ("modified bsd", "BSD-3-Clause"), ("3-clause bsd", "BSD-3-Clause"),
+    ("bsd 3-clause", "BSD-3-Clause"), ("bsd-3", "BSD-3-Clause"),
+    ("simplified bsd", "BSD-2-Clause"), ("2-clause bsd", "BSD-2-Clause"), ("bsd 2-clause", "BSD-2-Clause"),
+    ("freebsd", "BSD-2-Clause"),
+    ("isc license", "ISC"), ("iscl", "ISC"),
+    ("psf", "PSF-2.0"), ("psfl", "PSF-2.0"), ("psf license", "PSF-2.0"),
+    ("python software foundation license", "PSF-2.0"),
+    ("mpl 2.0", "MPL-2.0"), ("mozilla public license 2.0", "MPL-2.0"),
+    ("the unlicense", "Unlicense"), ("cc0", "CC0-1.0"), ("cc0 1.0 universal", "CC0-1.0"),
+    ("cc0 1.0 universal public domain dedication", "CC0-1.0"),
+    ("zlib/libpng license", "Zlib"), ("historical permission notice and disclaimer", "HPND"),
+    ("eclipse public license 2.0", "EPL-2.0"), ("boost software license 1.0", "BSL-1.0"),
+    ("public domain", "LicenseRef-Public-Domain"),
+    ("zope public license", "ZPL-2.1"),
+];
+
+/// Phrases found in license texts, checked in order
+const TEXTS: [(&str, &str); 11] = [
+    ("permission is hereby granted, free of charge", "MIT"),
+    ("apache license version 2.0", "Apache-2.0"),
+    ("mozilla public license version 2.0", "MPL-2.0"),
+    ("this is free and unencumbered software released into the public domain", "Unlicense"),
+    ("python software foundation license", "PSF-2.0"),
+    ("boost software license", "BSL-1.0"),
+    ("eclipse public license - v 2.0", "EPL-2.0"),
+    ("cc0 1.0 universal", "CC0-1.0"),
+    ("gnu affero general public license version 3", "AGPL-3.0-only"),
+    ("gnu lesser general public license version 3", "LGPL-3.0-only"),
+    ("permission to use, copy, modify, and/or distribute this software for any purpose", "ISC"),
+];
+
+/// An SPDX license expression (`MIT`, `Apache-2.0 OR BSD-3-Clause`, ...)
+#[derive(Clone, Debug, PartialEq)]
+pub enum Expression {
+    /// A license identifier, with a `WITH` exception if it has one
+    License(String, Option<String>),
+    And(Vec<Expression>),
+    Or(Vec<Expression>),
+}
+
+/// Where a license was found
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Source {
+    Expression,
+    License,
+    File,
+    Classifier,
+}
+
+/// Find the license of a distribution and where its metadata says it
+pub fn normalise(license: &License) -> Option<(Expression, Source)> {
+    if let Some(expression) = license.expression.as_deref().and_then(Expression::parse) {
+        return Some((expression, Source::Expression));
+    }
+
+    if let Some(text) = &license.text {
+        // The field holds a name, an expression or the whole license text
+        let found = match text.lines().count() > 1 || text.len() > 200 {
+            true => from_text(text).map(|x| Expression::License(x.to_string(), None)),
+            false => from_name(text)
+                .map(|x| Expression::License(x, None))
+                .or_else(|| Expression::parse(text).filter(|x| x.is_known())),
+        };
+
+        if let Some(expression) = found {
+            return Some((expression, Source::License));
+        }
+    }
+
+    let mut classifiers: Vec<Expression> = Vec::new();
+    for classifier in &license.classifiers {
+        let name = classifier.rsplit("::").next().unwrap_or("").trim();
+
+        if let Some(id) = from_name(name) {
+            let expression = Expression::License(id, None);
+            if !classifiers.contains(&expression) {
+                classifiers.push(expression);
+            }
+        }
+    }
+
+    // Several license classifiers mean the user can choose
+    if classifiers.len() > 1 {
+        return Some((Expression::Or(classifiers), Source::Classifier));
+    }
+
+    // A single classifier can be vague (`BSD License`), so the bundled license text is checked first
+    for file in &license.files {
+        if let Some(id) = fs::read_to_string(file).ok().as_deref().and_then(from_text) {
+            return Some((Expression::License(id.to_string(), None), Source::File));
+        }
+    }
+
+    classifiers.pop().map(|x| (x, Source::Classifier))
+}
+
+/// Get the SPDX identifier of a license name (`MIT License`, `GPLv3+`, ...)
+pub fn from_name(name: &str) -> Option<String> {
+    let lower = name.trim().trim_end_matches('.').to_lowercase();
+
+    if let Some(id) = IDENTIFIERS.iter().find(|x| x.to_lowercase() == lower) {
+        return Some(id.to_string());
+    }
+
+    // `Mozilla Public License 2.0 (MPL 2.0)`, `ISC License (ISCL)`
+    let without_note = match lower.find(" (") {
+        Some(at) if lower.ends_with(')') => &lower[..at],
+        _ => &lower,
+    };
+
+    for candidate in [lower.as_str(), without_note] {
+        if let Some((_, id)) = NAMES.iter().find(|(x, _)| *x == candidate) {
+            return Some(id.to_string());
+        }
+    }
+
+    // A list of licenses (`public domain, Python, GPL 3`) isn't a single one
+    match lower.contains([',', ';']) {
+        true => None,
+        false => gnu(&lower),
+    }
+}
+
+/// Get the SPDX identifier of a GNU license name (`GNU General Public License v3 (GPLv3)`, `LGPL-2.1+`)
+fn gnu(name: &str) -> Option<String> {
+    let compact = name.replace([' ', '-', '_'], "");
+
+    let family = if compact.contains("affero") || compact.contains("agpl") {
+        "AGPL"
+    } else if compact.contains("lesser") || compact.contains("library") || compact.contains("lgpl") {
+        "LGPL"
+    } else if compact.contains("generalpubliclicense") || compact.contains("gpl") {
+        "GPL"
+    } else {
+        return None;
+    };
+
+    let later = name.contains('+') || name.contains("or later") || name.contains("or-later");
+
+    // The first version number (`v3`, `2.1`, `version 3`)
+    let version = ["3.0", "2.1", "2.0", "v3", "v2", "version 3", "version 2", "gpl3", "gpl2", "gpl-3", "gpl-2", "gpl 3", "gpl 2"]
+        .iter()
+        .find(|x| name.contains(*x))
+        .map(|x| match *x {
+            "2.1" => "2.1",
+            x if x.contains('3') => "3.0",
+            _ => "2.0",
+        });
+
+    let id = match (family, version) {
+        // Without a version, any version may be used
+        ("GPL", None) => String::from("GPL-1.0-or-later"),
+        ("LGPL", None) => String::from("LGPL-2.0-or-later"),
+        (_, None) => String::from("AGPL-3.0-or-later"),
+        (family, Some(version)) => format!("{}-{}-{}", family, version, if later { "or-later" } else { "only" }),
+    };
+
+    IDENTIFIERS.iter().find(|x| **x == id).map(|x| x.to_string())
+}
+
+/// Recognise the text of a license
+pub fn from_text(text: &str) -> Option<&'static str> {
+    let text = text.to_lowercase().split_whitespace().collect::<Vec<&str>>().join(" ");
+    let text = text.replace(", version", " version");
+
+    if let Some((_, id)) = TEXTS.iter().find(|(phrase, _)| text.contains(phrase)) {
+        return Some(id);
+    }
+
+    if text.contains("redistribution and use in source and binary forms") {
+        return Some(if text.contains("advertising materials") {
+            "BSD-4-Clause"
+        } else if text.contains("neither the name") || text.contains("names of its contributors") {
+            "BSD-3-Clause"
+        } else {
+            "BSD-2-Clause"
+        });
+    }
+
+    if text.contains("gnu lesser general public license") && text.contains("version 2.1") {
+        return Some("LGPL-2.1-only");
+    }
+
+    if text.contains("gnu general public license") {
+        return match text.contains("version 3") {
+            true => Some("GPL-3.0-only"),
+            false if text.contains("version 2") => Some("GPL-2.0-only"),
+            false => None,
+        };
+    }
+
+    None
+}
+
+impl Expression {
+    /// Parse an SPDX expression, normalising the case of known identifiers
+    pub fn parse(expression: &str) -> Option<Expression> {
+        let spaced = expression.replace('(', " ( ").replace(')', " ) ");
+        let tokens: Vec<&str> = spaced.split_whitespace().collect();
+
+        if tokens.is_empty() {
+            return None;
+        }
+
+        let mut at = 0;
+        let parsed = parse_or(&tokens, &mut at)?;
+
+        match at == tokens.len() {
+            true => Some(parsed),
+            false => None,
+        }
+    }
+
+    /// Every license identifier in the expression
+    pub fn licenses(&self) -> Vec<&str> {
+        match self {
+            Expression::License(id, _) => vec![id.as_str()],
+            Expression::And(x) | Expression::Or(x) => x.iter().flat_map(|x| x.licenses()).collect(),
+        }
+    }
+
+    /// Check whether every identifier is a known SPDX one (or a `LicenseRef-`)
+    pub fn is_known(&self) -> bool {
+        self.licenses()
+            .iter()
+            .all(|x| IDENTIFIERS.contains(&x.trim_end_matches('+')) || x.starts_with("LicenseRef-"))
+    }
+}
+
+fn parse_or(tokens: &[&str], at: &mut usize) -> Option<Expression> {
+    let mut terms = vec![parse_and(tokens, at)?];
+
+    while tokens.get(*at).map(|x| x.eq_ignore_ascii_case("OR")).unwrap_or(false) {
+        *at += 1;
+        terms.push(parse_and(tokens, at)?);
+    }
+
+    match terms.len() {
+        1 => terms.pop(),
+        _ => Some(Expression::Or(terms)),
+    }
+}
+
+fn parse_and(tokens: &[&str], at: &mut usize) -> Option<Expression> {
+    let mut terms = vec![parse_license(tokens, at)?];
+
+    while tokens.get(*at).map(|x| x.eq_ignore_ascii_case("AND")).unwrap_or(false) {
+        *at += 1;
+        terms.push(parse_license(tokens, at)?);
+    }
+
+    match terms.len() {
+        1 => terms.pop(),
+        _ => Some(Expression::And(terms)),
+    }
+}
+
+fn parse_license(tokens: &[&str], at: &mut usize) -> Option<Expression> {
+    let token = *tokens.get(*at)?;
+    *at += 1;
+
+    if token == "(" {
+        let inner = parse_or(tokens, at)?;
+        return match tokens.get(*at) {
+            Some(&")") => {
+                *at += 1;
+                Some(inner)
+            },
+            _ => None,
+        };
+    }
+
+    let valid = token.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '.' || c == '+' || c == ':');
+    if !valid || ["AND", "OR", "WITH", ")"].iter().any(|x| token.eq_ignore_ascii_case(x)) {
+        return None;
+    }
+
+    // Deprecated forms (`GPL-3.0`, `GPL-3.0+`) become `-only` and `-or-later`
+    let (base, later) = match token.strip_suffix('+') {
+        Some(x) => (x, true),
+        None => (token, false),
+    };
+    let id = match IDENTIFIERS.iter().find(|x| x.eq_ignore_ascii_case(base)) {
+        Some(x) => x.to_string(),
+        None => match IDENTIFIERS.iter().find(|x| x.eq_ignore_ascii_case(&format!("{}-{}", base, if later { "or-later" } else { "only" }))) {
+            Some(x) => x.to_string(),
+            None => token.to_string(),
+        },
+    };
+
+    let exception = match tokens.get(*at) {
+        Some(x) if x.eq_ignore_ascii_case("WITH") => {
+            let exception = tokens.get(*at + 1)?.to_string();
+            *at += 2;
+            Some(exception)
+        },
+        _ => None,
+    };
+
+    Some(Expression::License(id, exception))
+}
+
+impl fmt::Display for Expression {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // Parenthesise an OR inside an AND
+        let join = |f: &mut fmt::Formatter, terms: &[Expression], operator: &str| -> fmt::Result {
+            for (i, term) in terms.iter().enumerate() {
+                if i > 0 {
+                    write!(f, " {} ", operator)?;
+                }
+                match (operator, term) {
+                    ("AND", Expression::Or(_)) => write!(f, "({})", term)?,
+                    _ => write!(f, "{}", term)?,
+                }
+            }
+            Ok(())
+        };
+
+        match self {
+            Expression::License(id, None) => write!(f, "{}", id),
+            Expression::License(id, Some(exception)) => write!(f, "{} WITH {}", id, exception),
+            Expression::And(terms) => join(f, terms, "AND"),
+            Expression::Or(terms) => join(f, terms, "OR"),
+        }
+    }
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Source::Expression => "License-Expression",
+            Source::License => "License",
+            Source::File => "license file",
+            Source::Classifier => "classifier",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn expressions_are_parsed_and_normalised() {
+        let cases = [
+            ("mit", "MIT"),
+            ("Apache-2.0 OR mit", "Apache-2.0 OR MIT"),
+            ("(MIT OR Apache-2.0) AND bsd-3-clause", "(MIT OR Apache-2.0) AND BSD-3-Clause"),
+            ("GPL-3.0+", "GPL-3.0-or-later"),
+            ("GPL-2.0 WITH Classpath-exception-2.0", "GPL-2.0-only WITH Classpath-exception-2.0"),
+            ("LicenseRef-Proprietary", "LicenseRef-Proprietary"),
+        ];
+
+        for (written, normalised) in cases {
+            let expression = Expression::parse(written).unwrap_or_else(|| panic!("{} is valid", written));
+            assert_eq!(expression.to_string(), normalised);
+        }
+
+        let expression = Expression::parse("MIT AND (Apache-2.0 OR Unknown-1.0)").unwrap();
+        assert_eq!(expression.licenses(), ["MIT", "Apache-2.0", "Unknown-1.0"]);
+        assert!(!expression.is_known());
+    }
+
+    #[test]
+    fn invalid_expressions_are_rejected() {
+        for x in ["", "MIT OR", "(MIT", "MIT)", "AND MIT", "MIT WITH", "My License"] {
+            assert!(Expression::parse(x).is_none(), "{}", x);
+        }
+    }
+
+    #[test]
+    fn license_names_are_recognised() {
+        let cases = [
+            ("MIT License", Some("MIT")),
+            ("Apache Software License", Some("Apache-2.0")),
+            ("Mozilla Public License 2.0 (MPL 2.0)", Some("MPL-2.0")),
+            ("GNU Lesser General Public License v2 or later (LGPLv2+)", Some("LGPL-2.0-or-later")),
+            ("GPLv3", Some("GPL-3.0-only")),
+            ("public domain, Python, GPL 3", None),
+            ("Proprietary", None),
+        ];
+
+        for (name, id) in cases {
+            assert_eq!(from_name(name).as_deref(), id, "{}", name);
+        }
+    }
+
+    #[test]
+    fn metadata_prefers_the_license_expression() {
+        let license = License {
+            expression: Some(String::from("BSD-3-Clause")),
+            text: Some(String::from("MIT")),
+            classifiers: vec![String::from("License :: OSI Approved :: Apache Software License")],
+            files: Vec::new(),
+        };
+        assert_eq!(
+            normalise(&license),
+            Some((Expression::License(String::from("BSD-3-Clause"), None), Source::Expression))
+        );
+
+        // Several classifiers are a choice
+        let license = License {
+            expression: None,
+            text: Some(String::from("UNKNOWN")),
+            classifiers: vec![
+                String::from("License :: OSI Approved :: MIT License"),
+                String::from("License :: OSI Approved :: Apache Software License"),
+            ],
+            files: Vec::new(),
+        };
+        let (expression, source) = normalise(&license).unwrap();
+        assert_eq!((expression.to_string().as_str(), source), ("MIT OR Apache-2.0", Source::Classifier));
+    }
+}
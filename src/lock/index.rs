@@ -0,0 +1,587 @@
+//! Find packages in a local `--find-links` directory or a PEP 503
+//! simple index (a local directory or served over http from localhost)
+
+use std::collections::HashMap;
+use std::fs;
+use std::io::{Cursor, ErrorKind, Read, Write};
+use std::net::TcpStream;
+use std::path::PathBuf;
+
+use flate2::read::GzDecoder;
+
+use crate::package::installed::{header, headers};
+use crate::package::marker::Environment;
+use crate::package::name::normalise_name;
+use crate::package::requirement::Requirement;
+use crate::package::specifier::SpecifierSet;
+use crate::package::version::Version;
+
+/// Where packages are found
+pub enum Index {
+    /// A directory of wheels and sdists
+    FindLinks(PathBuf),
+    /// A PEP 503 simple index (a directory or an `http://` URL)
+    Simple(String),
+}
+
+/// Kind of distribution file
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Kind {
+    Wheel,
+    Sdist,
+}
+
+/// A distribution file of some version of a package
+#[derive(Clone, Debug)]
+pub struct Candidate {
+    pub name: String,
+    pub version: Version,
+    pub kind: Kind,
+    pub filename: String,
+    /// Path or URL of the file
+    pub location: String,
+    /// Python versions it supports, if the index says so
+    pub requires_python: Option<SpecifierSet>,
+    /// Wheel tags (python, abi, platform)
+    tags: Option<(String, String, String)>,
+}
+
+/// The metadata needed to resolve a candidate
+#[derive(Clone, Debug, Default)]
+pub struct Metadata {
+    pub requires: Vec<Requirement>,
+    pub requires_python: Option<SpecifierSet>,
+}
+
+impl Index {
+    /// Every distribution file of a package
+    pub fn candidates(&self, package: &str) -> Result<Vec<Candidate>, String> {
+        let package = normalise_name(package);
+
+        let files: Vec<(String, String, Option<SpecifierSet>)> = match self {
+            Index::FindLinks(dir) => {
+                let entries = fs::read_dir(dir)
+                    .map_err(|e| format!("Error while reading {}: {}", dir.display(), e))?;
+
+                entries
+                    .filter_map(|x| x.ok())
+                    .map(|x| (
+                        x.file_name().to_string_lossy().to_string(),
+                        x.path().to_string_lossy().to_string(),
+                        None,
+                    ))
+                    .collect()
+            },
+            Index::Simple(base) => {
+                let page_url = format!("{}/{}/", base.trim_end_matches('/'), package);
+
+                let page = match read_location(&page_url, true)? {
+                    Some(x) => String::from_utf8_lossy(&x).to_string(),
+                    // Packages which aren't in the index have no page
+                    None => return Ok(Vec::new()),
+                };
+
+                links(&page)
+                    .into_iter()
+                    .map(|(href, requires_python)| {
+                        let href = href.split('#').next().unwrap_or("").to_string();
+                        let filename = href.rsplit('/').next().unwrap_or("").to_string();
+                        let requires_python = requires_python.and_then(|x| SpecifierSet::parse(&x));
+                        (filename, join_url(&page_url, &href), requires_python)
+                    })
+                    .collect()
+            },
+        };
+
+        let mut candidates: Vec<Candidate> = files
+            .into_iter()
+            .filter_map(|(filename, location, requires_python)| {
+                let mut candidate = Candidate::from_filename(&filename, location)?;
+                candidate.requires_python = requires_python;
+                Some(candidate)
+            })
+            .filter(|x| normalise_name(&x.name) == package)
+            .collect();
+
+        // Newest first, preferring wheels
+        candidates.sort_by(|a, b| b.version.cmp(&a.version).then_with(|| (a.kind == Kind::Sdist).cmp(&(b.kind == Kind::Sdist))));
+        Ok(candidates)
+    }
+}
+
+impl Candidate {
+    /// Parse a wheel (`name-1.0-py3-none-any.whl`) or sdist (`name-1.0.tar.gz`) filename
+    pub fn from_filename(filename: &str, location: String) -> Option<Candidate> {
+        if let Some(stem) = filename.strip_suffix(".whl") {
+            let parts: Vec<&str> = stem.split('-').collect();
+
+            // name-version(-build)?-python-abi-platform
+            if parts.len() != 5 && parts.len() != 6 {
+                return None;
+            }
+
+            let n = parts.len();
+            return Some(Candidate {
+                name: parts[0].to_string(),
+                version: Version::parse(parts[1])?,
+                kind: Kind::Wheel,
+                filename: filename.to_string(),
+                location,
+                requires_python: None,
+                tags: Some((parts[n - 3].to_string(), parts[n - 2].to_string(), parts[n - 1].to_string())),
+            });
+        }
+
+        let stem = [".tar.gz", ".zip", ".tar.bz2", ".tgz"]
+            .iter()
+            .find_map(|x| filename.strip_suffix(x))?;
+
+        // The name may contain dashes, the version can't
+        let (name, version) = stem.rsplit_once('-')?;
+
+        Some(Candidate {
+            name: name.to_string(),
+            version: Version::parse(version)?,
+            kind: Kind::Sdist,
+            filename: filename.to_string(),
+            location,
+            requires_python: None,
+            tags: None,
+        })
+    }
+
+    /// Check whether a wheel can be installed in an environment (sdists always can)
+    pub fn is_compatible(&self, environment: &Environment) -> bool {
+        let (python, abi, platform) = match &self.tags {
+            Some(x) => x,
+            None => return true,
+        };
+
+        let version = environment.get("python_version").replace('.', "");
+        let major = version.chars().next().map(|x| x.to_string()).unwrap_or_default();
+
+        let python_ok = version.is_empty() || python.split('.').any(|tag| {
+            tag == format!("py{}", major)
+                || tag == format!("py{}", version)
+                || tag == format!("cp{}", version)
+                // abi3 wheels work on every later CPython
+                || (abi == "abi3" && tag.strip_prefix("cp").map(|x| at_least(&version, x)).unwrap_or(false))
+        });
+
+        let abi_ok = version.is_empty() || abi.split('.').any(|tag| {
+            tag == "none" || tag == "abi3" || tag.trim_end_matches(['m', 'd', 'u']) == format!("cp{}", version)
+        });
+
+        python_ok && abi_ok && platform.split('.').any(|tag| platform_matches(tag, environment))
+    }
+
+    /// Read the candidate's metadata
+    pub fn metadata(&self) -> Result<Metadata, String> {
+        let data = read_location(&self.location, false)?
+            .ok_or_else(|| format!("{} doesn't exist", self.location))?;
+
+        match self.kind {
+            Kind::Wheel => wheel_metadata(&data),
+            Kind::Sdist => sdist_metadata(&self.filename, &data),
+        }
+        .map_err(|e| format!("Couldn't read the metadata of {}: {}", self.filename, e))
+    }
+}
+
+/// Check whether a platform tag matches an environment
+fn platform_matches(tag: &str, environment: &Environment) -> bool {
+    if tag == "any" {
+        return true;
+    }
+
+    let family = match environment.get("sys_platform") {
+        "win32" | "cygwin" => tag.starts_with("win"),
+        "darwin" => tag.starts_with("macosx"),
+        "" => true,
+        _ => tag.starts_with("manylinux") || tag.starts_with("musllinux") || tag.starts_with("linux"),
+    };
+
+    // Architectures are spelt differently on each platform
+    let machine = environment.get("platform_machine").to_lowercase();
+    let aliases: &[&str] = match machine.as_str() {
+        "x86_64" | "amd64" => &["x86_64", "amd64", "universal2", "intel"],
+        "aarch64" | "arm64" => &["aarch64", "arm64", "universal2"],
+        "x86" | "i386" | "i686" => &["i686", "win32", "i386"],
+        _ => &[],
+    };
+
+    let arch = aliases.is_empty() || aliases.iter().any(|x| tag.ends_with(x));
+
+    family && arch
+}
+
+/// Check whether a `cpXY` version is at least another (`311` >= `37`)
+fn at_least(version: &str, minimum: &str) -> bool {
+    let split = |x: &str| -> Option<(u32, u32)> {
+        let major = x.get(..1)?.parse().ok()?;
+        let minor = x.get(1..)?.parse().ok()?;
+        Some((major, minor))
+    };
+
+    match (split(version), split(minimum)) {
+        (Some(a), Some(b)) => a >= b,
+        _ => false,
+    }
+}
+
+/// Read the `.dist-info/METADATA` of a wheel
+fn wheel_metadata(data: &[u8]) -> Result<Metadata, String> {
+    let mut archive = zip::ZipArchive::new(Cursor::new(data)).map_err(|e| e.to_string())?;
+
+    let name = archive
+        .file_names()
+        .find(|x| x.ends_with(".dist-info/METADATA") && x.matches('/').count() == 1)
+        .map(|x| x.to_string())
+        .ok_or_else(|| String::from("no METADATA file in the wheel"))?;
+
+    let mut content = String::new();
+    archive
+        .by_name(&name)
+        .map_err(|e| e.to_string())?
+        .read_to_string(&mut content)
+        .map_err(|e| e.to_string())?;
+
+    Ok(parse_metadata(&content))
+}
+
+/// Read the metadata of an sdist, from `PKG-INFO` if it lists the
+/// dependencies, else from setuptools' `.egg-info/requires.txt`
+fn sdist_metadata(filename: &str, data: &[u8]) -> Result<Metadata, String> {
+    let mut files: HashMap<String, String> = HashMap::new();
+    let wanted = |path: &str| path.ends_with("PKG-INFO") || path.ends_with(".egg-info/requires.txt");
+
+    if filename.ends_with(".zip") {
+        let mut archive = zip::ZipArchive::new(Cursor::new(data)).map_err(|e| e.to_string())?;
+
+        for i in 0..archive.len() {
+            let mut file = archive.by_index(i).map_err(|e| e.to_string())?;
+            let path = file.name().to_string();
+
+            if wanted(&path) {
+                let mut content = String::new();
+                file.read_to_string(&mut content).map_err(|e| e.to_string())?;
+                files.insert(path, content);
+            }
+        }
+    } else if filename.ends_with(".tar.gz") || filename.ends_with(".tgz") {
+        let mut archive = tar::Archive::new(GzDecoder::new(data));
+
+        for entry in archive.entries().map_err(|e| e.to_string())? {
+            let mut entry = entry.map_err(|e| e.to_string())?;
+            let path = entry.path().map_err(|e| e.to_string())?.to_string_lossy().to_string();
+
+            if wanted(&path) {
+                let mut content = String::new();
+                entry.read_to_string(&mut content).map_err(|e| e.to_string())?;
+                files.insert(path, content);
+            }
+        }
+    } else {
+        return Err(String::from("unsupported sdist format"));
+    }
+
+    // The sdist's own PKG-INFO is at `name-version/PKG-INFO`
+    let pkg_info = files
+        .iter()
+        .filter(|(path, _)| path.ends_with("PKG-INFO") && path.matches('/').count() == 1)
+        .map(|(_, content)| content.clone())
+        .next()
+        .ok_or_else(|| String::from("no PKG-INFO file in the sdist"))?;
+
+    let mut metadata = parse_metadata(&pkg_info);
+
+    // Metadata 2.2+ lists the dependencies unless they are dynamic
+    let headers = headers(&pkg_info);
+    let version = header(&headers, "Metadata-Version").and_then(|x| Version::parse(&x));
+    let dynamic = headers
+        .iter()
+        .any(|(key, value)| key.eq_ignore_ascii_case("Dynamic") && value.eq_ignore_ascii_case("Requires-Dist"));
+    let reliable = version.map(|x| x >= Version::parse("2.2").unwrap()).unwrap_or(false) && !dynamic;
+
+    if reliable {
+        return Ok(metadata);
+    }
+
+    let requires_txt = files
+        .iter()
+        .filter(|(path, _)| path.ends_with(".egg-info/requires.txt"))
+        .min_by_key(|(path, _)| path.matches('/').count())
+        .map(|(_, content)| content.clone());
+
+    match requires_txt {
+        Some(content) => metadata.requires = parse_requires_txt(&content)?,
+        None if metadata.requires.is_empty() => {
+            println!("WARNING: {} doesn't list its dependencies, assuming it has none", filename);
+        },
+        None => {},
+    }
+
+    Ok(metadata)
+}
+
+/// Read `Requires-Dist` and `Requires-Python` from a METADATA or PKG-INFO file
+fn parse_metadata(content: &str) -> Metadata {
+    let headers = headers(content);
+
+    let requires = headers
+        .iter()
+        .filter(|(key, _)| key.eq_ignore_ascii_case("Requires-Dist"))
+        .filter_map(|(_, value)| match Requirement::parse(value) {
+            Ok(x) => Some(x),
+            Err(e) => {
+                println!("WARNING: Skipped a dependency: {}", e);
+                None
+            }
+        })
+        .collect();
+
+    let requires_python = header(&headers, "Requires-Python").and_then(|x| SpecifierSet::parse(&x));
+
+    Metadata { requires, requires_python }
+}
+
+/// Parse setuptools' `requires.txt`, where `[extra:marker]` sections
+/// hold the dependencies of an extra or environment
+fn parse_requires_txt(content: &str) -> Result<Vec<Requirement>, String> {
+    let mut requires: Vec<Requirement> = Vec::new();
+    let mut section_marker: Option<String> = None;
+
+    for line in content.lines().map(|x| x.trim()).filter(|x| !x.is_empty() && !x.starts_with('#')) {
+        if let Some(section) = line.strip_prefix('[').and_then(|x| x.strip_suffix(']')) {
+            let (extra, marker) = section.split_once(':').unwrap_or((section, ""));
+
+            let mut markers: Vec<String> = Vec::new();
+            if !extra.is_empty() {
+                markers.push(format!("extra == \"{}\"", extra));
+            }
+            if !marker.is_empty() {
+                markers.push(format!("({})", marker));
+            }
+
+            section_marker = match markers.is_empty() {
+                true => None,
+                false => Some(markers.join(" and ")),
+            };
+            continue;
+        }
+
+        let requirement = match &section_marker {
+            Some(marker) => format!("{}; {}", line, marker),
+            None => line.to_string(),
+        };
+
+        requires.push(Requirement::parse(&requirement)?);
+    }
+
+    Ok(requires)
+}
+
+/// Find the links of a simple index page as `(href, data-requires-python)`
+fn links(page: &str) -> Vec<(String, Option<String>)> {
+    let mut links: Vec<(String, Option<String>)> = Vec::new();
+
+    for anchor in page.split("<a ").skip(1) {
+        let tag = anchor.split('>').next().unwrap_or("");
+
+        if let Some(href) = attribute(tag, "href") {
+            links.push((href, attribute(tag, "data-requires-python")));
+        }
+    }
+
+    links
+}
+
+/// Get the (unescaped) value of an HTML attribute
+fn attribute(tag: &str, name: &str) -> Option<String> {
+    let start = tag.find(&format!("{}=", name))? + name.len() + 1;
+    let rest = &tag[start..];
+
+    let quote = rest.chars().next()?;
+    let value = match quote {
+        '"' | '\'' => rest[1..].split(quote).next()?,
+        _ => rest.split(char::is_whitespace).next()?,
+    };
+
+    Some(
+        value
+            .replace("&lt;", "<")
+            .replace("&gt;", ">")
+            .replace("&quot;", "\"")
+            .replace("&#39;", "'")
+            .replace("&amp;", "&"),
+    )
+}
+
+/// Resolve a link relative to the page it is on
+fn join_url(page: &str, href: &str) -> String {
+    if href.contains("://") {
+        return href.to_string();
+    }
+
+    if let Some(path) = href.strip_prefix('/') {
+        // Relative to the host
+        return match page.split_once("://") {
+            Some((scheme, rest)) => {
+                let host = rest.split('/').next().unwrap_or("");
+                format!("{}://{}/{}", scheme, host, path)
+            },
+            // Absolute path of a local index
+            None => href.to_string(),
+        };
+    }
+
+    format!("{}/{}", page.trim_end_matches('/'), href)
+}
+
+/// Read a local path, `file://` URL or `http://` URL, or None if it
+/// doesn't exist (a missing file or a 404)
+///
+/// A directory is read as its `index.html` when `page` is set.
+fn read_location(location: &str, page: bool) -> Result<Option<Vec<u8>>, String> {
+    if location.starts_with("http://") {
+        return http_get(location);
+    }
+
+    if location.starts_with("https://") {
+        return Err(format!("{} can't be read offline, only local and http:// indexes are supported", location));
+    }
+
+    let path = PathBuf::from(location.strip_prefix("file://").unwrap_or(location));
+    let path = match page && path.is_dir() {
+        true => path.join("index.html"),
+        false => path,
+    };
+
+    match fs::read(&path) {
+        Ok(x) => Ok(Some(x)),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(format!("Error while reading {}: {}", path.display(), e)),
+    }
+}
+
+/// Get a URL over plain http (for indexes served from localhost), or None
+/// if the server doesn't have it
+fn http_get(url: &str) -> Result<Option<Vec<u8>>, String> {
+    let mut url = url.to_string();
+
+    // Follow a few redirects (`/simple/name` to `/simple/name/`)
+    for _ in 0..5 {
+        let rest = url.strip_prefix("http://").unwrap_or(&url);
+        let (host, path) = match rest.find('/') {
+            Some(at) => (&rest[..at], &rest[at..]),
+            None => (rest, "/"),
+        };
+        let address = match host.contains(':') {
+            true => host.to_string(),
+            false => format!("{}:80", host),
+        };
+
+        let mut stream = TcpStream::connect(&address)
+            .map_err(|e| format!("Couldn't connect to {}: {}", host, e))?;
+
+        let request = format!("GET {} HTTP/1.0\r\nHost: {}\r\nConnection: close\r\n\r\n", path, host);
+        stream
+            .write_all(request.as_bytes())
+            .map_err(|e| format!("Error while requesting {}: {}", url, e))?;
+
+        let mut response: Vec<u8> = Vec::new();
+        stream
+            .read_to_end(&mut response)
+            .map_err(|e| format!("Error while reading {}: {}", url, e))?;
+
+        let split = response
+            .windows(4)
+            .position(|x| x == b"\r\n\r\n")
+            .ok_or_else(|| format!("Invalid response from {}", url))?;
+
+        let head = String::from_utf8_lossy(&response[..split]).to_string();
+        let body = response[split + 4..].to_vec();
+
+        let status: u32 = head
+            .split_whitespace()
+            .nth(1)
+            .and_then(|x| x.parse().ok())
+            .unwrap_or(0);
+
+        let header = |name: &str| {
+            head.lines()
+                .filter_map(|x| x.split_once(':'))
+                .find(|(key, _)| key.trim().eq_ignore_ascii_case(name))
+                .map(|(_, value)| value.trim().to_string())
+        };
+
+        match status {
+            200 => {
+                return match header("Transfer-Encoding").map(|x| x.eq_ignore_ascii_case("chunked")) {
+                    Some(true) => Ok(Some(dechunk(&body))),
+                    _ => Ok(Some(body)),
+                };
+            },
+            404 => return Ok(None),
+            301 | 302 | 303 | 307 | 308 => {
+                let location = header("Location").ok_or_else(|| format!("Redirect without a location from {}", url))?;
+                url = join_url(&format!("http://{}", host), &location);
+            },
+            _ => return Err(format!("{} returned status {}", url, status)),
+        }
+    }
+
+    Err(format!("Too many redirects from {}", url))
+}
+
+/// Decode a chunked http body
+fn dechunk(body: &[u8]) -> Vec<u8> {
+    let mut out: Vec<u8> = Vec::new();
+    let mut at = 0;
+
+    while at < body.len() {
+        let end = match body[at..].windows(2).position(|x| x == b"\r\n") {
+            Some(x) => at + x,
+            None => break,
+        };
+
+        let size_line = String::from_utf8_lossy(&body[at..end]).to_string();
+        let size = usize::from_str_radix(size_line.split(';').next().unwrap_or("").trim(), 16).unwrap_or(0);
+        if size == 0 {
+            break;
+        }
+
+        let start = end + 2;
+        out.extend_from_slice(&body[start..(start + size).min(body.len())]);
+        at = start + size + 2;
+    }
+
+    out
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_missing_pages_are_empty() {
+        let dir = std::env::temp_dir().join(format!("pyproj-index-{}", std::process::id()));
+        fs::create_dir_all(dir.join("simple/present")).unwrap();
+        fs::write(
+            dir.join("simple/present/index.html"),
+            "<a href=\"present-1.0-py3-none-any.whl\">present-1.0-py3-none-any.whl</a>",
+        ).unwrap();
+        // A file where the index should be a directory
+        fs::write(dir.join("broken"), "").unwrap();
+
+        let index = Index::Simple(dir.join("simple").to_string_lossy().to_string());
+        assert_eq!(index.candidates("present").unwrap().len(), 1);
+        assert!(index.candidates("missing").unwrap().is_empty());
+
+        let broken = Index::Simple(dir.join("broken").to_string_lossy().to_string());
+        assert!(broken.candidates("present").is_err());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
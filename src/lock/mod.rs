@@ -0,0 +1,108 @@
+//! Resolve the project's requirements to a lockfile without network
+//! access, using a local wheelhouse or simple index
+
+pub mod index;
+pub mod resolver;
+
+use std::fs;
+
+use crate::build::Group;
+use crate::deps::find_declarations;
+use crate::package::marker::Environment;
+
+use self::index::Index;
+use self::resolver::{Pin, Resolver};
+
+pub const LOCKFILE: &str = "requirements.lock";
+
+/// Options of the lock subcommand
+pub struct LockOptions {
+    pub index: Index,
+    /// Include the dev requirements
+    pub dev: bool,
+    /// Target Python version (`3.11`), defaults to the current one
+    pub python: Option<String>,
+    /// Target platform (`linux`, `win32`, `darwin`), defaults to the current one
+    pub platform: Option<String>,
+    pub output: String,
+}
+
+/// Resolve the project's requirements and write them to a lockfile
+///
+/// Returns false if they can't be resolved.
+pub fn lock(options: &LockOptions) -> bool {
+    let declarations = match find_declarations() {
+        Ok(x) => x,
+        Err(e) => {
+            println!("{}", e);
+            return false;
+        }
+    };
+
+    let mut requirements = Vec::new();
+    for declaration in declarations {
+        if declaration.group == Group::Dev && !options.dev {
+            continue;
+        }
+
+        if declaration.requirement.url.is_some() {
+            println!(
+                "WARNING: Skipped {} declared in {}, direct URLs can't be locked",
+                declaration.requirement, declaration.source
+            );
+            continue;
+        }
+
+        requirements.push((declaration.requirement, declaration.source));
+    }
+
+    if requirements.is_empty() {
+        println!("The project has no requirements to lock");
+        return true;
+    }
+
+    let environment = Environment::current().with_target(options.python.as_deref(), options.platform.as_deref());
+    let target = format!(
+        "Python {} on {} ({})",
+        environment.get("python_version"),
+        environment.get("sys_platform"),
+        environment.get("platform_machine")
+    );
+
+    println!("Resolving {} requirement(s) for {}..", requirements.len(), target);
+
+    let mut resolver = Resolver::new(&options.index, &environment);
+    let pins = match resolver.resolve(&requirements) {
+        Ok(x) => x,
+        Err(e) => {
+            println!("{}", e);
+            return false;
+        }
+    };
+
+    match fs::write(&options.output, content(&pins, &target)) {
+        Ok(_) => {
+            println!("Successfully locked {} package(s) to {}", pins.len(), options.output);
+            true
+        },
+        Err(e) => {
+            println!("Error while writing to {}: {}", options.output, e);
+            false
+        }
+    }
+}
+
+/// Build the lockfile, in the requirements format pip installs from
+fn content(pins: &[Pin], target: &str) -> String {
+    let mut content = String::new();
+
+    content.push_str("# This file is generated by `pyproj lock`, do not edit it\n");
+    content.push_str(&format!("# Resolved for {}\n", target));
+
+    for pin in pins {
+        content.push_str(&format!("{}=={}\n", pin.name, pin.version));
+        content.push_str(&format!("    # via {}\n", pin.via.join(", ")));
+    }
+
+    content
+}
@@ -0,0 +1,399 @@
+//! Resolve requirements to a consistent set of versions,
+//! backtracking when a choice leads to a conflict
+
+use std::collections::HashMap;
+
+use crate::package::marker::Environment;
+use crate::package::name::normalise_name;
+use crate::package::requirement::Requirement;
+use crate::package::specifier::SpecifierSet;
+use crate::package::version::Version;
+
+use super::index::{Candidate, Index, Metadata};
+
+/// Number of candidates tried before giving up
+const MAX_ATTEMPTS: usize = 10000;
+
+/// A requirement and what it comes from
+#[derive(Clone)]
+struct Constraint {
+    requirement: Requirement,
+    /// Name of the package requiring it, or the file declaring it
+    via: String,
+}
+
+/// A version chosen for a package
+#[derive(Clone)]
+pub struct Pin {
+    /// Normalised package name
+    pub name: String,
+    pub version: Version,
+    pub candidate: Candidate,
+    /// Packages (or files) requiring it
+    pub via: Vec<String>,
+}
+
+/// Choices made so far
+#[derive(Clone, Default)]
+struct State {
+    constraints: Vec<Constraint>,
+    pins: Vec<Pin>,
+    /// Extras whose dependencies were added, as `(package, extra)`
+    extras: Vec<(String, String)>,
+}
+
+/// Resolves requirements against an index for a target environment
+pub struct Resolver<'a> {
+    index: &'a Index,
+    environment: &'a Environment,
+    candidates: HashMap<String, Vec<Candidate>>,
+    metadata: HashMap<String, Metadata>,
+    attempts: usize,
+    /// The conflict found with the most packages pinned, and how many
+    conflict: Option<(usize, String)>,
+}
+
+impl<'a> Resolver<'a> {
+    pub fn new(index: &'a Index, environment: &'a Environment) -> Resolver<'a> {
+        Resolver {
+            index,
+            environment,
+            candidates: HashMap::new(),
+            metadata: HashMap::new(),
+            attempts: 0,
+            conflict: None,
+        }
+    }
+
+    /// Resolve `(requirement, declared in)` pairs to a pin for every package needed
+    pub fn resolve(&mut self, requirements: &[(Requirement, String)]) -> Result<Vec<Pin>, String> {
+        let mut state = State::default();
+
+        for (requirement, via) in requirements {
+            if !requirement.applies(self.environment, &[]) {
+                continue;
+            }
+
+            state.constraints.push(Constraint {
+                requirement: requirement.clone(),
+                via: via.clone(),
+            });
+        }
+
+        match self.solve(state)? {
+            Some(state) => {
+                let mut pins = state.pins.clone();
+
+                for pin in pins.iter_mut() {
+                    let key = pin.name.clone();
+                    pin.via = state
+                        .constraints
+                        .iter()
+                        .filter(|x| x.requirement.key() == key)
+                        .map(|x| x.via.clone())
+                        .collect();
+                    pin.via.sort();
+                    pin.via.dedup();
+                }
+
+                pins.sort_by(|a, b| a.name.cmp(&b.name));
+                Ok(pins)
+            },
+            None => Err(match self.conflict.take() {
+                Some((_, conflict)) => format!("Couldn't find a consistent set of versions: {}", conflict),
+                None => String::from("Couldn't find a consistent set of versions"),
+            }),
+        }
+    }
+
+    /// Pin the next unpinned package, trying each of its versions in turn
+    fn solve(&mut self, state: State) -> Result<Option<State>, String> {
+        let next = state
+            .constraints
+            .iter()
+            .map(|x| x.requirement.key())
+            .find(|key| !state.pins.iter().any(|x| x.name == *key));
+
+        let package = match next {
+            Some(x) => x,
+            None => return Ok(Some(state)),
+        };
+
+        let constraints: Vec<&Constraint> = state
+            .constraints
+            .iter()
+            .filter(|x| x.requirement.key() == package)
+            .collect();
+
+        let candidates = self.matching(&package, &constraints)?;
+
+        if candidates.is_empty() {
+            let required: Vec<String> = constraints
+                .iter()
+                .map(|x| format!("{} (from {})", x.requirement, x.via))
+                .collect();
+            let conflict = match self.candidates[&package].is_empty() {
+                true => {
+                    let via: Vec<String> = constraints.iter().map(|x| x.via.clone()).collect();
+                    format!("{} isn't in the index (required by {})", package, via.join(", "))
+                },
+                false => format!("no version of {} matches {}", package, required.join(", ")),
+            };
+            self.record(state.pins.len(), conflict);
+            return Ok(None);
+        }
+
+        let extras: Vec<String> = constraints
+            .iter()
+            .flat_map(|x| x.requirement.extras.iter().map(|e| normalise_name(e)))
+            .collect();
+
+        for candidate in candidates {
+            self.attempts += 1;
+            if self.attempts > MAX_ATTEMPTS {
+                return Err(format!("Gave up after trying {} versions", MAX_ATTEMPTS));
+            }
+
+            let metadata = self.metadata(&candidate)?;
+
+            // Requires-Python is only known for certain from the metadata
+            if let Some(requires_python) = &metadata.requires_python {
+                if !self.supports_python(requires_python) {
+                    continue;
+                }
+            }
+
+            let mut next = state.clone();
+            next.pins.push(Pin {
+                name: package.clone(),
+                version: candidate.version.clone(),
+                candidate: candidate.clone(),
+                via: Vec::new(),
+            });
+            for extra in &extras {
+                next.extras.push((package.clone(), extra.clone()));
+            }
+
+            if !self.add_dependencies(&mut next, &package, &metadata, &extras)? {
+                continue;
+            }
+
+            if let Some(solved) = self.solve(next)? {
+                return Ok(Some(solved));
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Add the dependencies of a pinned package, checking them against the
+    /// existing pins (and adding the dependencies of newly requested extras)
+    ///
+    /// Returns false if one of them conflicts with a pin.
+    fn add_dependencies(&mut self, state: &mut State, name: &str, metadata: &Metadata, extras: &[String]) -> Result<bool, String> {
+        let dependencies: Vec<Requirement> = metadata
+            .requires
+            .iter()
+            .filter(|x| x.applies(self.environment, extras))
+            .cloned()
+            .collect();
+
+        for requirement in dependencies {
+            if requirement.url.is_some() {
+                println!("WARNING: Skipped {} required by {}, direct URLs can't be locked", requirement, name);
+                continue;
+            }
+
+            let key = requirement.key();
+            state.constraints.push(Constraint {
+                requirement: requirement.clone(),
+                via: name.to_string(),
+            });
+
+            let pin = match state.pins.iter().find(|x| x.name == key) {
+                Some(x) => x.clone(),
+                None => continue,
+            };
+
+            if !requirement.specifier.contains(&pin.version) {
+                self.record(
+                    state.pins.len(),
+                    format!("{} requires {} but {} {} was chosen", name, requirement, pin.name, pin.version),
+                );
+                return Ok(false);
+            }
+
+            // Extras requested after the package was pinned
+            let new: Vec<String> = requirement
+                .extras
+                .iter()
+                .map(|x| normalise_name(x))
+                .filter(|x| !state.extras.contains(&(key.clone(), x.clone())))
+                .collect();
+
+            if !new.is_empty() {
+                for extra in &new {
+                    state.extras.push((key.clone(), extra.clone()));
+                }
+
+                let all: Vec<String> = state
+                    .extras
+                    .iter()
+                    .filter(|(package, _)| *package == key)
+                    .map(|(_, extra)| extra.clone())
+                    .collect();
+
+                let metadata = self.metadata(&pin.candidate)?;
+                if !self.add_dependencies(state, &pin.name, &metadata, &all)? {
+                    return Ok(false);
+                }
+            }
+        }
+
+        Ok(true)
+    }
+
+    /// Candidates of a package matching every constraint on it, one per
+    /// version, newest first
+    fn matching(&mut self, package: &str, constraints: &[&Constraint]) -> Result<Vec<Candidate>, String> {
+        if !self.candidates.contains_key(package) {
+            let candidates = self.index.candidates(package)?;
+            self.candidates.insert(package.to_string(), candidates);
+        }
+
+        let compatible: Vec<Candidate> = self.candidates[package]
+            .iter()
+            .filter(|x| x.is_compatible(self.environment))
+            .filter(|x| match &x.requires_python {
+                Some(requires_python) => self.supports_python(requires_python),
+                None => true,
+            })
+            .filter(|x| constraints.iter().all(|c| c.requirement.specifier.contains(&x.version)))
+            .cloned()
+            .collect();
+
+        // Pre-releases are only used if they are asked for, or nothing else matches
+        let asked = constraints.iter().any(|c| {
+            c.requirement
+                .specifier
+                .specifiers
+                .iter()
+                .any(|s| Version::parse(&s.version).map(|v| is_pre(&v)).unwrap_or(false))
+        });
+        let has_final = compatible.iter().any(|x| !is_pre(&x.version));
+
+        let mut matching: Vec<Candidate> = Vec::new();
+        for candidate in compatible {
+            if is_pre(&candidate.version) && !asked && has_final {
+                continue;
+            }
+            if !matching.iter().any(|x| x.version == candidate.version) {
+                matching.push(candidate);
+            }
+        }
+
+        Ok(matching)
+    }
+
+    /// Metadata of a candidate, read once
+    fn metadata(&mut self, candidate: &Candidate) -> Result<Metadata, String> {
+        if let Some(metadata) = self.metadata.get(&candidate.location) {
+            return Ok(metadata.clone());
+        }
+
+        let metadata = candidate.metadata()?;
+        self.metadata.insert(candidate.location.clone(), metadata.clone());
+        Ok(metadata)
+    }
+
+    fn supports_python(&self, requires_python: &SpecifierSet) -> bool {
+        match Version::parse(self.environment.get("python_full_version")) {
+            Some(version) => requires_python.contains(&version),
+            None => true,
+        }
+    }
+
+    /// Keep the conflict found deepest in the search, it is the most useful to show
+    fn record(&mut self, depth: usize, conflict: String) {
+        let deeper = match &self.conflict {
+            Some((previous, _)) => depth >= *previous,
+            None => true,
+        };
+
+        if deeper {
+            self.conflict = Some((depth, conflict));
+        }
+    }
+}
+
+fn is_pre(version: &Version) -> bool {
+    version.pre.is_some() || version.dev.is_some()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::io::Write;
+    use std::path::Path;
+
+    use super::*;
+
+    /// Write a wheel holding only its METADATA
+    fn wheel(dir: &Path, name: &str, version: &str, requires: &[&str]) {
+        let file = fs::File::create(dir.join(format!("{}-{}-py3-none-any.whl", name, version))).unwrap();
+        let mut archive = zip::ZipWriter::new(file);
+
+        let mut metadata = format!("Metadata-Version: 2.1\nName: {}\nVersion: {}\n", name, version);
+        for x in requires {
+            metadata.push_str(&format!("Requires-Dist: {}\n", x));
+        }
+
+        archive
+            .start_file(format!("{}-{}.dist-info/METADATA", name, version), zip::write::FileOptions::default())
+            .unwrap();
+        archive.write_all(metadata.as_bytes()).unwrap();
+        archive.finish().unwrap();
+    }
+
+    fn resolve(dir: &Path, requirements: &[&str]) -> Result<Vec<(String, String)>, String> {
+        let index = Index::FindLinks(dir.to_path_buf());
+        let environment = Environment {
+            values: [("python_version", "3.8"), ("sys_platform", "linux")]
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+        };
+
+        let requirements: Vec<(Requirement, String)> = requirements
+            .iter()
+            .map(|x| (Requirement::parse(x).unwrap(), String::from("requirements.txt")))
+            .collect();
+
+        let pins = Resolver::new(&index, &environment).resolve(&requirements)?;
+        Ok(pins.into_iter().map(|x| (x.name, x.version.to_string())).collect())
+    }
+
+    fn pins(pins: &[(&str, &str)]) -> Vec<(String, String)> {
+        pins.iter().map(|(name, version)| (name.to_string(), version.to_string())).collect()
+    }
+
+    #[test]
+    fn conflicts_are_backtracked() {
+        let dir = std::env::temp_dir().join(format!("pyproj-resolver-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+
+        wheel(&dir, "a", "1.0", &[]);
+        wheel(&dir, "a", "2.0", &["c<2"]);
+        wheel(&dir, "b", "1.0", &["c>=2", "d; sys_platform == 'win32'"]);
+        wheel(&dir, "c", "1.0", &[]);
+        wheel(&dir, "c", "2.0", &[]);
+        wheel(&dir, "c", "3.0", &["e"]);
+
+        // The newest a needs an older c than b allows, c 3.0 needs e which doesn't exist
+        assert_eq!(resolve(&dir, &["a", "b"]), Ok(pins(&[("a", "1.0"), ("b", "1.0"), ("c", "2.0")])));
+        assert_eq!(resolve(&dir, &["a>=2", "c"]), Ok(pins(&[("a", "2.0"), ("c", "1.0")])));
+        assert!(resolve(&dir, &["a>=2", "b"]).is_err());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
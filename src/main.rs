@@ -2,20 +2,36 @@
 extern crate lazy_static;
 
 mod constants;
+mod advisory; // audit subcommand
 mod explorer; // explore directories
 
+mod config; // setup.cfg files
+
 mod create; // create subcommand
+mod datetime; // format dates and times
+mod digest; // format hash digests
+mod clean; // clean subcommand
+mod build; // build subcommand
+mod cache; // cache subcommand
+mod check; // check subcommand
+mod deps; // deps subcommand
+mod graph; // graph subcommand
 mod interpreter; // interpret py files
+mod licenses; // licenses subcommand
+mod lock; // lock subcommand
+mod package; // python packaging standards
+mod pool; // run work across threads
+mod report; // tables and notes shared by reports
+mod sbom; // sbom subcommand
+mod testing; // test subcommand
 
 use std::option::Option::{Some, None};
 use std::env::{args, Args};
 use std::result::Result;
 use std::fs;
 use std::path::{Path, PathBuf};
-use std::process::{Command, Stdio};
-use std::time::Instant;
 
-use explorer::Explorer;
+use interpreter::notebook::Notebook;
 
 fn main() {
     let mut args = args();
@@ -42,6 +58,42 @@ fn main() {
                 "test" => {
                     // Run unit tests
                     cmd_test(&mut args);
+                },
+                "nb" => {
+                    // Jupyter notebook tools
+                    cmd_nb(&mut args);
+                },
+                "graph" => {
+                    // Export the module import graph
+                    cmd_graph(&mut args);
+                },
+                "check" => {
+                    // Check the project for problems
+                    cmd_check(&mut args);
+                },
+                "deps" => {
+                    // Inspect dependencies
+                    cmd_deps(&mut args);
+                },
+                "lock" => {
+                    // Lock dependencies
+                    cmd_lock(&mut args);
+                },
+                "audit" => {
+                    // Check dependencies for known vulnerabilities
+                    cmd_audit(&mut args);
+                },
+                "licenses" => {
+                    // Report the licenses of dependencies
+                    cmd_licenses(&mut args);
+                },
+                "cache" => {
+                    // Manage the analysis cache
+                    cmd_cache(&mut args);
+                },
+                "sbom" => {
+                    // Export a software bill of materials
+                    cmd_sbom(&mut args);
                 }
 
                 _ => {
@@ -56,65 +108,272 @@ fn main() {
 
 /// Subcommand to run unit tests
 fn cmd_test(args: &mut Args) {
-    let target = match args.next() {
-        Some(f) => {
-            if !f.ends_with(".py") {
-                format!("tests/{}.py", f)
-            } else {
-                format!("tests/{}", f)
+    let mut target: Option<String> = None;
+    let mut keyword: Option<String> = None;
+    let mut runner: Option<testing::runner::Runner> = None;
+    let mut extra: Vec<String> = Vec::new();
+    let mut junit: Option<String> = None;
+    let mut json: Option<String> = None;
+    let mut list = false;
+    let mut flat = false;
+    let mut failed = false;
+    let mut failed_first = false;
+    let mut history: Option<String> = None;
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-k" => match args.next() {
+                Some(x) => keyword = Some(x),
+                None => {
+                    println!("Missing expression after -k");
+                    return;
+                }
+            },
+            "--list" => list = true,
+            "--flat" => flat = true,
+            "--failed" => failed = true,
+            "--failed-first" => failed_first = true,
+            "--history" => match args.next() {
+                Some(x) => history = Some(x),
+                None => {
+                    println!("Missing test after --history");
+                    return;
+                }
+            },
+            "--junit" => junit = args.next(),
+            "--json" => json = args.next(),
+            "--runner" => {
+                let name = args.next().unwrap_or_default();
+                match testing::runner::Runner::from_name(&name) {
+                    Some(x) => runner = Some(x),
+                    None => {
+                        println!("Unknown runner '{}' (expected one of pytest, unittest, nose2)", name);
+                        return;
+                    }
+                }
+            },
+            // Everything after `--` goes to the runner
+            "--" => extra.extend(args.by_ref()),
+            _ if target.is_none() && !arg.starts_with('-') => target = Some(arg),
+            _ => {
+                println!("Unknown argument {}", arg);
+                return;
             }
+        }
+    }
+
+    if list {
+        if !testing::list_tests(flat) {
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    if let Some(test) = history {
+        if !testing::show_history(&test) {
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    if failed && target.is_some() {
+        println!("--failed runs the tests which failed last time, it can't be given a target");
+        return;
+    }
+
+    let options = testing::TestOptions {
+        target, keyword, runner, extra, junit, json, failed, failed_first,
+    };
+
+    if !testing::run_tests(&options) {
+        std::process::exit(1);
+    }
+}
+
+/// Subcommand to work with Jupyter notebooks
+fn cmd_nb(args: &mut Args) {
+    match args.next().as_deref() {
+        Some("export") => {},
+        Some(x) => {
+            println!("Unknown nb subcommand {}", x);
+            return;
         },
-        None => String::from("tests")
+        None => {
+            println!("Missing nb subcommand (export)");
+            return;
+        }
     };
 
-    let command = format!("python -m unittest {}", target);
+    let mut notebook: Option<String> = None;
+    let mut module: Option<String> = None;
+    let mut force = false;
+
+    for arg in args {
+        match arg.as_str() {
+            "--force" => force = true,
+            _ if notebook.is_none() => notebook = Some(arg),
+            _ => module = Some(arg),
+        }
+    }
+
+    let notebook = match notebook {
+        Some(x) => PathBuf::from(x),
+        None => {
+            println!("Missing argument notebook");
+            return;
+        }
+    };
 
-    // Create a new command process to run unit tests
-    println!("Running unit tests..");
-    
-    let start = Instant::now();
+    // Make sure the env is valid
+    match validate_env() {
+        Ok(_) => {},
+        Err(x) => {
+            println!("Current working directory is not valid: {}", x);
+            return;
+        }
+    };
 
-    let process = Command::new("cmd")
-        .args(&["/c", command.as_str()])
-        .stdout(Stdio::inherit())
-        .status()
-        .expect("failed to execute process");
+    let nb = match Notebook::load(&notebook) {
+        Ok(x) => x,
+        Err(e) => {
+            println!("Error while reading {}: {}", notebook.display(), e);
+            return;
+        }
+    };
 
-    let elapsed = start.elapsed().as_millis();
+    // Default to the notebook's name
+    let module = match module {
+        Some(x) => x.trim_end_matches(".py").to_string(),
+        None => notebook
+            .file_stem()
+            .map(|x| x.to_string_lossy().replace(['-', ' ', '.'], "_"))
+            .unwrap_or_default(),
+    };
 
-    match process.success() {
-        true => println!("\n------------- SUCCESSFUL -------------"),
-        false => println!("\n------------ UNSUCCESSFUL ------------"),
+    let target = match module_path(&module) {
+        Some(x) => x,
+        None => {
+            println!("Invalid module name '{}' (expected a dotted Python name)", module);
+            return;
+        }
     };
 
-    println!(
-        "Elapsed {elapsed}ms\nCompleted with exit code {code}", 
-        elapsed=elapsed, 
-        code=process.code().unwrap()
-    );
+    // The module's parts are identifiers, but a package may be a link
+    // pointing out of the project
+    let inside = target
+        .parent()
+        .and_then(|x| x.canonicalize().ok())
+        .zip(Path::new("src").canonicalize().ok())
+        .map(|(parent, src)| parent.starts_with(src))
+        .unwrap_or(false);
+
+    if !inside {
+        println!("{} isn't in an existing package under src", target.display());
+        return;
+    }
+
+    if target.exists() && !force {
+        println!("{} already exists (use --force to overwrite)", target.display());
+        return;
+    }
+
+    match fs::write(&target, nb.to_python()) {
+        Ok(_) => println!("Successfully exported {} to {}", notebook.display(), target.display()),
+        Err(e) => println!("Error while writing to {}: {}", target.display(), e),
+    };
 }
 
-/// Subcommand to display help
-fn cmd_help() {
-    println!("Python project manager created with Rust.\nYou may need to cd into your projects to use most commands.\nCommands:");
-    for (k, v) in constants::COMMANDS.iter() {
-        println!("\t{cmd}\t\t{desc}\n\t\t\tUsage: {usage}\n",
-                cmd=k, desc=v[0], usage=v[1]);
+/// Subcommand to export the import graph of the project's modules
+fn cmd_graph(args: &mut Args) {
+    let mut format = String::from("dot");
+    let mut output: Option<String> = None;
+    let mut focus: Option<String> = None;
+    let mut collapse = false;
+    let mut third_party = false;
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--format" => format = args.next().unwrap_or_default(),
+            "--output" => output = args.next(),
+            "--focus" => focus = args.next(),
+            "--collapse" => collapse = true,
+            "--third-party" => third_party = true,
+            _ => {
+                println!("Unknown argument {}", arg);
+                return;
+            }
+        }
+    }
+
+    if !graph::export::FORMATS.contains(&format.as_str()) {
+        println!("Unknown format '{}' (expected one of {})", format, graph::export::FORMATS.join(", "));
+        return;
+    }
+
+    // Make sure the env is valid
+    match validate_env() {
+        Ok(_) => {},
+        Err(x) => {
+            println!("Current working directory is not valid: {}", x);
+            return;
+        }
+    };
+
+    let mut module_graph = graph::ModuleGraph::build(Path::new("src"));
+
+    // Keep warnings out of graphs printed to the console
+    for diagnostic in &module_graph.diagnostics {
+        eprintln!("WARNING: Skipped {}", diagnostic);
+    }
+
+    if !third_party {
+        module_graph = module_graph.without_third_party();
     }
+
+    if collapse {
+        module_graph = module_graph.collapse();
+    }
+
+    let focus = match focus {
+        Some(module) => {
+            if !module_graph.nodes.contains_key(&module) {
+                println!("Unknown module '{}'", module);
+                return;
+            }
+            Some(graph::export::Focus::new(&module_graph, &module))
+        },
+        None => None,
+    };
+
+    let exported = graph::export::export(&module_graph, &format, focus.as_ref()).unwrap();
+
+    match output {
+        Some(file) => match fs::write(&file, exported) {
+            Ok(_) => println!("Successfully exported the import graph to {}", file),
+            Err(e) => println!("Error while writing to {}: {}", file, e),
+        },
+        None => print!("{}", exported),
+    };
 }
 
-/// Subcommand to build certain files
-/// (requirements.txt, etc.)
-fn cmd_build<'a>(_args: &mut Args)  {
-    fn is_py(x: &PathBuf) -> bool {
-        match x.extension() {
-            Some(ext) => {
-                match ext.to_str().unwrap() {
-                    "py" => true,
-                    _ => false,
-                }
-            },
-            None => false,
+/// Subcommand to check the project for problems
+fn cmd_check(args: &mut Args) {
+    let check = match args.next() {
+        Some(x) => x,
+        None => {
+            println!("Missing check (cycles, python)");
+            return;
+        }
+    };
+
+    let mut write = false;
+    for arg in args {
+        match arg.as_str() {
+            "--write" if check == "python" => write = true,
+            _ => {
+                println!("Unknown argument {}", arg);
+                return;
+            }
         }
     }
 
@@ -127,69 +386,230 @@ fn cmd_build<'a>(_args: &mut Args)  {
         }
     };
 
-    // Get all python files
-    let mut explorer = Explorer::new(10u32);
-    let mut path = PathBuf::new();
-    path.push("src");
+    let passed = match check.as_str() {
+        "cycles" => check::check_cycles(),
+        "python" => check::python::check_python(write),
+        _ => {
+            println!("Unknown check {}", check);
+            return;
+        }
+    };
 
-    explorer.explore(&path, &is_py, 0u32).unwrap();
+    if !passed {
+        std::process::exit(1);
+    }
+}
 
-    // Get all modules in each file
-    let mut modules: Vec<String> = Vec::new();
-    let py_files = vec_to_filenames(&explorer.results);
+/// Subcommand to inspect the project's dependencies
+fn cmd_deps(args: &mut Args) {
+    let command = match args.next() {
+        Some(x) => x,
+        None => {
+            println!("Missing deps subcommand (audit, tree, why)");
+            return;
+        }
+    };
+
+    let mut dev = false;
+    let mut package: Option<String> = None;
+
+    for arg in args {
+        match arg.as_str() {
+            "--dev" if command == "tree" => dev = true,
+            _ if command == "why" && package.is_none() && !arg.starts_with('-') => package = Some(arg),
+            _ => {
+                println!("Unknown argument {}", arg);
+                return;
+            }
+        }
+    }
+
+    // Make sure the env is valid
+    match validate_env() {
+        Ok(_) => {},
+        Err(x) => {
+            println!("Current working directory is not valid: {}", x);
+            return;
+        }
+    };
 
-    for file in explorer.results {
-        // Read through all lines and find import statements
-        //let parser = ;
+    let passed = match command.as_str() {
+        "audit" => deps::audit::audit(),
+        "tree" => deps::tree::tree(dev),
+        "why" => match package {
+            Some(x) => deps::tree::why(&x),
+            None => {
+                println!("Missing package name");
+                return;
+            }
+        },
+        _ => {
+            println!("Unknown deps subcommand {}", command);
+            return;
+        }
+    };
 
-        for line in interpreter::Parser::new(&Path::new(&file)).lines() {
-            let unwrapped = &line.unwrap();
+    if !passed {
+        std::process::exit(1);
+    }
+}
 
-            if interpreter::Parser::is_import(unwrapped) {
-                // Find the module
-                match interpreter::Parser::get_import_module(unwrapped) {
-                    Some(mut m) => {
-                        m.retain(|i| !py_files.iter().any(|x| x == i) && !interpreter::Parser::is_built_in(i));
-                        modules.append(&mut m);
-                    },
-                    None => {},
+/// Subcommand to resolve the project's requirements to a lockfile
+fn cmd_lock(args: &mut Args) {
+    let mut index: Option<lock::index::Index> = None;
+    let mut dev = false;
+    let mut python: Option<String> = None;
+    let mut platform: Option<String> = None;
+    let mut output = String::from(lock::LOCKFILE);
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--find-links" | "--index" | "--python" | "--platform" | "--output" => {
+                let value = match args.next() {
+                    Some(x) => x,
+                    None => {
+                        println!("Missing value for {}", arg);
+                        return;
+                    }
+                };
+
+                match arg.as_str() {
+                    "--find-links" => index = Some(lock::index::Index::FindLinks(PathBuf::from(value))),
+                    "--index" => index = Some(lock::index::Index::Simple(value)),
+                    "--python" => python = Some(value),
+                    "--platform" => platform = Some(value),
+                    _ => output = value,
                 }
+            },
+            "--dev" => dev = true,
+            _ => {
+                println!("Unknown argument {}", arg);
+                return;
             }
         }
     }
-    
-    // Get versions for each module
-    let versions = interpreter::Parser::with_versions(&modules);
 
-    // Build final requirements.txt string
-    let mut requirements_str = String::new();
+    let index = match index {
+        Some(x) => x,
+        None => {
+            println!("Missing package source, use --find-links <dir> or --index <dir|url>");
+            return;
+        }
+    };
+
+    // Make sure the env is valid
+    match validate_env() {
+        Ok(_) => {},
+        Err(x) => {
+            println!("Current working directory is not valid: {}", x);
+            return;
+        }
+    };
 
-    for (module, version) in versions.iter() {
-        requirements_str.push_str(
-            &format!("{}=={}\n", module, version)
-        );
+    let options = lock::LockOptions {
+        index,
+        dev,
+        python,
+        platform,
+        output,
+    };
+
+    if !lock::lock(&options) {
+        std::process::exit(1);
     }
+}
+
+/// Subcommand to check the project's dependencies for known vulnerabilities
+fn cmd_audit(args: &mut Args) {
+    let mut database: Option<PathBuf> = None;
+    let mut format = String::from("table");
+    let mut lockfile: Option<String> = None;
+    let mut output: Option<String> = None;
+    let mut fail_on = advisory::osv::Severity::Low;
+    let mut fail_on_unknown = true;
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--db" => database = args.next().map(PathBuf::from),
+            "--format" => format = args.next().unwrap_or_default(),
+            "--lockfile" => lockfile = args.next(),
+            "--output" => output = args.next(),
+            "--ignore-unknown" => fail_on_unknown = false,
+            "--fail-on" => {
+                let level = args.next().unwrap_or_default();
+                fail_on = advisory::osv::Severity::parse(&level);
+
+                if fail_on == advisory::osv::Severity::Unknown {
+                    println!("Unknown severity '{}' (expected one of low, medium, high, critical)", level);
+                    return;
+                }
+            },
+            _ => {
+                println!("Unknown argument {}", arg);
+                return;
+            }
+        }
+    }
+
+    if !advisory::report::FORMATS.contains(&format.as_str()) {
+        println!("Unknown format '{}' (expected one of {})", format, advisory::report::FORMATS.join(", "));
+        return;
+    }
+
+    let database = match database {
+        Some(x) => x,
+        None => {
+            println!("Missing advisory database, use --db <dir|zip>");
+            return;
+        }
+    };
+
+    // Make sure the env is valid
+    match validate_env() {
+        Ok(_) => {},
+        Err(x) => {
+            println!("Current working directory is not valid: {}", x);
+            return;
+        }
+    };
 
-    match fs::write("requirements.txt", requirements_str) {
-        Ok(_) => {println!("Successfully updated requirements.txt");},
-        Err(e) => {println!("Error while writing to requirements.txt: {}", e);}
+    let options = advisory::AuditOptions {
+        database,
+        format,
+        lockfile,
+        output,
+        fail_on,
+        fail_on_unknown,
     };
+
+    if !advisory::audit(&options) {
+        std::process::exit(1);
+    }
 }
 
-/// Subcommand to clean cache and stuff
-fn cmd_clean(_args: &mut Args) {
-    /// Check if a file/dir is removable
-    fn is_removable(x: &PathBuf) -> bool {
-        let x = x
-            .file_name().unwrap()
-            .to_str().unwrap();
-    
-        match x {
-            "__pycache__" | "build" => true,
-            _ => false,
+/// Subcommand to report the licenses of the project's dependencies
+fn cmd_licenses(args: &mut Args) {
+    let mut dev = false;
+    let mut format = String::from("table");
+    let mut output: Option<String> = None;
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--dev" => dev = true,
+            "--format" => format = args.next().unwrap_or_default(),
+            "--output" => output = args.next(),
+            _ => {
+                println!("Unknown argument {}", arg);
+                return;
+            }
         }
     }
 
+    if !licenses::report::FORMATS.contains(&format.as_str()) {
+        println!("Unknown format '{}' (expected one of {})", format, licenses::report::FORMATS.join(", "));
+        return;
+    }
+
     // Make sure the env is valid
     match validate_env() {
         Ok(_) => {},
@@ -199,23 +619,177 @@ fn cmd_clean(_args: &mut Args) {
         }
     };
 
-    // Walk through the directory and remove cache directories
-    let mut explorer: Explorer = Explorer::new(10u32);
-    
-    let mut path = PathBuf::new();
-    path.push("src");
+    let options = licenses::LicenseOptions { dev, format, output };
 
-    explorer.explore(
-        &path,
-        &is_removable,
-        0u32,
-    ).unwrap();
+    if !licenses::licenses(&options) {
+        std::process::exit(1);
+    }
+}
 
-    for dir in &explorer.results {
-        fs::remove_dir_all(dir).unwrap();
+/// Subcommand to export a software bill of materials of the project
+fn cmd_sbom(args: &mut Args) {
+    let mut dev = false;
+    let mut format = String::from("cyclonedx");
+    let mut output: Option<String> = None;
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--dev" => dev = true,
+            "--format" => format = args.next().unwrap_or_default(),
+            "--output" => output = args.next(),
+            _ => {
+                println!("Unknown argument {}", arg);
+                return;
+            }
+        }
+    }
+
+    if !sbom::FORMATS.contains(&format.as_str()) {
+        println!("Unknown format '{}' (expected one of {})", format, sbom::FORMATS.join(", "));
+        return;
     }
 
-    // Possibly remove unused dependencies
+    // Make sure the env is valid
+    match validate_env() {
+        Ok(_) => {},
+        Err(x) => {
+            println!("Current working directory is not valid: {}", x);
+            return;
+        }
+    };
+
+    let options = sbom::SbomOptions { format, dev, output };
+
+    if !sbom::sbom(&options) {
+        std::process::exit(1);
+    }
+}
+
+/// Subcommand to manage the cache of parsed files
+fn cmd_cache(args: &mut Args) {
+    match args.next().as_deref() {
+        Some("clear") => {},
+        Some(x) => {
+            println!("Unknown cache subcommand {}", x);
+            return;
+        },
+        None => {
+            println!("Missing cache subcommand (clear)");
+            return;
+        }
+    };
+
+    if let Some(arg) = args.next() {
+        println!("Unknown argument {}", arg);
+        return;
+    }
+
+    match cache::clear() {
+        Ok(0) => println!("The cache is already empty"),
+        Ok(size) => println!("Successfully cleared the cache ({} bytes)", size),
+        Err(e) => {
+            println!("Error while clearing {}: {}", cache::CACHE_DIR, e);
+            std::process::exit(1);
+        }
+    };
+}
+
+/// Subcommand to display help
+fn cmd_help() {
+    println!("Python project manager created with Rust.\nYou may need to cd into your projects to use most commands.\nCommands:");
+    for (k, v) in constants::COMMANDS.iter() {
+        println!("\t{cmd}\t\t{desc}\n\t\t\tUsage: {usage}\n",
+                cmd=k, desc=v[0], usage=v[1]);
+    }
+}
+
+/// Subcommand to build certain files
+/// (requirements.txt, etc.)
+fn cmd_build(args: &mut Args)  {
+    let mut force = false;
+    let mut check = false;
+    let mut strict = false;
+    let mut policy: Option<build::policy::Policy> = None;
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--force" => force = true,
+            "--check" => check = true,
+            "--strict" => strict = true,
+            "--policy" => {
+                let name = args.next().unwrap_or_default();
+                policy = match build::policy::Policy::from_name(&name) {
+                    Some(x) => Some(x),
+                    None => {
+                        println!("Unknown version policy '{}'", name);
+                        return;
+                    }
+                };
+            },
+            _ => {
+                println!("Unknown argument {}", arg);
+                return;
+            }
+        }
+    }
+
+    if strict && !check {
+        println!("--strict can only be used with --check");
+        return;
+    }
+
+    // Make sure the env is valid
+    match validate_env() {
+        Ok(_) => {},
+        Err(x) => {
+            println!("Current working directory is not valid: {}", x);
+            return;
+        }
+    };
+
+    // Fail when checking so CI pipelines stop on out of date files
+    match build::build_requirements(force, policy, check, strict) {
+        Ok(true) => {},
+        Ok(false) => std::process::exit(1),
+        Err(e) => {
+            println!("{}", e);
+            if check {
+                std::process::exit(1);
+            }
+        }
+    }
+}
+
+/// Subcommand to clean cache and stuff
+fn cmd_clean(args: &mut Args) {
+    let mut all = false;
+    let mut trash = false;
+
+    for arg in args {
+        match arg.as_str() {
+            "--all" => all = true,
+            "--trash" => trash = true,
+            _ => {
+                println!("Unknown argument {}", arg);
+                return;
+            }
+        }
+    }
+
+    // Make sure the env is valid
+    match validate_env() {
+        Ok(_) => {},
+        Err(x) => {
+            println!("Current working directory is not valid: {}", x);
+            return;
+        }
+    };
+
+    let options = clean::CleanOptions { all, trash };
+
+    if !clean::clean(&options) {
+        std::process::exit(1);
+    }
 }
 
 /// Subcommand to create a new project
@@ -252,6 +826,46 @@ fn validate_name(s: &str) -> bool {
     }
 }
 
+/// Path of the file a dotted module name (`tools.report`) is written to,
+/// from the project's package directory, or None if the name isn't a
+/// dotted Python identifier
+fn module_path(module: &str) -> Option<PathBuf> {
+    let identifier = |x: &str| {
+        x.chars().next().map(|c| c.is_alphabetic() || c == '_').unwrap_or(false)
+            && x.chars().all(|c| c.is_alphanumeric() || c == '_')
+    };
+
+    let parts: Vec<&str> = module.split('.').collect();
+
+    if !validate_name(module) || !parts.iter().all(|x| identifier(x)) {
+        return None;
+    }
+
+    let mut path = package_dir();
+    path.extend(&parts);
+    Some(path.with_extension("py"))
+}
+
+/// Get the project's package directory (./src/<package> if there is
+/// exactly one package, otherwise ./src)
+fn package_dir() -> PathBuf {
+    let src = PathBuf::from("src");
+
+    let packages: Vec<PathBuf> = match fs::read_dir(&src) {
+        Ok(entries) => entries
+            .map_while(Result::ok)
+            .map(|x| x.path())
+            .filter(|x| x.is_dir() && x.file_name().map(|n| n != "__pycache__").unwrap_or(false))
+            .collect(),
+        Err(_) => Vec::new(),
+    };
+
+    match packages.as_slice() {
+        [package] => package.clone(),
+        _ => src,
+    }
+}
+
 /// Ensure the current environment has a src file
 fn validate_env() -> Result<(), &'static str> {
     match std::path::Path::new("src").exists() {
@@ -260,18 +874,17 @@ fn validate_env() -> Result<(), &'static str> {
     }
 }
 
-/// Get filenames of a vector of .py paths
-fn vec_to_filenames(v: &Vec<String>) -> Vec<String> {
-    let mut n: Vec<String> = Vec::new();
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    for i in v {
-        let split: String = i
-            .split("\\")
-            .filter(|i| i.ends_with(".py"))
-            .map(|i| i.to_string().replace(".py", ""))
-            .collect();
-        n.push(split);
-    }
+    #[test]
+    fn module_paths_stay_in_the_package() {
+        for module in ["../../x", "a/b", "a\\b", "a..b", ".a", "a.", "1a", "a-b", ""] {
+            assert!(module_path(module).is_none(), "{}", module);
+        }
 
-    n
-}
\ No newline at end of file
+        assert!(module_path("tools.analysis").unwrap().ends_with("tools/analysis.py"));
+        assert!(module_path("_private2").unwrap().ends_with("_private2.py"));
+    }
+}
@@ -0,0 +1,503 @@
+//! Find the distributions installed in the current Python environment
+//! by reading their `.dist-info` folders
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use super::marker::Environment;
+use super::name::normalise_name;
+use super::requirement::Requirement;
+
+/// An installed distribution
+pub struct Distribution {
+    /// Name as written in its metadata
+    pub name: String,
+    pub version: String,
+    /// Top-level modules it installs
+    pub modules: Vec<String>,
+    /// Distributions it requires (`Requires-Dist`), including
+    /// those only needed by an extra or another environment
+    pub requires: Vec<Requirement>,
+    /// License metadata (`License`, `License-Expression` and `License ::` classifiers)
+    pub license: License,
+    pub author: Option<String>,
+    pub home_page: Option<String>,
+    /// The `.dist-info` folder
+    pub path: PathBuf,
+}
+
+/// A file installed by a distribution, as listed in its `RECORD`
+pub struct RecordFile {
+    /// Path relative to the folder the distribution is installed in
+    pub path: String,
+    /// Hash algorithm (`sha256`) and hex digest
+    pub hash: Option<(String, String)>,
+}
+
+/// License information of a distribution, as written in its metadata
+#[derive(Default)]
+pub struct License {
+    /// `License-Expression` (an SPDX expression, PEP 639)
+    pub expression: Option<String>,
+    /// `License` (a name, an SPDX identifier or the whole license text)
+    pub text: Option<String>,
+    /// `License ::` trove classifiers
+    pub classifiers: Vec<String>,
+    /// License files bundled in the `.dist-info` folder
+    pub files: Vec<PathBuf>,
+}
+
+/// Find every distribution installed for the `python` on path
+pub fn find_distributions() -> Vec<Distribution> {
+    let mut distributions: Vec<Distribution> = Vec::new();
+
+    for dir in site_packages() {
+        let entries = match fs::read_dir(&dir) {
+            Ok(x) => x,
+            Err(_) => continue,
+        };
+
+        let mut paths: Vec<PathBuf> = entries
+            .filter_map(|x| x.ok())
+            .map(|x| x.path())
+            .filter(|x| x.is_dir() && x.extension().map(|e| e == "dist-info").unwrap_or(false))
+            .collect();
+        paths.sort();
+
+        for path in paths {
+            let distribution = match Distribution::load(&path) {
+                Some(x) => x,
+                None => continue,
+            };
+
+            // The first distribution on the path wins, like Python's import system
+            let name = normalise_name(&distribution.name);
+            if !distributions.iter().any(|x| normalise_name(&x.name) == name) {
+                distributions.push(distribution);
+            }
+        }
+    }
+
+    distributions
+}
+
+/// Map every top-level module to the name of the distribution installing it
+pub fn module_owners(distributions: &[Distribution]) -> HashMap<String, String> {
+    let mut owners: HashMap<String, String> = HashMap::new();
+
+    for distribution in distributions {
+        for module in &distribution.modules {
+            owners
+                .entry(module.clone())
+                .or_insert_with(|| distribution.name.clone());
+        }
+    }
+
+    owners
+}
+
+impl Distribution {
+    /// Read a `.dist-info` folder
+    pub fn load(path: &Path) -> Option<Distribution> {
+        let metadata = fs::read_to_string(path.join("METADATA")).unwrap_or_default();
+        let headers = headers(&metadata);
+
+        // Fall back to the folder's name (`name-version.dist-info`)
+        let stem = path.file_stem()?.to_string_lossy().to_string();
+        let (dir_name, dir_version) = stem.split_once('-').unwrap_or((&stem, ""));
+
+        let name = header(&headers, "Name").unwrap_or_else(|| dir_name.to_string());
+        let version = header(&headers, "Version").unwrap_or_else(|| dir_version.to_string());
+
+        let mut modules = top_level_modules(path);
+        if modules.is_empty() {
+            modules.push(name.replace('-', "_").to_lowercase());
+        }
+
+        let requires = headers
+            .iter()
+            .filter(|(key, _)| key.eq_ignore_ascii_case("Requires-Dist"))
+            .filter_map(|(_, value)| Requirement::parse(value).ok())
+            .collect();
+
+        let license = License {
+            expression: header(&headers, "License-Expression"),
+            text: header(&headers, "License").filter(|x| !x.is_empty() && x != "UNKNOWN"),
+            classifiers: headers
+                .iter()
+                .filter(|(key, value)| key.eq_ignore_ascii_case("Classifier") && value.starts_with("License ::"))
+                .map(|(_, value)| value.clone())
+                .collect(),
+            files: license_files(path, &headers),
+        };
+
+        let author = header(&headers, "Author")
+            .or_else(|| header(&headers, "Author-email"))
+            .filter(|x| !x.is_empty() && x != "UNKNOWN");
+
+        // Project-URL is `Label, https://...`
+        let home_page = header(&headers, "Home-page")
+            .filter(|x| !x.is_empty() && x != "UNKNOWN")
+            .or_else(|| {
+                headers
+                    .iter()
+                    .filter(|(key, _)| key.eq_ignore_ascii_case("Project-URL"))
+                    .filter_map(|(_, value)| value.split_once(','))
+                    .find(|(label, _)| ["homepage", "home", "source", "repository"].contains(&label.trim().to_lowercase().as_str()))
+                    .map(|(_, url)| url.trim().to_string())
+            });
+
+        Some(Distribution { name, version, modules, requires, license, author, home_page, path: path.to_path_buf() })
+    }
+
+    /// Files listed in the distribution's `RECORD`
+    pub fn record(&self) -> Vec<RecordFile> {
+        let record = fs::read_to_string(self.path.join("RECORD")).unwrap_or_default();
+
+        record
+            .lines()
+            .filter_map(|line| {
+                // `path,sha256=<urlsafe base64>,size`, where the path may be quoted
+                let (path, rest) = match line.strip_prefix('"') {
+                    Some(quoted) => {
+                        let end = quoted.find("\",")?;
+                        (quoted[..end].replace("\"\"", "\""), &quoted[end + 2..])
+                    },
+                    None => {
+                        let (path, rest) = line.split_once(',')?;
+                        (path.to_string(), rest)
+                    },
+                };
+
+                let hash = rest
+                    .split(',')
+                    .next()
+                    .and_then(|x| x.split_once('='))
+                    .and_then(|(algorithm, digest)| Some((algorithm.to_string(), base64_to_hex(digest)?)));
+
+                Some(RecordFile { path, hash })
+            })
+            .filter(|x| !x.path.is_empty())
+            .collect()
+    }
+
+    /// Folder the distribution's files are installed relative to (site-packages)
+    pub fn root(&self) -> &Path {
+        self.path.parent().unwrap_or(&self.path)
+    }
+}
+
+/// Convert a urlsafe base64 digest (as written in `RECORD`) to hex
+fn base64_to_hex(digest: &str) -> Option<String> {
+    let mut bits: u32 = 0;
+    let mut count = 0;
+    let mut hex = String::new();
+
+    for c in digest.trim_end_matches('=').chars() {
+        let value = match c {
+            'A'..='Z' => c as u32 - 'A' as u32,
+            'a'..='z' => c as u32 - 'a' as u32 + 26,
+            '0'..='9' => c as u32 - '0' as u32 + 52,
+            '-' | '+' => 62,
+            '_' | '/' => 63,
+            _ => return None,
+        };
+
+        bits = (bits << 6) | value;
+        count += 6;
+
+        if count >= 8 {
+            count -= 8;
+            hex.push_str(&format!("{:02x}", (bits >> count) & 0xff));
+        }
+    }
+
+    Some(hex)
+}
+
+/// Distributions needed by some requirements (and everything they require)
+/// in an environment with the extras requested of each, and the
+/// requirements which aren't installed
+pub fn required_distributions<'a>(
+    distributions: &'a [Distribution],
+    requirements: &[Requirement],
+    environment: &Environment,
+) -> (Vec<(&'a Distribution, Vec<String>)>, Vec<Requirement>) {
+    let mut required: Vec<(&Distribution, Vec<String>)> = Vec::new();
+    let mut missing: Vec<Requirement> = Vec::new();
+    // Requirements still to visit, with the extras requested
+    let mut queue: Vec<Requirement> = requirements
+        .iter()
+        .filter(|x| x.applies(environment, &[]))
+        .cloned()
+        .collect();
+    let mut visited: Vec<(String, Vec<String>)> = Vec::new();
+
+    while let Some(requirement) = queue.pop() {
+        let key = (requirement.key(), requirement.extras.clone());
+        if visited.contains(&key) {
+            continue;
+        }
+        visited.push(key);
+
+        let distribution = match distributions.iter().find(|x| normalise_name(&x.name) == requirement.key()) {
+            Some(x) => x,
+            None => {
+                if !missing.iter().any(|x| x.key() == requirement.key()) {
+                    missing.push(requirement);
+                }
+                continue;
+            }
+        };
+
+        match required.iter_mut().find(|(x, _)| std::ptr::eq(*x, distribution)) {
+            Some((_, extras)) => {
+                for extra in &requirement.extras {
+                    if !extras.contains(extra) {
+                        extras.push(extra.clone());
+                    }
+                }
+            },
+            None => required.push((distribution, requirement.extras.clone())),
+        }
+
+        for dependency in &distribution.requires {
+            if dependency.applies(environment, &requirement.extras) {
+                queue.push(dependency.clone());
+            }
+        }
+    }
+
+    required.sort_by_key(|(x, _)| normalise_name(&x.name));
+    (required, missing)
+}
+
+/// Folders Python imports from (site-packages, etc.)
+fn site_packages() -> Vec<PathBuf> {
+    let output = Command::new("cmd")
+        .args(["/c", "python -c \"import sys; print(chr(10).join(sys.path))\""])
+        .output();
+
+    match output {
+        Ok(x) => String::from_utf8_lossy(&x.stdout)
+            .lines()
+            .map(|x| x.trim())
+            .filter(|x| !x.is_empty())
+            .map(PathBuf::from)
+            .filter(|x| x.is_dir())
+            .collect(),
+        Err(e) => {
+            println!("WARNING: Couldn't find installed packages (python may not be installed on path): {}", e);
+            Vec::new()
+        }
+    }
+}
+
+/// Top-level modules of a distribution, from `top_level.txt` or else `RECORD`
+fn top_level_modules(path: &Path) -> Vec<String> {
+    if let Ok(content) = fs::read_to_string(path.join("top_level.txt")) {
+        let modules: Vec<String> = content
+            .lines()
+            .map(|x| x.trim().replace('/', "."))
+            .filter(|x| !x.is_empty())
+            .collect();
+
+        if !modules.is_empty() {
+            return modules;
+        }
+    }
+
+    let record = fs::read_to_string(path.join("RECORD")).unwrap_or_default();
+    let mut modules: Vec<String> = Vec::new();
+
+    for line in record.lines() {
+        let file = line.split(',').next().unwrap_or("");
+
+        let module = match file.split_once('/') {
+            Some((dir, _)) => {
+                if dir.ends_with(".dist-info") || dir.ends_with(".data") || dir == "__pycache__" || dir == ".." {
+                    continue;
+                }
+                dir.to_string()
+            },
+            None => {
+                // Single file modules and extensions (`six.py`, `_foo.cpython-311.so`)
+                let (stem, extension) = match file.split_once('.') {
+                    Some(x) => x,
+                    None => continue,
+                };
+
+                if !(extension == "py" || extension.ends_with("so") || extension.ends_with("pyd")) {
+                    continue;
+                }
+                stem.to_string()
+            },
+        };
+
+        if !module.is_empty() && !modules.contains(&module) {
+            modules.push(module);
+        }
+    }
+
+    modules
+}
+
+/// License files of a distribution, from its `License-File` headers (in
+/// `licenses/` since PEP 639) or else named like one (`LICENSE`, `COPYING`, ...)
+fn license_files(path: &Path, headers: &[(String, String)]) -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = headers
+        .iter()
+        .filter(|(key, _)| key.eq_ignore_ascii_case("License-File"))
+        .filter_map(|(_, file)| {
+            [path.join("licenses").join(file), path.join(file)]
+                .iter()
+                .find(|x| x.is_file())
+                .cloned()
+        })
+        .collect();
+
+    if files.is_empty() {
+        let mut dirs = vec![path.to_path_buf(), path.join("licenses")];
+
+        while let Some(dir) = dirs.pop() {
+            let entries = match fs::read_dir(&dir) {
+                Ok(x) => x,
+                Err(_) => continue,
+            };
+
+            for entry in entries.filter_map(|x| x.ok()).map(|x| x.path()) {
+                let name = entry.file_name().unwrap_or_default().to_string_lossy().to_uppercase();
+
+                if entry.is_dir() && dir != path {
+                    dirs.push(entry);
+                } else if entry.is_file() && ["LICEN", "COPYING", "NOTICE", "AUTHORS"].iter().any(|x| name.starts_with(x)) {
+                    files.push(entry);
+                }
+            }
+        }
+    }
+
+    files.sort();
+    files.dedup();
+    files
+}
+
+/// Read the headers of a metadata file (which end at the first blank line)
+pub fn headers(metadata: &str) -> Vec<(String, String)> {
+    let mut headers: Vec<(String, String)> = Vec::new();
+
+    for line in metadata.lines() {
+        if line.trim().is_empty() {
+            break;
+        }
+
+        // Continuation of the previous header
+        if line.starts_with([' ', '\t']) {
+            if let Some((_, value)) = headers.last_mut() {
+                value.push('\n');
+                value.push_str(line.trim());
+            }
+            continue;
+        }
+
+        if let Some((key, value)) = line.split_once(':') {
+            headers.push((key.trim().to_string(), value.trim().to_string()));
+        }
+    }
+
+    headers
+}
+
+/// Get the first value of a header
+pub fn header(headers: &[(String, String)], key: &str) -> Option<String> {
+    headers
+        .iter()
+        .find(|(x, _)| x.eq_ignore_ascii_case(key))
+        .map(|(_, value)| value.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Write a `.dist-info` folder and read it back
+    fn distribution(root: &Path, name: &str, version: &str, requires: &[&str]) -> Distribution {
+        let path = root.join(format!("{}-{}.dist-info", name, version));
+        fs::create_dir_all(&path).unwrap();
+
+        let mut metadata = format!("Metadata-Version: 2.1\nName: {}\nVersion: {}\n", name, version);
+        for requirement in requires {
+            metadata.push_str(&format!("Requires-Dist: {}\n", requirement));
+        }
+        fs::write(path.join("METADATA"), metadata).unwrap();
+
+        Distribution::load(&path).unwrap()
+    }
+
+    fn names(distributions: &[(&Distribution, Vec<String>)]) -> Vec<String> {
+        distributions.iter().map(|(x, _)| x.name.clone()).collect()
+    }
+
+    #[test]
+    fn requirements_are_followed_through_markers_and_extras() {
+        let root = std::env::temp_dir().join(format!("pyproj-installed-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+
+        let distributions = [
+            distribution(&root, "requests", "2.31.0", &[
+                "idna (>=2.5)",
+                "PySocks (!=1.5.7,>=1.5.6) ; extra == \"socks\"",
+                "win-inet-pton ; sys_platform == \"win32\" and extra == \"socks\"",
+            ]),
+            distribution(&root, "idna", "3.6", &[]),
+            distribution(&root, "PySocks", "1.7.1", &[]),
+            distribution(&root, "click", "8.1.7", &["colorama ; platform_system == \"Windows\""]),
+            distribution(&root, "unused", "1.0", &[]),
+        ];
+        let environment = Environment { values: HashMap::new() }.with_target(Some("3.11"), Some("linux"));
+
+        let requirements = [Requirement::parse("requests").unwrap(), Requirement::parse("click").unwrap()];
+        let (required, missing) = required_distributions(&distributions, &requirements, &environment);
+        assert_eq!(names(&required), ["click", "idna", "requests"]);
+        assert!(missing.is_empty());
+
+        let requirements = [Requirement::parse("Requests[socks]").unwrap()];
+        let (required, missing) = required_distributions(&distributions, &requirements, &environment);
+        assert_eq!(names(&required), ["idna", "PySocks", "requests"]);
+        assert_eq!(required[2].1, ["socks"]);
+        assert!(missing.is_empty());
+
+        let requirements = [Requirement::parse("requests[socks]").unwrap()];
+        let windows = Environment { values: HashMap::new() }.with_target(Some("3.11"), Some("win32"));
+        let (required, missing) = required_distributions(&distributions, &requirements, &windows);
+        assert_eq!(names(&required), ["idna", "PySocks", "requests"]);
+        assert_eq!(missing.iter().map(|x| x.name.clone()).collect::<Vec<String>>(), ["win-inet-pton"]);
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn cycles_and_missing_distributions() {
+        let root = std::env::temp_dir().join(format!("pyproj-installed-cycle-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+
+        let distributions = [
+            distribution(&root, "alpha", "1.0", &["beta"]),
+            distribution(&root, "beta", "2.0", &["alpha", "gamma"]),
+        ];
+        let environment = Environment { values: HashMap::new() }.with_target(Some("3.11"), Some("linux"));
+
+        let requirements = [
+            Requirement::parse("alpha").unwrap(),
+            Requirement::parse("Gamma>=1").unwrap(),
+            Requirement::parse("old ; python_version < \"3\"").unwrap(),
+        ];
+        let (required, missing) = required_distributions(&distributions, &requirements, &environment);
+        assert_eq!(names(&required), ["alpha", "beta"]);
+        assert_eq!(missing.iter().map(|x| x.key()).collect::<Vec<String>>(), ["gamma"]);
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
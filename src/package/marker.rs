@@ -0,0 +1,557 @@
+//! PEP 508 environment markers (`python_version < "3.8" and sys_platform == "win32"`)
+
+use std::collections::HashMap;
+use std::fmt;
+use std::process::Command;
+
+use serde_json::Value;
+
+use super::name::normalise_name;
+use super::specifier::Specifier;
+use super::version::Version;
+
+/// Marker variables
+pub const VARIABLES: [&str; 12] = [
+    "python_version",
+    "python_full_version",
+    "os_name",
+    "sys_platform",
+    "platform_release",
+    "platform_system",
+    "platform_version",
+    "platform_machine",
+    "platform_python_implementation",
+    "implementation_name",
+    "implementation_version",
+    "extra",
+];
+
+/// Old spellings of marker variables (PEP 345)
+const LEGACY_VARIABLES: [(&str, &str); 6] = [
+    ("os.name", "os_name"),
+    ("sys.platform", "sys_platform"),
+    ("platform.version", "platform_version"),
+    ("platform.machine", "platform_machine"),
+    ("platform.python_implementation", "platform_python_implementation"),
+    ("python_implementation", "platform_python_implementation"),
+];
+
+/// Variables compared as versions when both sides are valid versions
+const VERSION_VARIABLES: [&str; 3] = ["python_version", "python_full_version", "implementation_version"];
+
+/// Prints the values of every marker variable as JSON
+const ENVIRONMENT_SCRIPT: &str = "import json, os, sys, platform; \
+    v = sys.implementation.version; \
+    print(json.dumps(dict(\
+    python_version='.'.join(platform.python_version_tuple()[:2]), \
+    python_full_version=platform.python_version(), \
+    os_name=os.name, \
+    sys_platform=sys.platform, \
+    platform_release=platform.release(), \
+    platform_system=platform.system(), \
+    platform_version=platform.version(), \
+    platform_machine=platform.machine(), \
+    platform_python_implementation=platform.python_implementation(), \
+    implementation_name=sys.implementation.name, \
+    implementation_version='{}.{}.{}'.format(v.major, v.minor, v.micro))))";
+
+/// Operators of marker expressions
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum MarkerOperator {
+    Equal,
+    NotEqual,
+    LessEqual,
+    GreaterEqual,
+    Less,
+    Greater,
+    Compatible,
+    Arbitrary,
+    In,
+    NotIn,
+}
+
+/// Operators, longest first so they can be matched in order
+const OPERATORS: [(&str, MarkerOperator); 8] = [
+    ("===", MarkerOperator::Arbitrary),
+    ("==", MarkerOperator::Equal),
+    ("!=", MarkerOperator::NotEqual),
+    ("<=", MarkerOperator::LessEqual),
+    (">=", MarkerOperator::GreaterEqual),
+    ("~=", MarkerOperator::Compatible),
+    ("<", MarkerOperator::Less),
+    (">", MarkerOperator::Greater),
+];
+
+/// One side of a marker expression
+#[derive(Clone, PartialEq, Debug)]
+pub enum MarkerValue {
+    /// An environment variable (`python_version`)
+    Variable(String),
+    /// A quoted string (`"3.8"`)
+    Literal(String),
+}
+
+/// A parsed environment marker
+#[derive(Clone, PartialEq, Debug)]
+pub enum Marker {
+    Compare(MarkerValue, MarkerOperator, MarkerValue),
+    And(Box<Marker>, Box<Marker>),
+    Or(Box<Marker>, Box<Marker>),
+}
+
+/// Values of the marker variables of a Python environment
+#[derive(Clone, Debug)]
+pub struct Environment {
+    pub values: HashMap<String, String>,
+}
+
+impl Marker {
+    /// Parse a marker
+    pub fn parse(marker: &str) -> Result<Marker, String> {
+        let mut parser = MarkerParser {
+            chars: marker.chars().collect(),
+            at: 0,
+        };
+
+        let parsed = parser.or()?;
+        parser.whitespace();
+
+        match parser.at == parser.chars.len() {
+            true => Ok(parsed),
+            false => Err(format!("unexpected `{}` in marker", parser.rest())),
+        }
+    }
+
+    /// Check whether the marker applies to an environment, with
+    /// the given extras requested
+    pub fn evaluate(&self, environment: &Environment, extras: &[String]) -> bool {
+        match self {
+            Marker::And(a, b) => a.evaluate(environment, extras) && b.evaluate(environment, extras),
+            Marker::Or(a, b) => a.evaluate(environment, extras) || b.evaluate(environment, extras),
+            Marker::Compare(left, operator, right) => {
+                let is_extra = |x: &MarkerValue| *x == MarkerValue::Variable(String::from("extra"));
+
+                // `extra == "name"` is true for each requested extra
+                if is_extra(left) || is_extra(right) {
+                    let other = match is_extra(left) {
+                        true => right,
+                        false => left,
+                    };
+                    let name = normalise_name(&environment.resolve(other));
+
+                    return match operator {
+                        MarkerOperator::Equal => extras.iter().any(|x| normalise_name(x) == name),
+                        MarkerOperator::NotEqual => !extras.iter().any(|x| normalise_name(x) == name),
+                        _ => false,
+                    };
+                }
+
+                let versions = [left, right]
+                    .iter()
+                    .any(|x| matches!(x, MarkerValue::Variable(v) if VERSION_VARIABLES.contains(&v.as_str())));
+
+                compare(&environment.resolve(left), *operator, &environment.resolve(right), versions)
+            },
+        }
+    }
+}
+
+impl Environment {
+    /// Get the environment of the `python` on path, or a guess
+    /// from the current OS if it can't be run
+    pub fn current() -> Environment {
+        let output = Command::new("cmd")
+            .args(["/c", &format!("python -c \"{}\"", ENVIRONMENT_SCRIPT)])
+            .output();
+
+        let json: Option<Value> = output
+            .ok()
+            .and_then(|x| serde_json::from_slice(&x.stdout).ok());
+
+        match json.as_ref().and_then(|x| x.as_object()) {
+            Some(object) => Environment {
+                values: object
+                    .iter()
+                    .map(|(key, value)| (key.clone(), value.as_str().unwrap_or("").to_string()))
+                    .collect(),
+            },
+            None => {
+                println!("WARNING: Couldn't read the Python environment (python may not be installed on path), guessing it from the OS");
+                Environment::from_os()
+            }
+        }
+    }
+
+    /// The environment with a different Python version (`3.11`)
+    /// or platform (`linux`, `win32`, `darwin`)
+    pub fn with_target(mut self, python_version: Option<&str>, sys_platform: Option<&str>) -> Environment {
+        if let Some(version) = python_version {
+            let full = match version.split('.').count() {
+                1 | 2 => format!("{}.0", version),
+                _ => version.to_string(),
+            };
+            let short: Vec<&str> = version.split('.').take(2).collect();
+
+            self.values.insert(String::from("python_version"), short.join("."));
+            self.values.insert(String::from("python_full_version"), full.clone());
+            self.values.insert(String::from("implementation_version"), full);
+        }
+
+        if let Some(platform) = sys_platform {
+            let (os_name, platform_system) = match platform {
+                "win32" | "cygwin" => ("nt", "Windows"),
+                "darwin" => ("posix", "Darwin"),
+                _ => ("posix", "Linux"),
+            };
+
+            self.values.insert(String::from("sys_platform"), platform.to_string());
+            self.values.insert(String::from("os_name"), os_name.to_string());
+            self.values.insert(String::from("platform_system"), platform_system.to_string());
+        }
+
+        self
+    }
+
+    /// Get the value of a variable (empty if it isn't known)
+    pub fn get(&self, variable: &str) -> &str {
+        self.values.get(variable).map(|x| x.as_str()).unwrap_or("")
+    }
+
+    /// Guess the environment from the current OS
+    fn from_os() -> Environment {
+        let (os_name, sys_platform, platform_system) = match std::env::consts::OS {
+            "windows" => ("nt", "win32", "Windows"),
+            "macos" => ("posix", "darwin", "Darwin"),
+            _ => ("posix", "linux", "Linux"),
+        };
+
+        let values = [
+            ("os_name", os_name),
+            ("sys_platform", sys_platform),
+            ("platform_system", platform_system),
+            ("platform_machine", std::env::consts::ARCH),
+            ("platform_python_implementation", "CPython"),
+            ("implementation_name", "cpython"),
+        ];
+
+        Environment {
+            values: values.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect(),
+        }
+    }
+
+    /// Get the value of one side of a marker expression
+    fn resolve(&self, value: &MarkerValue) -> String {
+        match value {
+            MarkerValue::Variable(name) => self.values.get(name).cloned().unwrap_or_default(),
+            MarkerValue::Literal(s) => s.clone(),
+        }
+    }
+}
+
+/// Compare two marker values, as versions when `versions` is set and
+/// both are valid versions, else as strings
+fn compare(left: &str, operator: MarkerOperator, right: &str, versions: bool) -> bool {
+    match operator {
+        MarkerOperator::In => return right.contains(left),
+        MarkerOperator::NotIn => return !right.contains(left),
+        _ => {},
+    }
+
+    let symbol = OPERATORS
+        .iter()
+        .find(|(_, x)| *x == operator)
+        .map(|(symbol, _)| *symbol)
+        .unwrap_or("");
+
+    if versions {
+        if let (Some(version), Some(specifier)) = (
+            Version::parse(left),
+            Specifier::parse(&format!("{}{}", symbol, right)),
+        ) {
+            return specifier.contains(&version);
+        }
+    }
+
+    match operator {
+        MarkerOperator::Equal | MarkerOperator::Arbitrary => left == right,
+        MarkerOperator::NotEqual => left != right,
+        MarkerOperator::LessEqual => left <= right,
+        MarkerOperator::GreaterEqual => left >= right,
+        MarkerOperator::Less => left < right,
+        MarkerOperator::Greater => left > right,
+        // Only meaningful for versions
+        MarkerOperator::Compatible => false,
+        MarkerOperator::In | MarkerOperator::NotIn => false,
+    }
+}
+
+impl fmt::Display for MarkerOperator {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MarkerOperator::In => write!(f, "in"),
+            MarkerOperator::NotIn => write!(f, "not in"),
+            _ => {
+                let symbol = OPERATORS
+                    .iter()
+                    .find(|(_, x)| x == self)
+                    .map(|(symbol, _)| *symbol)
+                    .unwrap_or("");
+                write!(f, "{}", symbol)
+            },
+        }
+    }
+}
+
+impl fmt::Display for MarkerValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MarkerValue::Variable(name) => write!(f, "{}", name),
+            MarkerValue::Literal(s) if s.contains('"') => write!(f, "'{}'", s),
+            MarkerValue::Literal(s) => write!(f, "\"{}\"", s),
+        }
+    }
+}
+
+/// Normalised form of the marker
+impl fmt::Display for Marker {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Marker::Compare(left, operator, right) => write!(f, "{} {} {}", left, operator, right),
+            Marker::Or(a, b) => write!(f, "{} or {}", a, b),
+            Marker::And(a, b) => {
+                // `or` binds less tightly than `and`
+                for (i, side) in [a, b].iter().enumerate() {
+                    if i > 0 {
+                        write!(f, " and ")?;
+                    }
+
+                    match side.as_ref() {
+                        Marker::Or(_, _) => write!(f, "({})", side)?,
+                        _ => write!(f, "{}", side)?,
+                    }
+                }
+                Ok(())
+            },
+        }
+    }
+}
+
+/// Position in a marker being parsed
+struct MarkerParser {
+    chars: Vec<char>,
+    at: usize,
+}
+
+impl MarkerParser {
+    /// `and` expressions joined by `or`
+    fn or(&mut self) -> Result<Marker, String> {
+        let mut marker = self.and()?;
+
+        while self.keyword("or") {
+            marker = Marker::Or(Box::new(marker), Box::new(self.and()?));
+        }
+
+        Ok(marker)
+    }
+
+    /// Expressions joined by `and`
+    fn and(&mut self) -> Result<Marker, String> {
+        let mut marker = self.expression()?;
+
+        while self.keyword("and") {
+            marker = Marker::And(Box::new(marker), Box::new(self.expression()?));
+        }
+
+        Ok(marker)
+    }
+
+    /// A comparison or a marker in parentheses
+    fn expression(&mut self) -> Result<Marker, String> {
+        self.whitespace();
+
+        if self.eat("(") {
+            let marker = self.or()?;
+            self.whitespace();
+
+            return match self.eat(")") {
+                true => Ok(marker),
+                false => Err(String::from("missing `)` in marker")),
+            };
+        }
+
+        let left = self.value()?;
+        let operator = self.operator()?;
+        let right = self.value()?;
+
+        Ok(Marker::Compare(left, operator, right))
+    }
+
+    /// A variable or a quoted string
+    fn value(&mut self) -> Result<MarkerValue, String> {
+        self.whitespace();
+
+        match self.chars.get(self.at) {
+            Some(&quote) if quote == '"' || quote == '\'' => {
+                self.at += 1;
+                let start = self.at;
+
+                while self.at < self.chars.len() && self.chars[self.at] != quote {
+                    self.at += 1;
+                }
+
+                if self.at == self.chars.len() {
+                    return Err(String::from("unterminated string in marker"));
+                }
+
+                let value: String = self.chars[start..self.at].iter().collect();
+                self.at += 1;
+                Ok(MarkerValue::Literal(value))
+            },
+            _ => {
+                let start = self.at;
+                while self.at < self.chars.len()
+                    && (self.chars[self.at].is_alphanumeric() || self.chars[self.at] == '_' || self.chars[self.at] == '.')
+                {
+                    self.at += 1;
+                }
+
+                let name: String = self.chars[start..self.at].iter().collect();
+
+                if let Some((_, canonical)) = LEGACY_VARIABLES.iter().find(|(x, _)| *x == name) {
+                    return Ok(MarkerValue::Variable(canonical.to_string()));
+                }
+
+                match VARIABLES.contains(&name.as_str()) {
+                    true => Ok(MarkerValue::Variable(name)),
+                    false if name.is_empty() => Err(format!("expected a marker variable or string at `{}`", self.rest())),
+                    false => Err(format!("unknown marker variable `{}`", name)),
+                }
+            },
+        }
+    }
+
+    fn operator(&mut self) -> Result<MarkerOperator, String> {
+        self.whitespace();
+
+        if let Some((_, operator)) = OPERATORS.iter().find(|(symbol, _)| self.eat(symbol)) {
+            return Ok(*operator);
+        }
+
+        if self.keyword("in") {
+            return Ok(MarkerOperator::In);
+        }
+
+        let start = self.at;
+        if self.keyword("not") && self.keyword("in") {
+            return Ok(MarkerOperator::NotIn);
+        }
+        self.at = start;
+
+        Err(format!("expected a marker operator at `{}`", self.rest()))
+    }
+
+    /// Consume a keyword (`and`, `or`, `in`) surrounded by whitespace
+    fn keyword(&mut self, word: &str) -> bool {
+        let start = self.at;
+        self.whitespace();
+
+        let end = self.at + word.len();
+        let matches = self.chars.len() >= end
+            && self.chars[self.at..end].iter().collect::<String>() == word
+            && self.chars.get(end).map(|c| !(c.is_alphanumeric() || *c == '_')).unwrap_or(true);
+
+        match matches {
+            true => {
+                self.at = end;
+                true
+            },
+            false => {
+                self.at = start;
+                false
+            },
+        }
+    }
+
+    fn eat(&mut self, s: &str) -> bool {
+        let s: Vec<char> = s.chars().collect();
+
+        match self.chars.len() >= self.at + s.len() && self.chars[self.at..self.at + s.len()] == s[..] {
+            true => {
+                self.at += s.len();
+                true
+            },
+            false => false,
+        }
+    }
+
+    fn whitespace(&mut self) {
+        while self.at < self.chars.len() && self.chars[self.at].is_whitespace() {
+            self.at += 1;
+        }
+    }
+
+    fn rest(&self) -> String {
+        self.chars[self.at..].iter().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn environment() -> Environment {
+        let values = [
+            ("python_version", "3.8"),
+            ("python_full_version", "3.8.10"),
+            ("sys_platform", "linux"),
+            ("os_name", "posix"),
+            ("platform_machine", "x86_64"),
+            ("implementation_name", "cpython"),
+        ];
+
+        Environment {
+            values: values.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect(),
+        }
+    }
+
+    fn evaluate(marker: &str, extras: &[&str]) -> bool {
+        let extras: Vec<String> = extras.iter().map(|x| x.to_string()).collect();
+
+        Marker::parse(marker)
+            .unwrap_or_else(|e| panic!("{}: {}", marker, e))
+            .evaluate(&environment(), &extras)
+    }
+
+    #[test]
+    fn versions_compare_as_versions() {
+        assert!(evaluate("python_version >= '3.7'", &[]));
+        // As strings "3.8" > "3.10"
+        assert!(evaluate("python_version < '3.10'", &[]));
+        assert!(evaluate("python_full_version == '3.8.*'", &[]));
+        assert!(!evaluate("python_version ~= '3.9'", &[]));
+        assert!(evaluate("'3.6' < python_version", &[]));
+    }
+
+    #[test]
+    fn boolean_operators_follow_precedence() {
+        assert!(evaluate("sys_platform == 'win32' or os_name == 'posix' and platform_machine == 'x86_64'", &[]));
+        assert!(!evaluate("(sys_platform == 'win32' or os_name == 'posix') and platform_machine == 'arm64'", &[]));
+        assert!(evaluate("implementation_name in 'cpython pypy'", &[]));
+        assert!(evaluate("'win' not in sys_platform", &[]));
+    }
+
+    #[test]
+    fn extras_match_requested_extras() {
+        assert!(!evaluate("extra == 'socks'", &[]));
+        assert!(evaluate("extra == 'socks'", &["socks"]));
+        assert!(evaluate("extra == 'Security_Extra'", &["security-extra"]));
+        assert!(evaluate("python_version >= '3' and extra == \"test\"", &["docs", "test"]));
+    }
+
+    #[test]
+    fn invalid_markers_are_rejected() {
+        for x in ["", "python_version", "python_version >= ", "os_name == 'nt' and", "(os_name == 'nt'", "unknown == 'x'"] {
+            assert!(Marker::parse(x).is_err(), "{}", x);
+        }
+    }
+}
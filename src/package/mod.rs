@@ -0,0 +1,8 @@
+//! Python packaging standards (versions, requirements, metadata)
+
+pub mod installed;
+pub mod marker;
+pub mod name;
+pub mod requirement;
+pub mod specifier;
+pub mod version;
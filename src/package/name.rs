@@ -0,0 +1,32 @@
+//! Package names
+
+/// Normalise a package name (PEP 503)
+pub fn normalise_name(name: &str) -> String {
+    let mut normalised = String::new();
+
+    for c in name.chars() {
+        match c {
+            '-' | '_' | '.' => {
+                if !normalised.ends_with('-') {
+                    normalised.push('-');
+                }
+            },
+            _ => normalised.push(c.to_ascii_lowercase()),
+        }
+    }
+
+    normalised
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn separators_and_case_are_normalised() {
+        assert_eq!(normalise_name("Django"), "django");
+        assert_eq!(normalise_name("zope.interface"), "zope-interface");
+        assert_eq!(normalise_name("typing_extensions"), "typing-extensions");
+        assert_eq!(normalise_name("Foo-._-Bar"), "foo-bar");
+    }
+}
@@ -0,0 +1,206 @@
+//! PEP 508 requirements (`name[extra]>=1.0,<2; python_version < "3.8"`)
+
+use std::fmt;
+
+use super::marker::{Environment, Marker};
+use super::name::normalise_name;
+use super::specifier::SpecifierSet;
+
+/// A parsed requirement
+#[derive(Clone, Debug)]
+pub struct Requirement {
+    /// Name as written
+    pub name: String,
+    pub extras: Vec<String>,
+    pub specifier: SpecifierSet,
+    /// Direct reference (`name @ https://...`)
+    pub url: Option<String>,
+    pub marker: Option<Marker>,
+}
+
+impl Requirement {
+    /// A requirement on any version of a package
+    pub fn new(name: &str) -> Requirement {
+        Requirement {
+            name: name.to_string(),
+            extras: Vec::new(),
+            specifier: SpecifierSet::default(),
+            url: None,
+            marker: None,
+        }
+    }
+
+    /// Parse a requirement
+    pub fn parse(requirement: &str) -> Result<Requirement, String> {
+        let requirement = requirement.trim();
+
+        // Name
+        let end = requirement
+            .find(|c: char| !(c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.'))
+            .unwrap_or(requirement.len());
+        let name = &requirement[..end];
+
+        let valid = name.starts_with(|c: char| c.is_ascii_alphanumeric())
+            && name.ends_with(|c: char| c.is_ascii_alphanumeric());
+        if !valid {
+            return Err(format!("invalid package name in `{}`", requirement));
+        }
+
+        let mut parsed = Requirement::new(name);
+        let mut rest = requirement[end..].trim_start();
+
+        // Extras
+        if let Some(after) = rest.strip_prefix('[') {
+            let (extras, after) = after
+                .split_once(']')
+                .ok_or_else(|| format!("missing `]` in `{}`", requirement))?;
+
+            for extra in extras.split(',').map(|x| x.trim()).filter(|x| !x.is_empty()) {
+                if !extra.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.') {
+                    return Err(format!("invalid extra `{}` in `{}`", extra, requirement));
+                }
+                parsed.extras.push(extra.to_string());
+            }
+
+            rest = after.trim_start();
+        }
+
+        // Direct reference or version specifiers
+        if let Some(after) = rest.strip_prefix('@') {
+            let after = after.trim_start();
+            // The URL ends at whitespace, so a marker must be separated from it
+            let end = after.find(char::is_whitespace).unwrap_or(after.len());
+
+            if end == 0 {
+                return Err(format!("missing URL in `{}`", requirement));
+            }
+
+            parsed.url = Some(after[..end].to_string());
+            rest = after[end..].trim_start();
+        } else {
+            let end = rest.find(';').unwrap_or(rest.len());
+            let mut specifier = rest[..end].trim();
+
+            // Specifiers may be in parentheses (`name (>=1.0)`)
+            if let Some(inner) = specifier.strip_prefix('(') {
+                specifier = inner
+                    .strip_suffix(')')
+                    .ok_or_else(|| format!("missing `)` in `{}`", requirement))?;
+            }
+
+            parsed.specifier = SpecifierSet::parse(specifier)
+                .ok_or_else(|| format!("invalid version specifier `{}` in `{}`", specifier.trim(), requirement))?;
+            rest = &rest[end..];
+        }
+
+        // Marker
+        if let Some(marker) = rest.strip_prefix(';') {
+            parsed.marker = Some(Marker::parse(marker)
+                .map_err(|e| format!("{} in `{}`", e, requirement))?);
+        } else if !rest.trim().is_empty() {
+            return Err(format!("unexpected `{}` in `{}`", rest.trim(), requirement));
+        }
+
+        Ok(parsed)
+    }
+
+    /// Normalised package name (PEP 503)
+    pub fn key(&self) -> String {
+        normalise_name(&self.name)
+    }
+
+    /// Check whether the requirement applies to an environment (with
+    /// the given extras of the package requiring it)
+    pub fn applies(&self, environment: &Environment, extras: &[String]) -> bool {
+        match &self.marker {
+            Some(marker) => marker.evaluate(environment, extras),
+            None => true,
+        }
+    }
+
+    /// The requirement with its name normalised and its extras and
+    /// specifiers sorted, so equal requirements are written the same way
+    pub fn normalised(&self) -> Requirement {
+        let mut normalised = self.clone();
+        normalised.name = self.key();
+
+        normalised.extras = self.extras.iter().map(|x| normalise_name(x)).collect();
+        normalised.extras.sort();
+        normalised.extras.dedup();
+
+        normalised.specifier.specifiers.sort_by_key(|x| x.to_string());
+        normalised
+    }
+}
+
+impl fmt::Display for Requirement {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name)?;
+
+        if !self.extras.is_empty() {
+            write!(f, "[{}]", self.extras.join(","))?;
+        }
+
+        match &self.url {
+            Some(url) => {
+                write!(f, " @ {}", url)?;
+                // A marker after a URL must be separated by whitespace
+                if let Some(marker) = &self.marker {
+                    write!(f, " ; {}", marker)?;
+                }
+            },
+            None => {
+                write!(f, "{}", self.specifier)?;
+                if let Some(marker) = &self.marker {
+                    write!(f, "; {}", marker)?;
+                }
+            },
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn requirements_are_parsed() {
+        let parsed = Requirement::parse("Requests[Security, socks] (>=2.8.1, <3) ; python_version < '3.8'").unwrap();
+
+        assert_eq!(parsed.name, "Requests");
+        assert_eq!(parsed.key(), "requests");
+        assert_eq!(parsed.extras, ["Security", "socks"]);
+        assert_eq!(parsed.specifier.specifiers.len(), 2);
+        assert!(parsed.marker.is_some());
+        assert!(parsed.url.is_none());
+    }
+
+    #[test]
+    fn direct_references_keep_their_marker() {
+        let parsed = Requirement::parse("pip @ https://example.com/pip-23.0-py3-none-any.whl ; sys_platform == 'win32'").unwrap();
+
+        assert_eq!(parsed.url.as_deref(), Some("https://example.com/pip-23.0-py3-none-any.whl"));
+        assert!(parsed.marker.is_some());
+
+        let written = parsed.to_string();
+        let read = Requirement::parse(&written).unwrap();
+        assert_eq!(read.to_string(), written);
+    }
+
+    #[test]
+    fn normalised_requirements_are_equal() {
+        let a = Requirement::parse("Django_Rest[B,a]<4,>=3.0").unwrap();
+        let b = Requirement::parse("django-rest[a,b]>=3.0,<4").unwrap();
+
+        assert_eq!(a.normalised().to_string(), b.normalised().to_string());
+    }
+
+    #[test]
+    fn invalid_requirements_are_rejected() {
+        for x in ["", "-name", "name[extra", "name >=", "name @", "name (>=1.0", "name 1.0", "name; os_name =="] {
+            assert!(Requirement::parse(x).is_err(), "{}", x);
+        }
+    }
+}
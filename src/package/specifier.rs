@@ -0,0 +1,251 @@
+//! PEP 440 version specifiers (`>=1.2,<2`)
+
+use std::fmt;
+
+use super::version::Version;
+
+/// Comparison operator of a specifier
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Operator {
+    /// `~=`
+    Compatible,
+    /// `==`
+    Equal,
+    /// `!=`
+    NotEqual,
+    /// `<=`
+    LessEqual,
+    /// `>=`
+    GreaterEqual,
+    /// `<`
+    Less,
+    /// `>`
+    Greater,
+    /// `===`
+    Arbitrary,
+}
+
+/// Operators, longest first so they can be matched in order
+const OPERATORS: [(&str, Operator); 8] = [
+    ("===", Operator::Arbitrary),
+    ("~=", Operator::Compatible),
+    ("==", Operator::Equal),
+    ("!=", Operator::NotEqual),
+    ("<=", Operator::LessEqual),
+    (">=", Operator::GreaterEqual),
+    ("<", Operator::Less),
+    (">", Operator::Greater),
+];
+
+/// A single specifier (`>=1.2`, `==1.4.*`)
+#[derive(Clone, Debug)]
+pub struct Specifier {
+    pub operator: Operator,
+    /// The version as written (without a trailing `.*`)
+    pub version: String,
+    /// Whether the version ends with `.*` (only for `==` and `!=`)
+    pub wildcard: bool,
+}
+
+/// A comma separated set of specifiers, all of which must match
+#[derive(Clone, Debug, Default)]
+pub struct SpecifierSet {
+    pub specifiers: Vec<Specifier>,
+}
+
+impl Specifier {
+    /// Parse a single specifier
+    pub fn parse(specifier: &str) -> Option<Specifier> {
+        let specifier = specifier.trim();
+
+        let (symbol, operator) = OPERATORS
+            .iter()
+            .find(|(symbol, _)| specifier.starts_with(symbol))?;
+
+        let version = specifier[symbol.len()..].trim();
+        let (version, wildcard) = match version.strip_suffix(".*") {
+            Some(x) if matches!(operator, Operator::Equal | Operator::NotEqual) => (x, true),
+            _ => (version, false),
+        };
+
+        // Arbitrary equality compares strings, so any version is fine
+        let parsed = match *operator {
+            Operator::Arbitrary if !version.is_empty() => None,
+            _ => Some(Version::parse(version)?),
+        };
+
+        // `~=` needs a release of at least two parts (`~=1` has no prefix to match)
+        if *operator == Operator::Compatible && parsed.map(|x| x.release.len() < 2).unwrap_or(true) {
+            return None;
+        }
+
+        Some(Specifier {
+            operator: *operator,
+            version: version.to_string(),
+            wildcard,
+        })
+    }
+
+    /// Check whether a version matches the specifier
+    pub fn contains(&self, version: &Version) -> bool {
+        if self.operator == Operator::Arbitrary {
+            return version.to_string() == self.version.to_lowercase();
+        }
+
+        let spec = match Version::parse(&self.version) {
+            Some(x) => x,
+            None => return false,
+        };
+
+        match self.operator {
+            Operator::Equal => self.equals(&spec, version),
+            Operator::NotEqual => !self.equals(&spec, version),
+            Operator::LessEqual => version.public() <= spec,
+            Operator::GreaterEqual => version.public() >= spec,
+            // `<1.2` doesn't allow pre-releases of 1.2
+            Operator::Less => {
+                version.public() < spec
+                    && !(spec.pre.is_none() && spec.dev.is_none() && is_pre(version) && same_release(version, &spec))
+            },
+            // `>1.2` doesn't allow post-releases of 1.2
+            Operator::Greater => {
+                version.public() > spec
+                    && !(spec.post.is_none() && version.post.is_some() && same_release(version, &spec))
+            },
+            Operator::Compatible => {
+                // `~=1.4.2` is `>=1.4.2,==1.4.*`
+                let mut prefix = spec.clone();
+                prefix.release.pop();
+                version.public() >= spec && starts_with(version, &prefix.release) && version.epoch == spec.epoch
+            },
+            Operator::Arbitrary => false,
+        }
+    }
+
+    fn equals(&self, spec: &Version, version: &Version) -> bool {
+        match (self.wildcard, spec.local.is_empty()) {
+            (true, _) => version.epoch == spec.epoch && starts_with(version, &spec.release),
+            // Local labels are ignored unless the specifier has one
+            (false, true) => version.public() == *spec,
+            (false, false) => version == spec,
+        }
+    }
+}
+
+impl SpecifierSet {
+    /// Parse comma separated specifiers (an empty string matches every version)
+    pub fn parse(specifiers: &str) -> Option<SpecifierSet> {
+        let specifiers = specifiers
+            .split(',')
+            .map(|x| x.trim())
+            .filter(|x| !x.is_empty())
+            .map(Specifier::parse)
+            .collect::<Option<Vec<Specifier>>>()?;
+
+        Some(SpecifierSet { specifiers })
+    }
+
+    /// Check whether a version matches every specifier
+    pub fn contains(&self, version: &Version) -> bool {
+        self.specifiers.iter().all(|x| x.contains(version))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.specifiers.is_empty()
+    }
+}
+
+impl fmt::Display for Specifier {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let symbol = OPERATORS
+            .iter()
+            .find(|(_, x)| *x == self.operator)
+            .map(|(symbol, _)| *symbol)
+            .unwrap_or("");
+
+        match self.wildcard {
+            true => write!(f, "{}{}.*", symbol, self.version),
+            false => write!(f, "{}{}", symbol, self.version),
+        }
+    }
+}
+
+impl fmt::Display for SpecifierSet {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let specifiers: Vec<String> = self.specifiers.iter().map(|x| x.to_string()).collect();
+        write!(f, "{}", specifiers.join(","))
+    }
+}
+
+fn is_pre(version: &Version) -> bool {
+    version.pre.is_some() || version.dev.is_some()
+}
+
+/// Check whether two versions have the same release, ignoring trailing zeros
+fn same_release(a: &Version, b: &Version) -> bool {
+    let len = a.release.len().max(b.release.len());
+    (0..len).all(|i| a.release.get(i).unwrap_or(&0) == b.release.get(i).unwrap_or(&0))
+}
+
+/// Check whether a version's release starts with a prefix (padding it with zeros)
+fn starts_with(version: &Version, prefix: &[u64]) -> bool {
+    prefix
+        .iter()
+        .enumerate()
+        .all(|(i, x)| version.release.get(i).unwrap_or(&0) == x)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matches(specifiers: &str, version: &str) -> bool {
+        SpecifierSet::parse(specifiers)
+            .unwrap_or_else(|| panic!("{} is a valid specifier", specifiers))
+            .contains(&Version::parse(version).unwrap())
+    }
+
+    #[test]
+    fn specifiers_match_versions() {
+        let cases = [
+            (">=1.2,<2", "1.2", true),
+            (">=1.2,<2", "1.9.9", true),
+            (">=1.2,<2", "2.0", false),
+            ("==1.4.*", "1.4.7", true),
+            ("==1.4.*", "1.40", false),
+            ("!=1.4.*", "1.5", true),
+            ("~=1.4.2", "1.4.9", true),
+            ("~=1.4.2", "1.5", false),
+            ("~=1.4", "1.9", true),
+            ("~=1.4", "2.0", false),
+            ("==1.0", "1.0.0", true),
+            ("==1.0", "1.0+local", true),
+            ("==1.0+local", "1.0", false),
+            ("<=1.0", "1.0+local", true),
+            ("===1.0-Beta", "1.0b0", false),
+            ("", "0.1", true),
+        ];
+
+        for (specifiers, version, expected) in cases {
+            assert_eq!(matches(specifiers, version), expected, "{} {}", version, specifiers);
+        }
+    }
+
+    #[test]
+    fn exclusive_bounds_exclude_pre_and_post_releases() {
+        // `<2` doesn't allow pre-releases of 2, `>1.2` doesn't allow post-releases of 1.2
+        assert!(!matches("<2", "2.0a1"));
+        assert!(matches("<2", "1.9a1"));
+        assert!(matches("<2.0a2", "2.0a1"));
+        assert!(!matches(">1.2", "1.2.post1"));
+        assert!(matches(">1.2.post1", "1.2.post2"));
+        assert!(matches(">1.2", "1.3"));
+    }
+
+    #[test]
+    fn invalid_specifiers_are_rejected() {
+        for x in ["1.0", ">=", "~=1.*", "~=1", "~=2.post1", ">=1.0.*", "==a.b", "==="] {
+            assert!(SpecifierSet::parse(x).is_none(), "{}", x);
+        }
+    }
+}
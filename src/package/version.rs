@@ -0,0 +1,356 @@
+//! PEP 440 version parsing and ordering
+
+use std::cmp::Ordering;
+use std::fmt;
+
+/// Pre-release kinds, in order
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum PreRelease {
+    Alpha,
+    Beta,
+    Candidate,
+}
+
+/// A part of a local version label (`+ubuntu.1`)
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum LocalPart {
+    Number(u64),
+    Text(String),
+}
+
+/// Sort key of a version (epoch, release, pre-release, post-release, dev-release)
+type Key = (u64, Vec<u64>, (i8, Option<(PreRelease, u64)>), i128, i128);
+
+/// A PEP 440 version
+#[derive(Clone, Debug)]
+pub struct Version {
+    pub epoch: u64,
+    pub release: Vec<u64>,
+    pub pre: Option<(PreRelease, u64)>,
+    pub post: Option<u64>,
+    pub dev: Option<u64>,
+    pub local: Vec<LocalPart>,
+}
+
+impl Version {
+    /// Parse a version, accepting the alternative spellings PEP 440
+    /// normalises (`1.0-alpha.1`, `v2`, `1.0.post`, ...)
+    pub fn parse(version: &str) -> Option<Version> {
+        let lower = version.trim().to_lowercase();
+        let mut s = Cursor {
+            chars: lower.strip_prefix('v').unwrap_or(&lower).chars().collect(),
+            at: 0,
+        };
+
+        // Epoch and release
+        let mut first = s.number()?;
+        let epoch = match s.eat("!") {
+            true => {
+                let epoch = first;
+                first = s.number()?;
+                epoch
+            },
+            false => 0,
+        };
+
+        let mut release = vec![first];
+        loop {
+            let start = s.at;
+            if s.eat(".") {
+                if let Some(n) = s.number() {
+                    release.push(n);
+                    continue;
+                }
+            }
+            s.at = start;
+            break;
+        }
+
+        // Pre-release
+        let mut pre = None;
+        let start = s.at;
+        s.separator();
+        let kind = [
+            ("alpha", PreRelease::Alpha), ("a", PreRelease::Alpha),
+            ("beta", PreRelease::Beta), ("b", PreRelease::Beta),
+            ("preview", PreRelease::Candidate), ("pre", PreRelease::Candidate),
+            ("rc", PreRelease::Candidate), ("c", PreRelease::Candidate),
+        ]
+            .iter()
+            .find(|(label, _)| s.eat(label))
+            .map(|(_, kind)| *kind);
+
+        match kind {
+            Some(kind) => {
+                s.separator();
+                pre = Some((kind, s.number().unwrap_or(0)));
+            },
+            None => s.at = start,
+        }
+
+        // Post-release (`-1` is an implicit post-release)
+        let mut post = None;
+        let start = s.at;
+        if s.eat("-") {
+            if let Some(n) = s.number() {
+                post = Some(n);
+            } else {
+                s.at = start;
+            }
+        }
+
+        if post.is_none() {
+            s.separator();
+            match ["post", "rev", "r"].iter().any(|x| s.eat(x)) {
+                true => {
+                    s.separator();
+                    post = Some(s.number().unwrap_or(0));
+                },
+                false => s.at = start,
+            }
+        }
+
+        // Development release
+        let mut dev = None;
+        let start = s.at;
+        s.separator();
+        match s.eat("dev") {
+            true => {
+                s.separator();
+                dev = Some(s.number().unwrap_or(0));
+            },
+            false => s.at = start,
+        }
+
+        // Local version label
+        let mut local = Vec::new();
+        if s.eat("+") {
+            let label: String = s.chars[s.at..].iter().collect();
+            s.at = s.chars.len();
+
+            for part in label.split(['.', '-', '_']) {
+                if part.is_empty() || !part.chars().all(|c| c.is_ascii_alphanumeric()) {
+                    return None;
+                }
+
+                local.push(match part.parse() {
+                    Ok(n) => LocalPart::Number(n),
+                    Err(_) => LocalPart::Text(part.to_string()),
+                });
+            }
+        }
+
+        match s.at == s.chars.len() {
+            true => Some(Version { epoch, release, pre, post, dev, local }),
+            false => None,
+        }
+    }
+
+    /// First part of the release (`2` for `2.31.0`)
+    pub fn major(&self) -> u64 {
+        self.release[0]
+    }
+
+    /// The version without its local label
+    pub fn public(&self) -> Version {
+        Version {
+            local: Vec::new(),
+            ..self.clone()
+        }
+    }
+
+    /// Key used to order versions, following PEP 440
+    fn key(&self) -> Key {
+        let mut release = self.release.clone();
+        while release.len() > 1 && release.last() == Some(&0) {
+            release.pop();
+        }
+
+        // Development releases come before pre-releases, which
+        // come before the final release
+        let pre = match (&self.pre, self.post, self.dev) {
+            (None, None, Some(_)) => (-1, None),
+            (None, _, _) => (1, None),
+            (Some(pre), _, _) => (0, Some(*pre)),
+        };
+
+        let post = self.post.map(|x| x as i128).unwrap_or(-1);
+        let dev = self.dev.map(|x| x as i128).unwrap_or(i128::MAX);
+
+        (self.epoch, release, pre, post, dev)
+    }
+
+    fn compare_local(&self, other: &Version) -> Ordering {
+        for (a, b) in self.local.iter().zip(other.local.iter()) {
+            let ordering = match (a, b) {
+                (LocalPart::Number(a), LocalPart::Number(b)) => a.cmp(b),
+                (LocalPart::Text(a), LocalPart::Text(b)) => a.cmp(b),
+                // Numbers sort after text
+                (LocalPart::Number(_), LocalPart::Text(_)) => Ordering::Greater,
+                (LocalPart::Text(_), LocalPart::Number(_)) => Ordering::Less,
+            };
+
+            if ordering != Ordering::Equal {
+                return ordering;
+            }
+        }
+
+        self.local.len().cmp(&other.local.len())
+    }
+}
+
+impl Ord for Version {
+    fn cmp(&self, other: &Version) -> Ordering {
+        self.key()
+            .cmp(&other.key())
+            .then_with(|| self.compare_local(other))
+    }
+}
+
+impl PartialOrd for Version {
+    fn partial_cmp(&self, other: &Version) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Version {
+    fn eq(&self, other: &Version) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Version {}
+
+/// Normalised form of the version
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.epoch != 0 {
+            write!(f, "{}!", self.epoch)?;
+        }
+
+        let release: Vec<String> = self.release.iter().map(|x| x.to_string()).collect();
+        write!(f, "{}", release.join("."))?;
+
+        if let Some((kind, n)) = self.pre {
+            let label = match kind {
+                PreRelease::Alpha => "a",
+                PreRelease::Beta => "b",
+                PreRelease::Candidate => "rc",
+            };
+            write!(f, "{}{}", label, n)?;
+        }
+
+        if let Some(n) = self.post {
+            write!(f, ".post{}", n)?;
+        }
+
+        if let Some(n) = self.dev {
+            write!(f, ".dev{}", n)?;
+        }
+
+        if !self.local.is_empty() {
+            let local: Vec<String> = self.local
+                .iter()
+                .map(|x| match x {
+                    LocalPart::Number(n) => n.to_string(),
+                    LocalPart::Text(s) => s.clone(),
+                })
+                .collect();
+            write!(f, "+{}", local.join("."))?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Position in a version string being parsed
+struct Cursor {
+    chars: Vec<char>,
+    at: usize,
+}
+
+impl Cursor {
+    /// Consume a string if it comes next
+    fn eat(&mut self, s: &str) -> bool {
+        let s: Vec<char> = s.chars().collect();
+
+        match self.chars.len() >= self.at + s.len() && self.chars[self.at..self.at + s.len()] == s[..] {
+            true => {
+                self.at += s.len();
+                true
+            },
+            false => false,
+        }
+    }
+
+    /// Consume an optional `.`, `-` or `_` separator
+    fn separator(&mut self) {
+        if self.at < self.chars.len() && ['.', '-', '_'].contains(&self.chars[self.at]) {
+            self.at += 1;
+        }
+    }
+
+    /// Consume a number
+    fn number(&mut self) -> Option<u64> {
+        let start = self.at;
+        while self.at < self.chars.len() && self.chars[self.at].is_ascii_digit() {
+            self.at += 1;
+        }
+
+        match self.at > start {
+            true => self.chars[start..self.at].iter().collect::<String>().parse().ok(),
+            false => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn version(x: &str) -> Version {
+        Version::parse(x).unwrap_or_else(|| panic!("{} is a valid version", x))
+    }
+
+    #[test]
+    fn versions_are_ordered() {
+        let ordered = [
+            "1.0.dev0", "1.0a1.dev1", "1.0a1", "1.0a1.post1", "1.0b2", "1.0rc1", "1.0", "1.0+abc", "1.0+5",
+            "1.0.post1.dev1", "1.0.post1", "1.0.1", "1.1", "1!0.1",
+        ];
+
+        for pair in ordered.windows(2) {
+            assert!(version(pair[0]) < version(pair[1]), "{} < {}", pair[0], pair[1]);
+        }
+    }
+
+    #[test]
+    fn trailing_zeros_are_equal() {
+        assert_eq!(version("1.0"), version("1.0.0"));
+        assert_eq!(version("2"), version("2.0.0.0"));
+        assert!(version("1.0+local") != version("1.0"));
+    }
+
+    #[test]
+    fn alternative_spellings_are_normalised() {
+        for (written, normalised) in [
+            ("v1.0", "1.0"),
+            ("1.0-alpha.1", "1.0a1"),
+            ("1.0.BETA2", "1.0b2"),
+            ("1.0c1", "1.0rc1"),
+            ("1.0-1", "1.0.post1"),
+            ("1.0.post", "1.0.post0"),
+            ("1.0-dev", "1.0.dev0"),
+            ("1.0+Ubuntu-1", "1.0+ubuntu.1"),
+        ] {
+            assert_eq!(version(written).to_string(), normalised, "{}", written);
+        }
+    }
+
+    #[test]
+    fn invalid_versions_are_rejected() {
+        for x in ["", "a", "1.", "1..0", "1.0+", "1.0 beta"] {
+            assert!(Version::parse(x).is_none(), "{}", x);
+        }
+    }
+}
@@ -0,0 +1,107 @@
+//! Run work across a pool of threads, keeping results in order
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+
+/// Environment variable overriding the number of threads used
+pub const THREADS_VAR: &str = "PYPROJ_THREADS";
+
+/// Number of threads to use: `PYPROJ_THREADS` or one per CPU
+pub fn threads() -> usize {
+    std::env::var(THREADS_VAR)
+        .ok()
+        .and_then(|x| x.parse().ok())
+        .filter(|x| *x > 0)
+        .unwrap_or_else(|| thread::available_parallelism().map(|x| x.get()).unwrap_or(1))
+}
+
+/// Apply a function to every item across the pool, returning the results
+/// in the order of the items
+pub fn map<T, R, F>(items: &[T], f: F) -> Vec<R>
+where
+    T: Sync,
+    R: Send,
+    F: Fn(&T) -> R + Sync,
+{
+    map_with(threads(), items, f)
+}
+
+/// Apply a function to every item across a number of threads
+fn map_with<T, R, F>(threads: usize, items: &[T], f: F) -> Vec<R>
+where
+    T: Sync,
+    R: Send,
+    F: Fn(&T) -> R + Sync,
+{
+    let threads = threads.min(items.len());
+
+    if threads <= 1 {
+        return items.iter().map(f).collect();
+    }
+
+    // Threads take the next item when they're done, so slow files don't hold up a chunk
+    let next = AtomicUsize::new(0);
+    let results: Mutex<Vec<Option<R>>> = Mutex::new((0..items.len()).map(|_| None).collect());
+
+    thread::scope(|scope| {
+        for _ in 0..threads {
+            scope.spawn(|| loop {
+                let i = next.fetch_add(1, Ordering::Relaxed);
+                let item = match items.get(i) {
+                    Some(x) => x,
+                    None => break,
+                };
+
+                let result = f(item);
+                results.lock().unwrap()[i] = Some(result);
+            });
+        }
+    });
+
+    results
+        .into_inner()
+        .unwrap()
+        .into_iter()
+        .map(|x| x.expect("every item is mapped"))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn results_are_in_the_order_of_the_items() {
+        let items: Vec<u64> = (0..200).collect();
+
+        // Earlier items take longer, so they finish out of order
+        let results = map_with(8, &items, |x| {
+            thread::sleep(Duration::from_micros((200 - x) * 10));
+            x * 2
+        });
+
+        assert_eq!(results, items.iter().map(|x| x * 2).collect::<Vec<u64>>());
+        assert!(map_with(8, &[] as &[u64], |x| *x).is_empty());
+        assert_eq!(map_with(8, &[1, 2], |x| x + 1), [2, 3]);
+    }
+
+    #[test]
+    fn threads_can_be_overridden() {
+        let default = thread::available_parallelism().map(|x| x.get()).unwrap_or(1);
+
+        std::env::set_var(THREADS_VAR, "3");
+        assert_eq!(threads(), 3);
+
+        // Invalid values are ignored
+        for value in ["0", "-1", "many", ""] {
+            std::env::set_var(THREADS_VAR, value);
+            assert_eq!(threads(), default, "{}", value);
+        }
+
+        std::env::remove_var(THREADS_VAR);
+        assert_eq!(threads(), default);
+    }
+}
@@ -0,0 +1,58 @@
+//! Helpers shared by the subcommands writing reports
+
+/// Align rows into columns under a header, two spaces apart
+pub fn table<const N: usize>(header: [&str; N], rows: &[[String; N]]) -> String {
+    let mut widths = header.map(|x| x.len());
+    for row in rows {
+        for (i, cell) in row.iter().enumerate() {
+            widths[i] = widths[i].max(cell.len());
+        }
+    }
+
+    let header = header.map(|x| x.to_string());
+
+    let mut table = String::new();
+    for row in std::iter::once(&header).chain(rows) {
+        let cells: Vec<String> = row
+            .iter()
+            .enumerate()
+            .map(|(i, cell)| format!("{:width$}", cell, width = widths[i]))
+            .collect();
+        table.push_str(cells.join("  ").trim_end());
+        table.push('\n');
+    }
+
+    table
+}
+
+/// Print notes (progress and warnings) alongside a report
+///
+/// Notes go to stderr when the report is printed to the console in a
+/// format other than a table, so they can't end up in a redirected
+/// report.
+pub fn notes(format: &str, output: Option<&str>) -> impl Fn(String) {
+    let quiet = output.is_none() && format != "table";
+
+    move |message: String| match quiet {
+        true => eprintln!("{}", message),
+        false => println!("{}", message),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn columns_are_aligned() {
+        let rows = [
+            [String::from("requests"), String::from("2.31.0"), String::from("")],
+            [String::from("six"), String::from("1.16.0"), String::from("MIT")],
+        ];
+
+        assert_eq!(
+            table(["Package", "Version", "License"], &rows),
+            "Package   Version  License\nrequests  2.31.0\nsix       1.16.0   MIT\n"
+        );
+    }
+}
@@ -0,0 +1,109 @@
+//! Format an SBOM as a CycloneDX 1.5 JSON document
+
+use serde_json::{json, Value};
+
+use crate::licenses::spdx::Expression;
+
+use super::{Component, Sbom};
+
+pub fn to_json(sbom: &Sbom) -> Value {
+    let mut dependencies = vec![json!({
+        "ref": sbom.project.purl,
+        "dependsOn": sbom.project.depends_on,
+    })];
+
+    for component in &sbom.components {
+        dependencies.push(json!({
+            "ref": component.purl,
+            "dependsOn": component.depends_on,
+        }));
+    }
+
+    json!({
+        "bomFormat": "CycloneDX",
+        "specVersion": "1.5",
+        "serialNumber": format!("urn:uuid:{}", sbom.serial),
+        "version": 1,
+        "metadata": {
+            "timestamp": sbom.timestamp,
+            "tools": {
+                "components": [{
+                    "type": "application",
+                    "name": "pyproj",
+                    "version": env!("CARGO_PKG_VERSION"),
+                }],
+            },
+            "component": component(&sbom.project, "application"),
+        },
+        "components": sbom.components
+            .iter()
+            .map(|x| component(x, "library"))
+            .collect::<Vec<Value>>(),
+        "dependencies": dependencies,
+    })
+}
+
+fn component(component: &Component, kind: &str) -> Value {
+    let mut value = json!({
+        "type": kind,
+        "bom-ref": component.purl,
+        "name": component.name,
+        "version": component.version,
+        "purl": component.purl,
+    });
+
+    if let Some(licenses) = licenses(component) {
+        value["licenses"] = licenses;
+    }
+    if let Some(description) = &component.description {
+        value["description"] = json!(description);
+    }
+    if let Some(author) = &component.author {
+        value["author"] = json!(author);
+    }
+    if let Some(home_page) = &component.home_page {
+        value["externalReferences"] = json!([{ "type": "website", "url": home_page }]);
+    }
+
+    // Installed files are nested as components of their own
+    if !component.files.is_empty() {
+        value["components"] = component.files
+            .iter()
+            .map(|file| json!({
+                "type": "file",
+                "bom-ref": format!("{}#{}", component.purl, file.path),
+                "name": file.path,
+                "hashes": file.hashes
+                    .iter()
+                    .map(|(algorithm, digest)| json!({ "alg": algorithm_name(algorithm), "content": digest }))
+                    .collect::<Vec<Value>>(),
+            }))
+            .collect();
+    }
+
+    value
+}
+
+/// A single license as an SPDX id (or a name if it isn't one), anything
+/// else as an expression
+fn licenses(component: &Component) -> Option<Value> {
+    let (expression, known) = component.license.as_ref()?;
+
+    Some(match expression {
+        Expression::License(id, None) if *known => json!([{ "license": { "id": id } }]),
+        Expression::License(name, None) => json!([{ "license": { "name": name } }]),
+        _ => json!([{ "expression": expression.to_string() }]),
+    })
+}
+
+/// CycloneDX name of a hash algorithm (`sha256` is `SHA-256`)
+fn algorithm_name(algorithm: &str) -> String {
+    match algorithm.to_lowercase().as_str() {
+        "sha1" => String::from("SHA-1"),
+        "sha256" => String::from("SHA-256"),
+        "sha384" => String::from("SHA-384"),
+        "sha512" => String::from("SHA-512"),
+        "md5" => String::from("MD5"),
+        x => x.to_uppercase(),
+    }
+}
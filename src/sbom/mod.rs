@@ -0,0 +1,391 @@
+//! Build a software bill of materials of the project and every
+//! installed distribution it depends on
+
+pub mod cyclonedx;
+pub mod spdx;
+
+use std::fs;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use sha1::{Digest, Sha1};
+
+use crate::build::{Group, SETUP_CFG};
+use crate::config::SetupCfg;
+use crate::datetime::timestamp;
+use crate::deps::find_declarations;
+use crate::digest::hex;
+use crate::licenses::spdx::{self as license, Expression};
+use crate::package::installed::{self, required_distributions};
+use crate::package::marker::Environment;
+use crate::package::name::normalise_name;
+use crate::package::requirement::Requirement;
+use crate::report::notes;
+
+pub const FORMATS: [&str; 2] = ["cyclonedx", "spdx"];
+
+/// Options of the sbom subcommand
+pub struct SbomOptions {
+    pub format: String,
+    /// Include the dev requirements (and what they require)
+    pub dev: bool,
+    pub output: Option<String>,
+}
+
+/// Everything an SBOM describes
+pub struct Sbom {
+    pub project: Component,
+    pub components: Vec<Component>,
+    /// Creation time (RFC 3339)
+    pub timestamp: String,
+    /// Unique id of the document (a UUID)
+    pub serial: String,
+}
+
+/// The project or one of its dependencies
+pub struct Component {
+    pub name: String,
+    pub version: String,
+    /// Package URL (`pkg:pypi/requests@2.31.0`)
+    pub purl: String,
+    /// License expression, and whether it only uses SPDX identifiers
+    pub license: Option<(Expression, bool)>,
+    pub description: Option<String>,
+    pub author: Option<String>,
+    pub home_page: Option<String>,
+    pub files: Vec<File>,
+    /// Purls of the components it depends on
+    pub depends_on: Vec<String>,
+}
+
+/// A file installed by a dependency
+pub struct File {
+    pub path: String,
+    /// Hashes as `(algorithm, hex digest)`, the SHA-256 from `RECORD`
+    /// and a SHA-1 computed from the file
+    pub hashes: Vec<(String, String)>,
+}
+
+/// Write an SBOM of the project in CycloneDX or SPDX format
+///
+/// Returns false if it couldn't be built or written.
+pub fn sbom(options: &SbomOptions) -> bool {
+    let note = notes(&options.format, options.output.as_deref());
+
+    let declarations = match find_declarations() {
+        Ok(x) => x,
+        Err(e) => {
+            note(e);
+            return false;
+        }
+    };
+
+    let requirements: Vec<Requirement> = declarations
+        .into_iter()
+        .filter(|x| options.dev || x.group == Group::Runtime)
+        .map(|x| x.requirement)
+        .collect();
+
+    let distributions = installed::find_distributions();
+    let environment = Environment::current();
+    let (required, missing) = required_distributions(&distributions, &requirements, &environment);
+
+    for requirement in &missing {
+        note(format!("WARNING: {} isn't installed, so it isn't in the SBOM", requirement.name));
+    }
+
+    let names: Vec<String> = required.iter().map(|(x, _)| normalise_name(&x.name)).collect();
+    let purl_of = |name: &str| -> Option<String> {
+        required
+            .iter()
+            .find(|(x, _)| normalise_name(&x.name) == normalise_name(name))
+            .map(|(x, _)| purl(&x.name, &x.version))
+    };
+
+    let mut components: Vec<Component> = Vec::new();
+
+    for (distribution, extras) in &required {
+        let mut depends_on: Vec<String> = distribution
+            .requires
+            .iter()
+            .filter(|x| x.applies(&environment, extras) && names.contains(&x.key()))
+            .filter_map(|x| purl_of(&x.name))
+            .collect();
+        depends_on.sort();
+        depends_on.dedup();
+
+        let root = distribution.root();
+        let files = distribution
+            .record()
+            .into_iter()
+            // Files without a hash (RECORD itself, bytecode) are generated on install
+            .filter_map(|file| {
+                let (algorithm, digest) = file.hash?;
+                let mut hashes = vec![(algorithm, digest)];
+
+                if let Ok(content) = fs::read(root.join(&file.path)) {
+                    hashes.push((String::from("sha1"), hex(&Sha1::digest(&content))));
+                }
+
+                Some(File { path: file.path, hashes })
+            })
+            .collect();
+
+        components.push(Component {
+            name: distribution.name.clone(),
+            version: distribution.version.clone(),
+            purl: purl(&distribution.name, &distribution.version),
+            license: license::normalise(&distribution.license).map(|(x, _)| {
+                let known = x.is_known();
+                (x, known)
+            }),
+            description: None,
+            author: distribution.author.clone(),
+            home_page: distribution.home_page.clone(),
+            files,
+            depends_on,
+        });
+    }
+
+    let mut direct: Vec<String> = requirements
+        .iter()
+        .filter(|x| x.applies(&environment, &[]))
+        .filter_map(|x| purl_of(&x.name))
+        .collect();
+    direct.sort();
+    direct.dedup();
+
+    let project = match project(direct) {
+        Ok(x) => x,
+        Err(e) => {
+            note(e);
+            return false;
+        }
+    };
+
+    let (timestamp, seconds) = now();
+    let serial = uuid(&format!("{}@{}:{}", project.name, project.version, seconds));
+
+    let sbom = Sbom { project, components, timestamp, serial };
+
+    let document = match options.format.as_str() {
+        "spdx" => spdx::to_json(&sbom),
+        _ => cyclonedx::to_json(&sbom),
+    };
+    let document = serde_json::to_string_pretty(&document).unwrap();
+
+    match &options.output {
+        Some(file) => match fs::write(file, document) {
+            Ok(_) => {
+                println!("Successfully wrote an SBOM of {} component(s) to {}", sbom.components.len(), file);
+                true
+            },
+            Err(e) => {
+                println!("Error while writing to {}: {}", file, e);
+                false
+            }
+        },
+        None => {
+            println!("{}", document);
+            true
+        }
+    }
+}
+
+/// The project, described by setup.cfg's `[metadata]`
+fn project(depends_on: Vec<String>) -> Result<Component, String> {
+    let cfg = match Path::new(SETUP_CFG).exists() {
+        true => Some(SetupCfg::load(Path::new(SETUP_CFG)).map_err(|e| format!("Error while reading {}: {}", SETUP_CFG, e))?),
+        false => None,
+    };
+
+    let get = |key: &str| -> Option<String> {
+        cfg.as_ref()
+            .and_then(|x| x.get("metadata", key))
+            .filter(|x| !x.is_empty())
+    };
+
+    // Fall back to the project's folder name
+    let name = get("name").unwrap_or_else(|| {
+        std::env::current_dir()
+            .ok()
+            .and_then(|x| x.file_name().map(|x| x.to_string_lossy().to_string()))
+            .unwrap_or_else(|| String::from("project"))
+    });
+    let version = get("version").unwrap_or_else(|| String::from("0.0.0"));
+
+    let license = get("license").map(|x| {
+        let expression = license::from_name(&x)
+            .map(|id| Expression::License(id, None))
+            .or_else(|| Expression::parse(&x))
+            .unwrap_or_else(|| Expression::License(x.clone(), None));
+        let known = expression.is_known();
+        (expression, known)
+    });
+
+    Ok(Component {
+        purl: purl(&name, &version),
+        name,
+        version,
+        license,
+        description: get("description"),
+        author: get("author"),
+        home_page: get("url"),
+        files: Vec::new(),
+        depends_on,
+    })
+}
+
+/// Package URL of a PyPI distribution (the name is normalised)
+fn purl(name: &str, version: &str) -> String {
+    format!("pkg:pypi/{}@{}", normalise_name(name), version)
+}
+
+/// A UUID derived from a name (version 5 style, from its SHA-1)
+fn uuid(name: &str) -> String {
+    let mut bytes = Sha1::digest(name.as_bytes())[..16].to_vec();
+    bytes[6] = (bytes[6] & 0x0f) | 0x50;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+
+    let hex = hex(&bytes);
+    format!("{}-{}-{}-{}-{}", &hex[..8], &hex[8..12], &hex[12..16], &hex[16..20], &hex[20..])
+}
+
+/// The current time (or `SOURCE_DATE_EPOCH` for reproducible builds)
+/// in RFC 3339, with the seconds since the epoch
+fn now() -> (String, u64) {
+    let seconds = std::env::var("SOURCE_DATE_EPOCH")
+        .ok()
+        .and_then(|x| x.parse().ok())
+        .unwrap_or_else(|| SystemTime::now().duration_since(UNIX_EPOCH).map(|x| x.as_secs()).unwrap_or(0));
+
+    (timestamp(seconds), seconds)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn component(name: &str, version: &str, license: &str) -> Component {
+        let license = Expression::License(license.to_string(), None);
+        let known = license.is_known();
+
+        Component {
+            name: name.to_string(),
+            version: version.to_string(),
+            purl: purl(name, version),
+            license: Some((license, known)),
+            description: None,
+            author: None,
+            home_page: None,
+            files: Vec::new(),
+            depends_on: Vec::new(),
+        }
+    }
+
+    fn example() -> Sbom {
+        let mut project = component("My_App", "1.0", "MIT");
+        project.depends_on = vec![purl("requests", "2.31.0")];
+
+        let mut requests = component("requests", "2.31.0", "Apache-2.0");
+        requests.depends_on = vec![purl("idna", "3.6")];
+        requests.files = vec![
+            File {
+                path: String::from("requests/__init__.py"),
+                hashes: vec![
+                    (String::from("sha256"), String::from("ab12")),
+                    (String::from("sha1"), String::from("cd34")),
+                ],
+            },
+            // Couldn't be read, so there's no SHA-1
+            File {
+                path: String::from("requests/api.py"),
+                hashes: vec![(String::from("sha256"), String::from("ef56"))],
+            },
+        ];
+
+        Sbom {
+            project,
+            components: vec![requests, component("idna", "3.6", "Custom License")],
+            timestamp: timestamp(0),
+            serial: uuid("My_App@1.0:0"),
+        }
+    }
+
+    #[test]
+    fn uuids_are_derived_from_names() {
+        let id = uuid("project@1.0:0");
+        let groups: Vec<usize> = id.split('-').map(|x| x.len()).collect();
+
+        assert_eq!(groups, [8, 4, 4, 4, 12]);
+        assert_eq!(&id[14..15], "5");
+        assert!(["8", "9", "a", "b"].contains(&&id[19..20]));
+        assert_eq!(id, uuid("project@1.0:0"));
+        assert_ne!(id, uuid("project@1.0:1"));
+    }
+
+    #[test]
+    fn cyclonedx_documents() {
+        let sbom = example();
+        let document = cyclonedx::to_json(&sbom);
+
+        assert_eq!(document["serialNumber"], format!("urn:uuid:{}", sbom.serial));
+        assert_eq!(document["metadata"]["timestamp"], "1970-01-01T00:00:00Z");
+        assert_eq!(document["metadata"]["component"]["purl"], "pkg:pypi/my-app@1.0");
+        assert_eq!(document["metadata"]["component"]["licenses"][0]["license"]["id"], "MIT");
+
+        let requests = &document["components"][0];
+        assert_eq!(requests["type"], "library");
+        assert_eq!(requests["components"][0]["bom-ref"], "pkg:pypi/requests@2.31.0#requests/__init__.py");
+        assert_eq!(requests["components"][0]["hashes"][1]["alg"], "SHA-1");
+        assert_eq!(requests["components"].as_array().unwrap().len(), 2);
+        assert_eq!(document["components"][1]["licenses"][0]["license"]["name"], "Custom License");
+
+        assert_eq!(document["dependencies"][0]["dependsOn"][0], "pkg:pypi/requests@2.31.0");
+        assert_eq!(document["dependencies"][1]["dependsOn"][0], "pkg:pypi/idna@3.6");
+    }
+
+    #[test]
+    fn spdx_documents() {
+        let sbom = example();
+        let document = spdx::to_json(&sbom);
+
+        assert_eq!(document["documentDescribes"][0], "SPDXRef-Package-My-App");
+        assert!(document["documentNamespace"].as_str().unwrap().ends_with(&format!("My-App-1.0-{}", sbom.serial)));
+        assert_eq!(document["packages"][0]["licenseDeclared"], "MIT");
+        assert_eq!(document["packages"][2]["licenseDeclared"], "LicenseRef-Custom-License");
+        assert_eq!(document["hasExtractedLicensingInfos"][0]["name"], "Custom License");
+
+        // Only files with a SHA-1 are listed
+        let requests = &document["packages"][1];
+        assert_eq!(requests["filesAnalyzed"], true);
+        assert_eq!(
+            requests["packageVerificationCode"]["packageVerificationCodeValue"],
+            hex(&Sha1::digest(b"cd34"))
+        );
+        assert_eq!(document["files"].as_array().unwrap().len(), 1);
+        assert_eq!(document["files"][0]["fileName"], "./requests/__init__.py");
+
+        let relationships: Vec<(String, String, String)> = document["relationships"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|x| (
+                x["spdxElementId"].as_str().unwrap().to_string(),
+                x["relationshipType"].as_str().unwrap().to_string(),
+                x["relatedSpdxElement"].as_str().unwrap().to_string(),
+            ))
+            .collect();
+        let expected = [
+            ("SPDXRef-DOCUMENT", "DESCRIBES", "SPDXRef-Package-My-App"),
+            ("SPDXRef-Package-My-App", "DEPENDS_ON", "SPDXRef-Package-requests"),
+            ("SPDXRef-Package-requests", "CONTAINS", "SPDXRef-File-requests-1"),
+            ("SPDXRef-Package-requests", "DEPENDS_ON", "SPDXRef-Package-idna"),
+        ];
+        let expected: Vec<(String, String, String)> = expected
+            .iter()
+            .map(|(a, b, c)| (a.to_string(), b.to_string(), c.to_string()))
+            .collect();
+        assert_eq!(relationships, expected);
+    }
+}
@@ -0,0 +1,167 @@
+//! Format an SBOM as an SPDX 2.3 JSON document
+
+use serde_json::{json, Value};
+use sha1::{Digest, Sha1};
+
+use crate::digest::hex;
+
+use super::{Component, File, Sbom};
+
+pub fn to_json(sbom: &Sbom) -> Value {
+    let project_id = package_id(&sbom.project.name);
+
+    let mut packages: Vec<Value> = Vec::new();
+    let mut files: Vec<Value> = Vec::new();
+    let mut relationships = vec![json!({
+        "spdxElementId": "SPDXRef-DOCUMENT",
+        "relationshipType": "DESCRIBES",
+        "relatedSpdxElement": project_id,
+    })];
+    // License texts of expressions which aren't SPDX ones
+    let mut extracted: Vec<Value> = Vec::new();
+
+    for component in std::iter::once(&sbom.project).chain(&sbom.components) {
+        let id = package_id(&component.name);
+        let mut package = package(component, &id, &mut extracted);
+
+        // SPDX requires a SHA-1 of every file, so files which couldn't be read are left out
+        let hashed: Vec<(&File, &str)> = component.files
+            .iter()
+            .filter_map(|file| {
+                let (_, sha1) = file.hashes.iter().find(|(algorithm, _)| algorithm == "sha1")?;
+                Some((file, sha1.as_str()))
+            })
+            .collect();
+
+        if !hashed.is_empty() {
+            let mut digests: Vec<&str> = hashed.iter().map(|(_, sha1)| *sha1).collect();
+            digests.sort_unstable();
+
+            package["filesAnalyzed"] = json!(true);
+            package["packageVerificationCode"] = json!({
+                "packageVerificationCodeValue": hex(&Sha1::digest(digests.concat().as_bytes())),
+            });
+        }
+
+        for (i, (file, _)) in hashed.iter().enumerate() {
+            let file_id = format!("SPDXRef-File-{}-{}", &id["SPDXRef-Package-".len()..], i + 1);
+
+            files.push(json!({
+                "fileName": format!("./{}", file.path),
+                "SPDXID": file_id,
+                "checksums": file.hashes
+                    .iter()
+                    .map(|(algorithm, digest)| json!({ "algorithm": algorithm.to_uppercase(), "checksumValue": digest }))
+                    .collect::<Vec<Value>>(),
+                "licenseConcluded": "NOASSERTION",
+                "copyrightText": "NOASSERTION",
+            }));
+            relationships.push(json!({
+                "spdxElementId": id,
+                "relationshipType": "CONTAINS",
+                "relatedSpdxElement": file_id,
+            }));
+        }
+
+        for purl in &component.depends_on {
+            if let Some(dependency) = sbom.components.iter().find(|x| &x.purl == purl) {
+                relationships.push(json!({
+                    "spdxElementId": id,
+                    "relationshipType": "DEPENDS_ON",
+                    "relatedSpdxElement": package_id(&dependency.name),
+                }));
+            }
+        }
+
+        packages.push(package);
+    }
+
+    let mut document = json!({
+        "spdxVersion": "SPDX-2.3",
+        "dataLicense": "CC0-1.0",
+        "SPDXID": "SPDXRef-DOCUMENT",
+        "name": format!("{}-{}", sbom.project.name, sbom.project.version),
+        "documentNamespace": format!(
+            "https://spdx.org/spdxdocs/{}-{}-{}",
+            id_safe(&sbom.project.name), id_safe(&sbom.project.version), sbom.serial
+        ),
+        "creationInfo": {
+            "created": sbom.timestamp,
+            "creators": [format!("Tool: pyproj-{}", env!("CARGO_PKG_VERSION"))],
+        },
+        "documentDescribes": [project_id],
+        "packages": packages,
+        "files": files,
+        "relationships": relationships,
+    });
+
+    if !extracted.is_empty() {
+        document["hasExtractedLicensingInfos"] = json!(extracted);
+    }
+
+    document
+}
+
+fn package(component: &Component, id: &str, extracted: &mut Vec<Value>) -> Value {
+    let license = match &component.license {
+        Some((expression, true)) => expression.to_string(),
+        // Refer to licenses which aren't SPDX ones by a `LicenseRef-`
+        Some((expression, false)) => {
+            let reference = format!("LicenseRef-{}", id_safe(&expression.to_string()));
+
+            if !extracted.iter().any(|x| x["licenseId"] == json!(reference)) {
+                extracted.push(json!({
+                    "licenseId": reference,
+                    "name": expression.to_string(),
+                    "extractedText": expression.to_string(),
+                }));
+            }
+
+            reference
+        },
+        None => String::from("NOASSERTION"),
+    };
+
+    let mut package = json!({
+        "name": component.name,
+        "SPDXID": id,
+        "versionInfo": component.version,
+        "downloadLocation": "NOASSERTION",
+        "filesAnalyzed": false,
+        "licenseConcluded": "NOASSERTION",
+        "licenseDeclared": license,
+        "copyrightText": "NOASSERTION",
+        "externalRefs": [{
+            "referenceCategory": "PACKAGE-MANAGER",
+            "referenceType": "purl",
+            "referenceLocator": component.purl,
+        }],
+    });
+
+    if let Some(description) = &component.description {
+        package["description"] = json!(description);
+    }
+    if let Some(author) = &component.author {
+        package["originator"] = json!(format!("Person: {}", author));
+    }
+    if let Some(home_page) = &component.home_page {
+        package["homepage"] = json!(home_page);
+    }
+
+    package
+}
+
+fn package_id(name: &str) -> String {
+    format!("SPDXRef-Package-{}", id_safe(name))
+}
+
+/// Replace the characters SPDX ids can't contain (anything but letters,
+/// digits, `.` and `-`)
+fn id_safe(x: &str) -> String {
+    x.chars()
+        .map(|c| match c.is_ascii_alphanumeric() || c == '.' || c == '-' {
+            true => c,
+            false => '-',
+        })
+        .collect()
+}
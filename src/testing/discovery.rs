@@ -0,0 +1,311 @@
+//! Find the project's tests without running them
+
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use crate::build::is_py;
+use crate::explorer::Explorer;
+use crate::interpreter::definitions::Definition;
+use crate::interpreter::source::Diagnostic;
+use crate::interpreter::Parser;
+use crate::pool;
+
+use super::TESTS_DIR;
+
+/// A test file and the tests defined in it
+pub struct TestFile {
+    pub path: PathBuf,
+    pub classes: Vec<TestClass>,
+    /// Module-level test functions (pytest style)
+    pub functions: Vec<TestItem>,
+}
+
+/// A `unittest.TestCase` subclass or a pytest-style `Test*` class
+pub struct TestClass {
+    pub name: String,
+    pub line: usize,
+    pub methods: Vec<TestItem>,
+}
+
+/// A test function or method
+pub struct TestItem {
+    pub name: String,
+    pub line: usize,
+}
+
+/// Every test found under the tests folder
+pub struct Discovery {
+    pub files: Vec<TestFile>,
+    /// Files which couldn't be read
+    pub errors: Vec<Diagnostic>,
+}
+
+impl TestFile {
+    /// Number of tests in the file
+    pub fn count(&self) -> usize {
+        self.functions.len() + self.classes.iter().map(|x| x.methods.len()).sum::<usize>()
+    }
+
+    /// Targets `pyproj test` accepts for the file and every class and test
+    /// in it (`tests/test_math.py::TestAdd::test_one`)
+    pub fn targets(&self) -> Vec<String> {
+        let file = self.path.to_string_lossy().replace('\\', "/");
+        let mut targets = vec![file.clone()];
+
+        for class in &self.classes {
+            targets.push(format!("{}::{}", file, class.name));
+            for method in &class.methods {
+                targets.push(format!("{}::{}::{}", file, class.name, method.name));
+            }
+        }
+
+        for function in &self.functions {
+            targets.push(format!("{}::{}", file, function.name));
+        }
+
+        targets
+    }
+}
+
+impl Discovery {
+    /// Statically find the tests under the tests folder: test methods of
+    /// `unittest.TestCase` subclasses and `Test*` classes, and `test*`
+    /// functions, in files named `test*.py` or `*_test.py`
+    pub fn find() -> Discovery {
+        let mut explorer = Explorer::new();
+        explorer.explore(Path::new(TESTS_DIR), &is_test_file);
+
+        let mut errors = explorer.errors;
+        let mut parsed: Vec<(PathBuf, Vec<Definition>)> = Vec::new();
+
+        let definitions = pool::map(&explorer.results, |x| Parser::new(x).definitions());
+
+        for (path, definitions) in explorer.results.into_iter().zip(definitions) {
+            match definitions {
+                Ok(x) => parsed.push((path, x)),
+                Err(e) => errors.push(e),
+            }
+        }
+
+        Discovery::from_definitions(&parsed, errors)
+    }
+
+    /// Find the tests among the definitions of each test file
+    fn from_definitions(parsed: &[(PathBuf, Vec<Definition>)], errors: Vec<Diagnostic>) -> Discovery {
+        // Test classes may subclass test classes of other files
+        let classes = test_classes(parsed);
+
+        let files = parsed
+            .iter()
+            .map(|(path, definitions)| TestFile {
+                path: path.clone(),
+                classes: definitions
+                    .iter()
+                    .filter(|x| x.class && x.scope.is_empty() && classes.contains(&x.name))
+                    .map(|class| TestClass {
+                        name: class.name.clone(),
+                        line: class.line,
+                        methods: definitions
+                            .iter()
+                            .filter(|x| !x.class && is_test_name(&x.name) && x.scope == [class.name.clone()])
+                            .map(|x| TestItem { name: x.name.clone(), line: x.line })
+                            .collect(),
+                    })
+                    .filter(|x| !x.methods.is_empty())
+                    .collect(),
+                functions: definitions
+                    .iter()
+                    .filter(|x| !x.class && x.scope.is_empty() && is_test_name(&x.name))
+                    .map(|x| TestItem { name: x.name.clone(), line: x.line })
+                    .collect(),
+            })
+            .filter(|x| x.count() > 0)
+            .collect();
+
+        Discovery { files, errors }
+    }
+
+    /// Number of tests found
+    pub fn count(&self) -> usize {
+        self.files.iter().map(|x| x.count()).sum()
+    }
+
+    /// The tests as a tree of files, classes and tests, with the file and
+    /// line each is defined at
+    pub fn to_tree(&self) -> String {
+        let mut tree = String::new();
+
+        for file in &self.files {
+            let path = file.path.to_string_lossy().replace('\\', "/");
+            tree.push_str(&format!("{}\n", path));
+
+            for class in &file.classes {
+                tree.push_str(&format!("    {}  {}:{}\n", class.name, path, class.line));
+                for method in &class.methods {
+                    tree.push_str(&format!("        {}  {}:{}\n", method.name, path, method.line));
+                }
+            }
+
+            for function in &file.functions {
+                tree.push_str(&format!("    {}  {}:{}\n", function.name, path, function.line));
+            }
+        }
+
+        tree
+    }
+}
+
+/// Names of every test class: `TestCase` subclasses (directly or through
+/// another test class) and `Test*` classes without an `__init__`
+fn test_classes(parsed: &[(PathBuf, Vec<Definition>)]) -> HashSet<String> {
+    let classes: Vec<(&Definition, bool)> = parsed
+        .iter()
+        .flat_map(|(_, definitions)| {
+            definitions.iter().filter(|x| x.class && x.scope.is_empty()).map(move |class| {
+                let init = definitions
+                    .iter()
+                    .any(|x| x.name == "__init__" && x.scope == [class.name.clone()]);
+                (class, init)
+            })
+        })
+        .collect();
+
+    let mut found: HashSet<String> = classes
+        .iter()
+        .filter(|(class, init)| {
+            class.bases.iter().any(|x| base_name(x).ends_with("TestCase"))
+                || (class.name.starts_with("Test") && !init)
+        })
+        .map(|(class, _)| class.name.clone())
+        .collect();
+
+    // Keep adding subclasses of test classes until there are no more
+    loop {
+        let count = found.len();
+
+        for (class, _) in &classes {
+            if class.bases.iter().any(|x| found.contains(base_name(x))) {
+                found.insert(class.name.clone());
+            }
+        }
+
+        if found.len() == count {
+            return found;
+        }
+    }
+}
+
+/// Name of a base class without its module (`unittest.TestCase` is `TestCase`)
+fn base_name(base: &str) -> &str {
+    base.rsplit('.').next().unwrap_or(base)
+}
+
+/// Whether a function or method is a test
+fn is_test_name(name: &str) -> bool {
+    name.starts_with("test")
+}
+
+/// Whether a file holds tests (`test*.py` or `*_test.py`)
+fn is_test_file(path: &Path) -> bool {
+    let name = match path.file_name() {
+        Some(x) => x.to_string_lossy(),
+        None => return false,
+    };
+
+    is_py(path) && (name.starts_with("test") || name.ends_with("_test.py"))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::interpreter::definitions::find_definitions;
+    use crate::interpreter::source::split_lines;
+
+    use super::*;
+
+    fn discover(files: &[(&str, &str)]) -> Discovery {
+        let parsed: Vec<(PathBuf, Vec<Definition>)> = files
+            .iter()
+            .map(|(path, source)| (PathBuf::from(path), find_definitions(&split_lines(source))))
+            .collect();
+
+        Discovery::from_definitions(&parsed, Vec::new())
+    }
+
+    const BASE: &str = "\
+import unittest
+
+class BaseCase(unittest.TestCase):
+    def setUp(self):
+        pass
+
+    def test_base(self):
+        pass
+";
+
+    const MATH: &str = "\
+from tests.test_base import BaseCase
+
+class TestAdd(BaseCase):
+    def test_one(self):
+        def test_nested():
+            pass
+
+    def helper(self):
+        pass
+
+class TestWithInit:
+    def __init__(self):
+        pass
+
+    def test_ignored(self):
+        pass
+
+class Helper:
+    def test_ignored(self):
+        pass
+
+def test_function():
+    pass
+
+def helper():
+    pass
+";
+
+    #[test]
+    fn test_case_subclasses_and_test_functions_are_found() {
+        let discovery = discover(&[("tests/test_base.py", BASE), ("tests/test_math.py", MATH)]);
+
+        assert_eq!(discovery.count(), 3);
+        assert_eq!(discovery.to_tree(), "\
+tests/test_base.py
+    BaseCase  tests/test_base.py:3
+        test_base  tests/test_base.py:7
+tests/test_math.py
+    TestAdd  tests/test_math.py:3
+        test_one  tests/test_math.py:4
+    test_function  tests/test_math.py:22
+");
+    }
+
+    #[test]
+    fn flat_targets_list_files_classes_and_tests() {
+        let discovery = discover(&[("tests/test_base.py", BASE), ("tests/test_math.py", MATH)]);
+
+        assert_eq!(discovery.files[1].targets(), [
+            "tests/test_math.py",
+            "tests/test_math.py::TestAdd",
+            "tests/test_math.py::TestAdd::test_one",
+            "tests/test_math.py::test_function",
+        ]);
+    }
+
+    #[test]
+    fn files_without_tests_are_skipped() {
+        let discovery = discover(&[("tests/test_empty.py", "class TestNothing:\n    def helper(self):\n        pass\n")]);
+
+        assert!(discovery.files.is_empty());
+        assert!(is_test_file(Path::new("tests/test_x.py")));
+        assert!(is_test_file(Path::new("tests/x_test.py")));
+        assert!(!is_test_file(Path::new("tests/conftest.py")));
+    }
+}
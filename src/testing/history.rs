@@ -0,0 +1,270 @@
+//! Keep the outcome of every test over the recent runs, to re-run the
+//! tests which failed and find flaky tests
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use super::results::{Outcome, TestRun};
+use super::REPORTS_DIR;
+
+/// File (in the reports folder) holding the history
+const HISTORY_FILE: &str = "history";
+
+/// First line of the history file, followed by the format's version
+const HEADER: &str = "pyproj-test-history 1";
+
+/// Runs kept in the history, older runs are forgotten
+const MAX_RUNS: usize = 50;
+
+/// Times a test must change between passing and failing to be flaky
+pub const FLAKY_FLIPS: usize = 2;
+
+/// Outcomes of the tests over the recent runs, oldest first
+#[derive(Default)]
+pub struct History {
+    pub runs: Vec<RunRecord>,
+}
+
+/// A test run as it was recorded
+pub struct RunRecord {
+    /// Seconds since the epoch
+    pub time: u64,
+    pub runner: String,
+    pub tests: Vec<TestRecord>,
+}
+
+pub struct TestRecord {
+    /// Dotted id of the test (`tests.test_math.TestAdd.test_one`)
+    pub id: String,
+    pub outcome: Outcome,
+    /// Seconds
+    pub duration: f64,
+}
+
+impl History {
+    /// Load the history, starting afresh if it doesn't exist or can't be read
+    pub fn load() -> History {
+        fs::read_to_string(Path::new(REPORTS_DIR).join(HISTORY_FILE))
+            .ok()
+            .and_then(|x| parse(&x))
+            .unwrap_or_default()
+    }
+
+    /// Add a run to the history, forgetting the oldest runs
+    ///
+    /// Runs which didn't report any tests aren't recorded.
+    pub fn record(&mut self, run: &TestRun) {
+        if run.results.is_empty() {
+            return;
+        }
+
+        let time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|x| x.as_secs())
+            .unwrap_or(0);
+
+        self.runs.push(RunRecord {
+            time,
+            runner: run.runner.clone(),
+            tests: run.results
+                .iter()
+                .map(|x| TestRecord { id: x.id(), outcome: x.outcome, duration: x.duration })
+                .collect(),
+        });
+
+        if self.runs.len() > MAX_RUNS {
+            self.runs.drain(..self.runs.len() - MAX_RUNS);
+        }
+    }
+
+    /// Write the history to disk
+    pub fn save(&self) -> io::Result<()> {
+        let content = self.serialise();
+
+        fs::create_dir_all(REPORTS_DIR)?;
+
+        // Write then rename, so an interrupted run can't leave half a history
+        let path = Path::new(REPORTS_DIR).join(HISTORY_FILE);
+        let temporary = path.with_extension("tmp");
+        fs::write(&temporary, content)?;
+        fs::rename(&temporary, &path)
+    }
+
+    /// Content of the history file: the header, then a line for every
+    /// run (`R`, time, runner) followed by a line for each of its tests
+    /// (`T`, outcome, duration, id), the fields separated by tabs
+    fn serialise(&self) -> String {
+        // Tabs and newlines would break the format
+        let clean = |x: &str| x.replace(['\t', '\n', '\r'], " ");
+
+        let mut content = format!("{}\n", HEADER);
+        for run in &self.runs {
+            content.push_str(&format!("R\t{}\t{}\n", run.time, clean(&run.runner)));
+
+            for test in &run.tests {
+                content.push_str(&format!("T\t{}\t{}\t{}\n", test.outcome, test.duration, clean(&test.id)));
+            }
+        }
+
+        content
+    }
+
+    /// Tests which failed (or errored) the last time they ran, sorted
+    pub fn failed(&self) -> Vec<String> {
+        let mut latest: BTreeMap<&str, Outcome> = BTreeMap::new();
+
+        for run in &self.runs {
+            for test in &run.tests {
+                latest.insert(&test.id, test.outcome);
+            }
+        }
+
+        latest
+            .into_iter()
+            .filter(|(_, outcome)| outcome.is_problem())
+            .map(|(id, _)| id.to_string())
+            .collect()
+    }
+
+    /// Every recorded run of a test, oldest first
+    pub fn outcomes(&self, id: &str) -> Vec<(&RunRecord, &TestRecord)> {
+        self.runs
+            .iter()
+            .filter_map(|run| run.tests.iter().find(|x| x.id == id).map(|x| (run, x)))
+            .collect()
+    }
+
+    /// Number of times a test changed between passing and failing
+    /// (skipped runs are ignored)
+    pub fn flips(&self, id: &str) -> usize {
+        let problems: Vec<bool> = self
+            .outcomes(id)
+            .iter()
+            .filter(|(_, x)| x.outcome != Outcome::Skipped)
+            .map(|(_, x)| x.outcome.is_problem())
+            .collect();
+
+        problems.windows(2).filter(|x| x[0] != x[1]).count()
+    }
+
+    /// Ids of the recorded tests matching a test given by the user, either
+    /// a dotted id or a target (`tests/test_math.py::TestAdd`), matching
+    /// the test itself or every test in a file or class
+    pub fn matching(&self, test: &str) -> Vec<String> {
+        let query = test
+            .replace('\\', "/")
+            .replace(".py::", "::")
+            .trim_end_matches(".py")
+            .replace("::", ".")
+            .replace('/', ".");
+
+        let mut ids: Vec<String> = self.runs
+            .iter()
+            .flat_map(|x| x.tests.iter().map(|x| &x.id))
+            .filter(|id| {
+                *id == &query
+                    || id.starts_with(&format!("{}.", query))
+                    || id.ends_with(&format!(".{}", query))
+                    || id.contains(&format!(".{}.", query))
+            })
+            .cloned()
+            .collect();
+
+        ids.sort();
+        ids.dedup();
+        ids
+    }
+}
+
+/// Parse a history file, or None if it's from another version of the format
+fn parse(content: &str) -> Option<History> {
+    let mut lines = content.lines();
+
+    if lines.next()? != HEADER {
+        return None;
+    }
+
+    let mut runs: Vec<RunRecord> = Vec::new();
+
+    for line in lines {
+        let fields: Vec<&str> = line.splitn(4, '\t').collect();
+
+        match fields.as_slice() {
+            ["R", time, runner] => runs.push(RunRecord {
+                time: time.parse().ok()?,
+                runner: runner.to_string(),
+                tests: Vec::new(),
+            }),
+            ["T", outcome, duration, id] => runs.last_mut()?.tests.push(TestRecord {
+                id: id.to_string(),
+                outcome: Outcome::from_name(outcome)?,
+                duration: duration.parse().ok()?,
+            }),
+            // A corrupt history is thrown away
+            _ => return None,
+        }
+    }
+
+    Some(History { runs })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(time: u64, tests: &[(&str, Outcome)]) -> RunRecord {
+        RunRecord {
+            time,
+            runner: String::from("pytest"),
+            tests: tests
+                .iter()
+                .map(|(id, outcome)| TestRecord { id: id.to_string(), outcome: *outcome, duration: 0.25 })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn round_trip_keeps_every_run() {
+        let history = History {
+            runs: vec![
+                run(1, &[("tests.test_a.TestA.test_one", Outcome::Passed), ("tests.test_a.test_two[x\ty]", Outcome::Error)]),
+                run(2, &[("tests.test_a.TestA.test_one", Outcome::Skipped)]),
+            ],
+        };
+
+        let read = parse(&history.serialise()).expect("the history is read back");
+        assert_eq!(read.runs.len(), 2);
+        assert_eq!((read.runs[0].time, read.runs[0].runner.as_str()), (1, "pytest"));
+
+        let test = &read.runs[0].tests[1];
+        assert_eq!((test.id.as_str(), test.outcome, test.duration), ("tests.test_a.test_two[x y]", Outcome::Error, 0.25));
+        assert_eq!(read.runs[1].tests[0].outcome, Outcome::Skipped);
+    }
+
+    #[test]
+    fn corrupt_histories_are_ignored() {
+        assert!(parse("pyproj-test-history 0\n").is_none());
+        assert!(parse(&format!("{}\nT\tpassed\t0.1\tno.run\n", HEADER)).is_none());
+        assert!(parse(&format!("{}\nR\t1\tpytest\nT\tsurprised\t0.1\tx\n", HEADER)).is_none());
+    }
+
+    #[test]
+    fn failed_tests_and_flips_use_the_latest_outcomes() {
+        let history = History {
+            runs: vec![
+                run(1, &[("a", Outcome::Failed), ("b", Outcome::Failed), ("c", Outcome::Passed)]),
+                run(2, &[("a", Outcome::Passed), ("c", Outcome::Passed)]),
+                run(3, &[("a", Outcome::Skipped), ("c", Outcome::Error)]),
+                run(4, &[("a", Outcome::Failed)]),
+            ],
+        };
+
+        assert_eq!(history.failed(), ["a", "b", "c"]);
+        assert_eq!(history.flips("a"), 2);
+        assert_eq!(history.flips("b"), 0);
+        assert_eq!(history.flips("c"), 1);
+    }
+}
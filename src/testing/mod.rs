@@ -0,0 +1,331 @@
+//! Run the project's tests with pytest, unittest or nose2
+
+pub mod discovery;
+pub mod history;
+pub mod report;
+pub mod results;
+pub mod runner;
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::Instant;
+
+use crate::build::SETUP_CFG;
+use crate::config::SetupCfg;
+use crate::datetime::timestamp;
+
+use discovery::Discovery;
+use history::{History, FLAKY_FLIPS};
+use results::{Exit, Outcome, TestRun};
+use runner::{Runner, Target, FIRST_VAR};
+
+/// setup.cfg section configuring the test subcommand
+pub const SECTION: &str = "tool:pyproj.test";
+
+/// Folder holding the project's tests
+pub const TESTS_DIR: &str = "tests";
+
+/// Folder (from the project root) holding pyproj's test reports
+pub const REPORTS_DIR: &str = ".pyproj/test";
+
+/// Report written by the runner, read back for its results
+const RUNNER_REPORT: &str = "runner.xml";
+
+/// Reports written after every run, unless setup.cfg or the options
+/// give other paths
+const JUNIT_REPORT: &str = "junit.xml";
+const JSON_REPORT: &str = "results.json";
+
+/// Options of the test subcommand
+pub struct TestOptions {
+    /// File, folder, class or method to run (every test if None)
+    pub target: Option<String>,
+    /// Only run tests matching a `-k` expression
+    pub keyword: Option<String>,
+    /// Runner to use instead of the detected one
+    pub runner: Option<Runner>,
+    /// Arguments given after `--`, passed to the runner as they are
+    pub extra: Vec<String>,
+    /// Where to write the JUnit XML report
+    pub junit: Option<String>,
+    /// Where to write the JSON report
+    pub json: Option<String>,
+    /// Only run the tests which failed last time
+    pub failed: bool,
+    /// Run the tests which failed last time before the others
+    pub failed_first: bool,
+}
+
+/// Show the tests found under the tests folder without running them,
+/// as a tree or (with `flat`) as one target per line for shell completion
+///
+/// Returns false if no tests were found.
+pub fn list_tests(flat: bool) -> bool {
+    let discovery = Discovery::find();
+
+    for e in &discovery.errors {
+        eprintln!("WARNING: Skipped {}", e);
+    }
+
+    if flat {
+        for target in discovery.files.iter().flat_map(|x| x.targets()) {
+            println!("{}", target);
+        }
+        return discovery.count() > 0;
+    }
+
+    if discovery.files.is_empty() {
+        println!("No tests found in ./{}/", TESTS_DIR);
+        return false;
+    }
+
+    print!("{}", discovery.to_tree());
+    println!(
+        "\n{} tests in {} files",
+        discovery.count(), discovery.files.len()
+    );
+
+    true
+}
+
+/// Run the tests with the project's runner, then show a summary of the
+/// results, write them as JUnit XML and JSON and add them to the history
+///
+/// With `failed`, only the tests which failed last time are run. With
+/// `failed_first`, they run before the other tests: pytest is given `--ff`
+/// and pyproj's unittest runner reorders the suite, nose2 can't.
+///
+/// Returns false if the tests couldn't be run or any failed.
+pub fn run_tests(options: &TestOptions) -> bool {
+    let runner = match options.runner {
+        Some(runner) => runner,
+        None => {
+            let (runner, reason) = Runner::detect();
+            println!("Using {} ({})", runner, reason);
+            runner
+        }
+    };
+
+    let mut targets: Vec<Target> = match options.target.as_deref().map(Target::parse).transpose() {
+        Ok(x) => x.into_iter().collect(),
+        Err(e) => {
+            println!("{}", e);
+            return false;
+        }
+    };
+
+    let mut history = History::load();
+
+    // Ids of the tests run before the others
+    let mut first: Vec<String> = Vec::new();
+
+    if options.failed {
+        targets = failed_targets(&history);
+
+        if targets.is_empty() {
+            println!("No tests failed last time");
+            return true;
+        }
+        println!("Re-running the {} tests which failed last time", targets.len());
+    } else if options.failed_first {
+        first = history.failed();
+
+        match (first.is_empty(), runner) {
+            (true, _) => println!("No tests failed last time"),
+            (false, Runner::Nose2) => println!("WARNING: nose2 can't run the tests which failed last time first"),
+            (false, _) => println!("Running the {} tests which failed last time first", first.len()),
+        };
+    }
+
+    let mut arguments = match runner.arguments(&targets, options.keyword.as_deref()) {
+        Ok(x) => x,
+        Err(e) => {
+            println!("{}", e);
+            return false;
+        }
+    };
+
+    // pytest keeps its own record of the tests which failed
+    if runner == Runner::Pytest && !first.is_empty() {
+        arguments.push(String::from("--ff"));
+    }
+    arguments.extend(options.extra.iter().cloned());
+
+    let run = match execute(runner, &arguments, &first) {
+        Ok(x) => x,
+        Err(e) => {
+            println!("Error while running {}: {}", runner, e);
+            return false;
+        }
+    };
+
+    println!();
+    if run.results.is_empty() {
+        println!("WARNING: {} didn't report any tests", runner);
+    } else {
+        print!("{}", report::to_table(&run));
+    }
+
+    write_reports(&run, options);
+
+    history.record(&run);
+    if let Err(e) = history.save() {
+        println!("WARNING: Couldn't write the test history to {}: {}", REPORTS_DIR, e);
+    }
+
+    for result in &run.results {
+        let flips = history.flips(&result.id());
+
+        if flips >= FLAKY_FLIPS {
+            println!(
+                "WARNING: {} is flaky, it changed between passing and failing {} times in {} recorded runs",
+                result.id(), flips, history.outcomes(&result.id()).len()
+            );
+        }
+    }
+
+    match run.is_success() {
+        true => println!("\n------------- SUCCESSFUL -------------"),
+        false => println!("\n------------ UNSUCCESSFUL ------------"),
+    };
+
+    match run.exit {
+        Exit::Code(code) => println!(
+            "Elapsed {elapsed}ms\nCompleted with exit code {code}",
+            elapsed=(run.duration * 1000.0).round(),
+            code=code
+        ),
+        Exit::Signal(signal) => println!(
+            "Elapsed {elapsed}ms\nTerminated by signal {signal}",
+            elapsed=(run.duration * 1000.0).round(),
+            signal=signal
+        ),
+    };
+
+    run.is_success()
+}
+
+/// Show the recorded outcomes of a test (or of every test in a file or
+/// class), flagging flaky tests
+///
+/// Returns false if the test was never recorded.
+pub fn show_history(test: &str) -> bool {
+    let history = History::load();
+    let ids = history.matching(test);
+
+    if ids.is_empty() {
+        println!("No recorded runs of '{}'", test);
+        return false;
+    }
+
+    for id in ids {
+        let outcomes = history.outcomes(&id);
+        let flips = history.flips(&id);
+
+        match flips >= FLAKY_FLIPS {
+            true => println!("{}  FLAKY (changed between passing and failing {} times)", id, flips),
+            false => println!("{}", id),
+        };
+
+        for (run, test) in &outcomes {
+            println!(
+                "    {}  {:<7}  {:>8}  {}",
+                timestamp(run.time), test.outcome, report::format_duration(test.duration), run.runner
+            );
+        }
+
+        let passed = outcomes.iter().filter(|(_, x)| x.outcome == Outcome::Passed).count();
+        println!("    Passed {} of {} recorded runs\n", passed, outcomes.len());
+    }
+
+    true
+}
+
+/// Targets of the tests which failed last time, skipping the tests which
+/// no longer exist
+fn failed_targets(history: &History) -> Vec<Target> {
+    let mut targets: Vec<Target> = Vec::new();
+
+    for id in history.failed() {
+        match Target::parse(&id) {
+            Ok(x) => targets.push(x),
+            Err(_) => println!("WARNING: Skipped {} which no longer exists", id),
+        }
+    }
+
+    targets
+}
+
+/// Run the runner with its arguments, collecting the results it reports
+///
+/// The tests in `first` run before the others with pyproj's unittest
+/// runner.
+fn execute(runner: Runner, arguments: &[String], first: &[String]) -> io::Result<TestRun> {
+    fs::create_dir_all(REPORTS_DIR)?;
+
+    // Results of a previous run mustn't be mistaken for this one's
+    let report = Path::new(REPORTS_DIR).join(RUNNER_REPORT);
+    if report.exists() {
+        fs::remove_file(&report)?;
+    }
+
+    let command = runner.command(&report)?;
+
+    println!("Running unit tests..");
+    println!("python -m {} {}", runner.module(), arguments.join(" "));
+
+    let start = Instant::now();
+
+    let status = Command::new("cmd")
+        .args(["/c", "python"])
+        .args(&command)
+        .args(arguments)
+        .env(FIRST_VAR, first.join("\n"))
+        .stdout(Stdio::inherit())
+        .status()?;
+
+    let duration = start.elapsed().as_secs_f64();
+
+    // The report is missing if the runner couldn't start or was killed
+    let results = fs::read_to_string(&report)
+        .map(|x| results::parse_junit(&x))
+        .unwrap_or_default();
+
+    Ok(TestRun {
+        runner: runner.to_string(),
+        results,
+        duration,
+        exit: Exit::from_status(status),
+    })
+}
+
+/// Write the JUnit XML and JSON reports where the options or setup.cfg
+/// say, or in the reports folder
+fn write_reports(run: &TestRun, options: &TestOptions) {
+    let cfg = SetupCfg::load(Path::new(SETUP_CFG)).ok();
+    let path = |option: &Option<String>, key: &str, default: &str| -> PathBuf {
+        option
+            .clone()
+            .or_else(|| cfg.as_ref().and_then(|x| x.get(SECTION, key)))
+            .map(PathBuf::from)
+            .unwrap_or_else(|| Path::new(REPORTS_DIR).join(default))
+    };
+
+    let reports = [
+        (path(&options.junit, "junit", JUNIT_REPORT), report::to_junit(run)),
+        (path(&options.json, "json", JSON_REPORT), serde_json::to_string_pretty(&report::to_json(run)).unwrap()),
+    ];
+
+    for (path, content) in reports {
+        let written = match path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => fs::create_dir_all(dir).and_then(|_| fs::write(&path, content)),
+            _ => fs::write(&path, content),
+        };
+
+        if let Err(e) = written {
+            println!("WARNING: Couldn't write {}: {}", path.display(), e);
+        }
+    }
+}
@@ -0,0 +1,160 @@
+//! Format test results as a summary table, JUnit XML or JSON
+
+use serde_json::{json, Value};
+
+use crate::report::table;
+
+use super::results::{escape, Exit, Outcome, TestRun};
+
+/// Tests shown as the slowest in the summary
+const SLOWEST: usize = 5;
+
+/// A table of the tests which didn't pass, the slowest tests and the
+/// number of tests with each outcome
+pub fn to_table(run: &TestRun) -> String {
+    let mut summary = String::new();
+
+    let problems: Vec<[String; 4]> = run.results
+        .iter()
+        .filter(|x| x.outcome != Outcome::Passed)
+        .map(|x| [
+            x.outcome.to_string(),
+            x.id(),
+            format_duration(x.duration),
+            x.message.clone().unwrap_or_default().lines().next().unwrap_or_default().to_string(),
+        ])
+        .collect();
+
+    if !problems.is_empty() {
+        summary.push_str(&table(["Outcome", "Test", "Duration", "Message"], &problems));
+        summary.push('\n');
+    }
+
+    let mut slowest: Vec<_> = run.results.iter().filter(|x| x.duration > 0.0).collect();
+    slowest.sort_by(|a, b| b.duration.total_cmp(&a.duration));
+
+    if run.results.len() > SLOWEST {
+        let slow: Vec<[String; 4]> = slowest
+            .iter()
+            .take(SLOWEST)
+            .map(|x| [x.outcome.to_string(), x.id(), format_duration(x.duration), String::new()])
+            .collect();
+
+        summary.push_str(&format!("Slowest {} tests\n", slow.len()));
+        summary.push_str(&table(["Outcome", "Test", "Duration", ""], &slow));
+        summary.push('\n');
+    }
+
+    let counts: Vec<String> = Outcome::ALL
+        .iter()
+        .map(|x| match (x, run.count(*x)) {
+            (Outcome::Error, n) if n != 1 => format!("{} errors", n),
+            (x, n) => format!("{} {}", n, x),
+        })
+        .collect();
+
+    summary.push_str(&format!(
+        "{} tests: {} in {}\n",
+        run.results.len(), counts.join(", "), format_duration(run.duration)
+    ));
+
+    summary
+}
+
+/// A JUnit XML report, with a single test suite
+pub fn to_junit(run: &TestRun) -> String {
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<testsuites>\n");
+
+    xml.push_str(&format!(
+        "  <testsuite name=\"{}\" tests=\"{}\" failures=\"{}\" errors=\"{}\" skipped=\"{}\" time=\"{:.3}\">\n",
+        escape(&run.runner),
+        run.results.len(),
+        run.count(Outcome::Failed),
+        run.count(Outcome::Error),
+        run.count(Outcome::Skipped),
+        run.duration
+    ));
+
+    for result in &run.results {
+        let mut attributes = format!(
+            "classname=\"{}\" name=\"{}\" time=\"{:.3}\"",
+            escape(&result.classname), escape(&result.name), result.duration
+        );
+
+        if let Some(file) = &result.file {
+            attributes.push_str(&format!(" file=\"{}\"", escape(file)));
+        }
+        if let Some(line) = result.line {
+            attributes.push_str(&format!(" line=\"{}\"", line));
+        }
+
+        let tag = match result.outcome {
+            Outcome::Passed => {
+                xml.push_str(&format!("    <testcase {} />\n", attributes));
+                continue;
+            },
+            Outcome::Failed => "failure",
+            Outcome::Error => "error",
+            Outcome::Skipped => "skipped",
+        };
+
+        xml.push_str(&format!("    <testcase {}>\n", attributes));
+        xml.push_str(&format!(
+            "      <{} message=\"{}\">{}</{}>\n",
+            tag,
+            escape(result.message.as_deref().unwrap_or_default()),
+            escape(result.details.as_deref().unwrap_or_default()),
+            tag
+        ));
+        xml.push_str("    </testcase>\n");
+    }
+
+    xml.push_str("  </testsuite>\n</testsuites>\n");
+    xml
+}
+
+pub fn to_json(run: &TestRun) -> Value {
+    let tests: Vec<Value> = run.results
+        .iter()
+        .map(|x| json!({
+            "id": x.id(),
+            "classname": x.classname,
+            "name": x.name,
+            "file": x.file,
+            "line": x.line,
+            "outcome": x.outcome.name(),
+            "duration": x.duration,
+            "message": x.message,
+            "details": x.details,
+        }))
+        .collect();
+
+    let mut summary = serde_json::Map::new();
+    summary.insert(String::from("total"), json!(run.results.len()));
+    for outcome in Outcome::ALL {
+        summary.insert(outcome.name().to_string(), json!(run.count(outcome)));
+    }
+
+    let (code, signal) = match run.exit {
+        Exit::Code(x) => (Some(x), None),
+        Exit::Signal(x) => (None, Some(x)),
+    };
+
+    json!({
+        "runner": run.runner,
+        "success": run.is_success(),
+        "exit_code": code,
+        "signal": signal,
+        "duration": run.duration,
+        "summary": summary,
+        "tests": tests,
+    })
+}
+
+/// A duration in seconds, as milliseconds under a second
+pub fn format_duration(seconds: f64) -> String {
+    match seconds < 1.0 {
+        true => format!("{}ms", (seconds * 1000.0).round()),
+        false => format!("{:.2}s", seconds),
+    }
+}
@@ -0,0 +1,392 @@
+//! Per-test outcomes, read from the JUnit XML written by the runner
+
+use std::collections::HashMap;
+use std::fmt;
+use std::process::ExitStatus;
+
+/// How a test ended
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Outcome {
+    Passed,
+    Failed,
+    /// The test couldn't run (an exception outside its assertions, a
+    /// module which can't be imported...)
+    Error,
+    Skipped,
+}
+
+/// Result of a single test
+#[derive(Clone)]
+pub struct TestResult {
+    /// Dotted module and class (`tests.test_math.TestAdd`)
+    pub classname: String,
+    pub name: String,
+    pub file: Option<String>,
+    pub line: Option<usize>,
+    pub outcome: Outcome,
+    /// Seconds
+    pub duration: f64,
+    /// One line describing the failure, error or skip
+    pub message: Option<String>,
+    /// Traceback of a failure or error
+    pub details: Option<String>,
+}
+
+/// How the runner's process ended
+#[derive(Clone, Copy, PartialEq)]
+pub enum Exit {
+    Code(i32),
+    /// Killed by a signal (Unix only)
+    Signal(i32),
+}
+
+/// Everything a test run produced
+pub struct TestRun {
+    pub runner: String,
+    pub results: Vec<TestResult>,
+    /// Seconds
+    pub duration: f64,
+    pub exit: Exit,
+}
+
+impl Outcome {
+    pub const ALL: [Outcome; 4] = [Outcome::Passed, Outcome::Failed, Outcome::Error, Outcome::Skipped];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Outcome::Passed => "passed",
+            Outcome::Failed => "failed",
+            Outcome::Error => "error",
+            Outcome::Skipped => "skipped",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Outcome> {
+        Outcome::ALL.iter().find(|x| x.name() == name).copied()
+    }
+
+    /// Whether the test counts against the run
+    pub fn is_problem(&self) -> bool {
+        matches!(self, Outcome::Failed | Outcome::Error)
+    }
+}
+
+impl fmt::Display for Outcome {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl TestResult {
+    /// Dotted id of the test (`tests.test_math.TestAdd.test_one`)
+    pub fn id(&self) -> String {
+        match self.classname.is_empty() {
+            true => self.name.clone(),
+            false => format!("{}.{}", self.classname, self.name),
+        }
+    }
+}
+
+impl Exit {
+    pub fn from_status(status: ExitStatus) -> Exit {
+        if let Some(code) = status.code() {
+            return Exit::Code(code);
+        }
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::process::ExitStatusExt;
+
+            if let Some(signal) = status.signal() {
+                return Exit::Signal(signal);
+            }
+        }
+
+        Exit::Code(-1)
+    }
+}
+
+impl TestRun {
+    /// Number of tests with an outcome
+    pub fn count(&self, outcome: Outcome) -> usize {
+        self.results.iter().filter(|x| x.outcome == outcome).count()
+    }
+
+    /// Whether the runner exited cleanly and every test passed (or was
+    /// skipped)
+    pub fn is_success(&self) -> bool {
+        self.exit == Exit::Code(0) && !self.results.iter().any(|x| x.outcome.is_problem())
+    }
+}
+
+/// Read the tests of a JUnit XML report (as written by pytest, nose2 and
+/// pyproj's unittest runner)
+pub fn parse_junit(content: &str) -> Vec<TestResult> {
+    let mut results: Vec<TestResult> = Vec::new();
+    let mut current: Option<TestResult> = None;
+    // Text of the failure, error or skip being read
+    let mut text: Option<String> = None;
+
+    let mut rest = content;
+
+    while let Some(start) = rest.find('<') {
+        if let Some(text) = text.as_mut() {
+            text.push_str(&unescape(&rest[..start]));
+        }
+        rest = &rest[start..];
+
+        // Comments, declarations and CDATA
+        if let Some(x) = rest.strip_prefix("<![CDATA[") {
+            let end = x.find("]]>").unwrap_or(x.len());
+            if let Some(text) = text.as_mut() {
+                text.push_str(&x[..end]);
+            }
+            rest = x.get(end + 3..).unwrap_or("");
+            continue;
+        }
+
+        let skip = [("<!--", "-->"), ("<?", "?>"), ("<!", ">")]
+            .iter()
+            .find(|(open, _)| rest.starts_with(open));
+
+        if let Some((_, close)) = skip {
+            rest = match rest.find(close) {
+                Some(x) => &rest[x + close.len()..],
+                None => "",
+            };
+            continue;
+        }
+
+        let end = match tag_end(rest) {
+            Some(x) => x,
+            None => break,
+        };
+        let tag = &rest[1..end];
+        rest = &rest[end + 1..];
+
+        if let Some(name) = tag.strip_prefix('/') {
+            match name.trim() {
+                "testcase" => results.extend(current.take()),
+                "failure" | "error" | "skipped" => {
+                    if let (Some(result), Some(text)) = (current.as_mut(), text.take()) {
+                        let text = text.trim();
+                        if !text.is_empty() {
+                            result.details = Some(text.to_string());
+                        }
+                    }
+                },
+                _ => {},
+            }
+            continue;
+        }
+
+        let closed = tag.ends_with('/');
+        let tag = tag.trim_end_matches('/');
+        let name = tag.split_whitespace().next().unwrap_or_default();
+        let attributes = attributes(&tag[name.len()..]);
+
+        match name {
+            "testcase" => {
+                results.extend(current.take());
+
+                let result = TestResult {
+                    classname: attributes.get("classname").cloned().unwrap_or_default(),
+                    name: attributes.get("name").cloned().unwrap_or_default(),
+                    file: attributes.get("file").cloned(),
+                    line: attributes.get("line").and_then(|x| x.parse().ok()),
+                    outcome: Outcome::Passed,
+                    duration: attributes.get("time").and_then(|x| x.parse().ok()).unwrap_or(0.0),
+                    message: None,
+                    details: None,
+                };
+
+                match closed {
+                    true => results.push(result),
+                    false => current = Some(result),
+                }
+            },
+            "failure" | "error" | "skipped" => {
+                if let Some(result) = current.as_mut() {
+                    // A test failing then erroring in teardown keeps its failure
+                    if !result.outcome.is_problem() {
+                        result.outcome = match name {
+                            "failure" => Outcome::Failed,
+                            "error" => Outcome::Error,
+                            _ => Outcome::Skipped,
+                        };
+                        result.message = attributes.get("message").cloned().filter(|x| !x.is_empty());
+
+                        if !closed {
+                            text = Some(String::new());
+                        }
+                    }
+                }
+            },
+            _ => {},
+        }
+    }
+
+    results.extend(current);
+    results
+}
+
+/// Index of the `>` closing a tag, skipping quoted attribute values
+fn tag_end(tag: &str) -> Option<usize> {
+    let mut quote: Option<char> = None;
+
+    for (i, c) in tag.char_indices() {
+        match (quote, c) {
+            (None, '"') | (None, '\'') => quote = Some(c),
+            (Some(q), _) if q == c => quote = None,
+            (None, '>') => return Some(i),
+            _ => {},
+        }
+    }
+
+    None
+}
+
+/// Attributes of a tag (`name="value"` or `name='value'`)
+fn attributes(tag: &str) -> HashMap<String, String> {
+    let mut attributes: HashMap<String, String> = HashMap::new();
+    let mut rest = tag;
+
+    while let Some(equals) = rest.find('=') {
+        let name = rest[..equals].trim().to_string();
+        let value = rest[equals + 1..].trim_start();
+
+        let quote = match value.chars().next() {
+            Some(x) if x == '"' || x == '\'' => x,
+            _ => break,
+        };
+
+        let end = match value[1..].find(quote) {
+            Some(x) => x + 1,
+            None => break,
+        };
+
+        attributes.insert(name, unescape(&value[1..end]));
+        rest = &value[end + 1..];
+    }
+
+    attributes
+}
+
+/// Replace XML entities and character references
+fn unescape(text: &str) -> String {
+    let mut unescaped = String::new();
+    let mut rest = text;
+
+    while let Some(start) = rest.find('&') {
+        unescaped.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        let end = match rest.find(';') {
+            Some(x) if x < 12 => x,
+            _ => {
+                unescaped.push('&');
+                rest = &rest[1..];
+                continue;
+            }
+        };
+
+        let entity = &rest[1..end];
+        let character = match entity {
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "amp" => Some('&'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            _ => match entity.strip_prefix("#x").or_else(|| entity.strip_prefix("#X")) {
+                Some(hex) => u32::from_str_radix(hex, 16).ok().and_then(char::from_u32),
+                None => entity.strip_prefix('#').and_then(|x| x.parse().ok()).and_then(char::from_u32),
+            },
+        };
+
+        match character {
+            Some(c) => {
+                unescaped.push(c);
+                rest = &rest[end + 1..];
+            },
+            None => {
+                unescaped.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+
+    unescaped.push_str(rest);
+    unescaped
+}
+
+/// Escape text for XML content and attribute values
+pub fn escape(text: &str) -> String {
+    let mut escaped = String::new();
+
+    for c in text.chars() {
+        match c {
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '&' => escaped.push_str("&amp;"),
+            '"' => escaped.push_str("&quot;"),
+            '\n' | '\r' | '\t' => escaped.push(c),
+            // Other control characters aren't allowed in XML 1.0
+            c if (c as u32) < 0x20 => {},
+            c => escaped.push(c),
+        }
+    }
+
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn junit_reports_are_read() {
+        let report = r#"<?xml version="1.0" encoding="utf-8"?>
+<!-- written by pytest -->
+<testsuites><testsuite name="pytest" tests="4">
+  <testcase classname="tests.test_math.TestAdd" name="test_one" file="tests/test_math.py" line="7" time="0.012" />
+  <testcase classname="tests.test_math.TestAdd" name="test_two" time="1.5">
+    <failure message="assert 1 == 2">def test_two():
+&gt;       assert 1 == 2</failure>
+    <error message="teardown failed">ignored</error>
+  </testcase>
+  <testcase classname="tests.test_math" name="test_skip[a&amp;b]" time="0">
+    <skipped message='needs "numpy"' />
+  </testcase>
+  <testcase classname="" name="test_error" time="0.1"><error message=""><![CDATA[Traceback <here>]]></error></testcase>
+</testsuite></testsuites>"#;
+
+        let results = parse_junit(report);
+        assert_eq!(results.len(), 4);
+
+        let passed = &results[0];
+        assert_eq!(passed.id(), "tests.test_math.TestAdd.test_one");
+        assert_eq!((passed.file.as_deref(), passed.line), (Some("tests/test_math.py"), Some(7)));
+        assert_eq!((passed.outcome, passed.duration), (Outcome::Passed, 0.012));
+
+        // The failure is kept over the teardown error
+        let failed = &results[1];
+        assert_eq!(failed.outcome, Outcome::Failed);
+        assert_eq!(failed.message.as_deref(), Some("assert 1 == 2"));
+        assert_eq!(failed.details.as_deref(), Some("def test_two():\n>       assert 1 == 2"));
+
+        let skipped = &results[2];
+        assert_eq!((skipped.name.as_str(), skipped.outcome), ("test_skip[a&b]", Outcome::Skipped));
+        assert_eq!(skipped.message.as_deref(), Some("needs \"numpy\""));
+
+        let error = &results[3];
+        assert_eq!((error.id().as_str(), error.outcome), ("test_error", Outcome::Error));
+        assert_eq!((error.message.as_deref(), error.details.as_deref()), (None, Some("Traceback <here>")));
+    }
+
+    #[test]
+    fn escaped_text_is_read_back() {
+        let text = "a < b && \"c\" > d\n";
+        assert_eq!(unescape(&escape(text)), text);
+        assert_eq!(unescape("&#65;&#x42;&bogus;&"), "AB&bogus;&");
+    }
+}
@@ -0,0 +1,506 @@
+//! Detect the project's test runner and translate targets into its
+//! selector syntax
+
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::build::SETUP_CFG;
+use crate::config::SetupCfg;
+use crate::package::installed;
+use crate::package::name::normalise_name;
+
+use super::{SECTION, TESTS_DIR};
+
+/// Script running unittest and writing its results as JUnit XML
+const UNITTEST_SCRIPT: &str = "unittest_junit.py";
+
+/// Environment variable listing the ids of the tests (one per line) the
+/// unittest script runs before the others
+pub const FIRST_VAR: &str = "PYPROJ_TEST_FIRST";
+
+/// Content of the script: `python unittest_junit.py <report> <arguments>`
+/// takes the same arguments as `python -m unittest`
+const UNITTEST_RUNNER: &str = r#"import os
+import sys
+import time
+import traceback
+import unittest
+from xml.sax.saxutils import escape, quoteattr
+
+# Tests in the order they ran: id -> (outcome, seconds, message, details)
+RESULTS = {}
+
+
+class Result(unittest.TextTestResult):
+    def startTest(self, test):
+        self._started = time.perf_counter()
+        super().startTest(test)
+
+    def _record(self, test, outcome, err=None, message=""):
+        started = getattr(self, "_started", None)
+        duration = time.perf_counter() - started if started else 0.0
+        details = ""
+        if err is not None:
+            message = traceback.format_exception_only(err[0], err[1])[-1].strip()
+            details = self._exc_info_to_string(err, test)
+        # The first problem of a test is kept (a failing subtest, then teardown)
+        previous = RESULTS.get(test.id())
+        if previous is None or previous[0] == "passed":
+            RESULTS[test.id()] = (outcome, duration, message, details)
+
+    def addSuccess(self, test):
+        super().addSuccess(test)
+        self._record(test, "passed")
+
+    def addFailure(self, test, err):
+        super().addFailure(test, err)
+        self._record(test, "failed", err)
+
+    def addError(self, test, err):
+        super().addError(test, err)
+        self._record(test, "error", err)
+
+    def addSkip(self, test, reason):
+        super().addSkip(test, reason)
+        self._record(test, "skipped", message=reason)
+
+    def addExpectedFailure(self, test, err):
+        super().addExpectedFailure(test, err)
+        self._record(test, "skipped", message="expected failure")
+
+    def addUnexpectedSuccess(self, test):
+        super().addUnexpectedSuccess(test)
+        self._record(test, "failed", message="unexpected success")
+
+    def addSubTest(self, test, subtest, err):
+        super().addSubTest(test, subtest, err)
+        if err is not None:
+            outcome = "failed" if issubclass(err[0], test.failureException) else "error"
+            self._record(test, outcome, err)
+
+
+class Runner(unittest.TextTestRunner):
+    def run(self, test):
+        # Tests which failed last time run first, in their usual order
+        first = set(os.environ.get("PYPROJ_TEST_FIRST", "").splitlines())
+        if first - {""}:
+            tests = sorted(flatten(test), key=lambda x: x.id() not in first)
+            test = unittest.TestSuite(tests)
+        return super().run(test)
+
+
+def flatten(suite):
+    for test in suite:
+        if isinstance(test, unittest.TestSuite):
+            yield from flatten(test)
+        else:
+            yield test
+
+
+def write(path):
+    lines = ['<?xml version="1.0" encoding="utf-8"?>', '<testsuite name="unittest">']
+    for id, (outcome, duration, message, details) in RESULTS.items():
+        classname, _, name = id.rpartition(".")
+        lines.append('<testcase classname=%s name=%s time="%.6f">' % (quoteattr(classname), quoteattr(name), duration))
+        tag = {"failed": "failure", "error": "error", "skipped": "skipped"}.get(outcome)
+        if tag:
+            lines.append('<%s message=%s>%s</%s>' % (tag, quoteattr(message), escape(details), tag))
+        lines.append('</testcase>')
+    lines.append('</testsuite>')
+    with open(path, "w", encoding="utf-8") as f:
+        f.write("\n".join(lines))
+
+
+report = sys.argv[1]
+sys.argv = ["python -m unittest"] + sys.argv[2:]
+# Import tests from the project, as `python -m` would
+sys.path[0] = os.getcwd()
+
+success = False
+try:
+    runner = Runner(resultclass=Result)
+    program = unittest.main(module=None, testRunner=runner, exit=False)
+    success = program.result.wasSuccessful()
+finally:
+    write(report)
+
+sys.exit(0 if success else 1)
+"#;
+
+/// A Python test runner
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Runner {
+    Pytest,
+    Unittest,
+    Nose2,
+}
+
+/// Tests to run: a test folder or file, and optionally a class and a
+/// method in it
+#[derive(Debug)]
+pub struct Target {
+    pub path: PathBuf,
+    /// `[Class]` or `[Class, method]`, or a function of a pytest file
+    pub names: Vec<String>,
+}
+
+impl Runner {
+    pub fn from_name(name: &str) -> Option<Runner> {
+        match name.trim().to_lowercase().as_str() {
+            "pytest" | "py.test" => Some(Runner::Pytest),
+            "unittest" => Some(Runner::Unittest),
+            "nose2" => Some(Runner::Nose2),
+            _ => None,
+        }
+    }
+
+    /// Python module running the tests (`python -m <module>`)
+    pub fn module(&self) -> &'static str {
+        match self {
+            Runner::Pytest => "pytest",
+            Runner::Unittest => "unittest",
+            Runner::Nose2 => "nose2",
+        }
+    }
+
+    /// Find the project's runner, with the reason it was picked
+    ///
+    /// setup.cfg's `[tool:pyproj.test] runner` wins, then the runners
+    /// configured in the project, then the ones installed, then unittest.
+    pub fn detect() -> (Runner, String) {
+        let cfg = SetupCfg::load(Path::new(SETUP_CFG)).ok();
+
+        if let Some(name) = cfg.as_ref().and_then(|x| x.get(SECTION, "runner")) {
+            match Runner::from_name(&name) {
+                Some(runner) => return (runner, format!("set in {}", SETUP_CFG)),
+                None => println!("WARNING: Unknown test runner '{}' in {}", name, SETUP_CFG),
+            }
+        }
+
+        if let Some(file) = pytest_config(cfg.as_ref()) {
+            return (Runner::Pytest, format!("configured in {}", file));
+        }
+
+        if let Some(file) = ["nose2.cfg", "unittest.cfg"].iter().find(|x| Path::new(x).is_file()) {
+            return (Runner::Nose2, format!("configured in {}", file));
+        }
+
+        let installed: Vec<String> = installed::find_distributions()
+            .iter()
+            .map(|x| normalise_name(&x.name))
+            .collect();
+
+        for runner in [Runner::Pytest, Runner::Nose2] {
+            if installed.iter().any(|x| x == runner.module()) {
+                return (runner, String::from("installed"));
+            }
+        }
+
+        (Runner::Unittest, String::from("default"))
+    }
+
+    /// Arguments after `python` running the runner, writing a JUnit XML
+    /// report of every test to `report`
+    ///
+    /// unittest can't write reports, so it runs through a script recording
+    /// its results (written next to the report).
+    pub fn command(&self, report: &Path) -> io::Result<Vec<String>> {
+        let report = report.to_string_lossy().to_string();
+
+        let command = match self {
+            Runner::Pytest => vec![
+                String::from("-m"), String::from("pytest"),
+                format!("--junitxml={}", report),
+                // The legacy format includes the file and line of each test
+                String::from("-o"), String::from("junit_family=xunit1"),
+            ],
+            Runner::Nose2 => vec![
+                String::from("-m"), String::from("nose2"),
+                String::from("--plugin"), String::from("nose2.plugins.junitxml"),
+                String::from("--junit-xml"),
+                String::from("--junit-xml-path"), report,
+            ],
+            Runner::Unittest => {
+                let script = Path::new(&report).with_file_name(UNITTEST_SCRIPT);
+                fs::write(&script, UNITTEST_RUNNER)?;
+                vec![script.to_string_lossy().to_string(), report]
+            },
+        };
+
+        Ok(command)
+    }
+
+    /// Arguments after `python -m <module>` running targets (every test if
+    /// there are none), optionally only the tests matching a `-k` expression
+    pub fn arguments(&self, targets: &[Target], keyword: Option<&str>) -> Result<Vec<String>, String> {
+        let mut arguments: Vec<String> = Vec::new();
+
+        match (self, targets) {
+            (Runner::Pytest, []) => {
+                if Path::new(TESTS_DIR).is_dir() {
+                    arguments.push(String::from(TESTS_DIR));
+                }
+            },
+            (Runner::Pytest, _) => {
+                for target in targets {
+                    let mut selector = slashed(&target.path);
+                    for name in &target.names {
+                        selector.push_str("::");
+                        selector.push_str(name);
+                    }
+                    arguments.push(selector);
+                }
+            },
+            // unittest and nose2 take dotted names, or discover a folder
+            (_, []) => arguments.extend(discover(self, TESTS_DIR)),
+            (_, [target]) if target.path.is_dir() => {
+                if !target.names.is_empty() {
+                    return Err(format!("{} is a folder, so it has no {}", target.path.display(), target.names.join(".")));
+                }
+                arguments.extend(discover(self, &slashed(&target.path)));
+            },
+            (_, _) => {
+                for target in targets {
+                    if target.path.is_dir() {
+                        return Err(format!("{} can't discover {} along with other tests", self, target.path.display()));
+                    }
+
+                    let mut name = dotted(&target.path);
+                    for part in &target.names {
+                        name.push('.');
+                        name.push_str(part);
+                    }
+                    arguments.push(name);
+                }
+            },
+        }
+
+        if let Some(keyword) = keyword {
+            match self {
+                Runner::Nose2 => return Err(String::from("nose2 doesn't support -k expressions")),
+                _ => arguments.extend([String::from("-k"), keyword.to_string()]),
+            }
+        }
+
+        Ok(arguments)
+    }
+}
+
+impl fmt::Display for Runner {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.module())
+    }
+}
+
+impl Target {
+    /// Parse a target: a folder or file (`test_math`, `test_math.py`,
+    /// `tests/unit/test_math.py`), followed by `::Class::method`, or a
+    /// dotted name (`test_math.TestAdd.test_one`)
+    pub fn parse(target: &str) -> Result<Target, String> {
+        Target::parse_in(Path::new(""), target)
+    }
+
+    /// Parse a target, finding its file or folder under a project root
+    fn parse_in(root: &Path, target: &str) -> Result<Target, String> {
+        let mut parts = target.split("::");
+        let first = parts.next().unwrap_or_default();
+        let mut names: Vec<String> = parts.map(|x| x.to_string()).collect();
+
+        if names.iter().any(|x| x.is_empty()) {
+            return Err(format!("Invalid test target '{}'", target));
+        }
+
+        if let Some(path) = find_path(root, first) {
+            return Ok(Target { path, names });
+        }
+
+        // A dotted name, the longest prefix naming a file or folder
+        let segments = split_dotted(first);
+
+        for i in (1..segments.len()).rev() {
+            if let Some(path) = find_path(root, &segments[..i].join("/")) {
+                let mut dotted: Vec<String> = segments[i..].iter().map(|x| x.to_string()).collect();
+                dotted.append(&mut names);
+                return Ok(Target { path, names: dotted });
+            }
+        }
+
+        Err(format!("Couldn't find tests matching '{}'", first))
+    }
+}
+
+/// Find a test file or folder, from the project root or the tests folder
+fn find_path(root: &Path, name: &str) -> Option<PathBuf> {
+    let name = name.trim_end_matches(['/', '\\']);
+    if name.is_empty() {
+        return None;
+    }
+
+    let file = match name.ends_with(".py") {
+        true => name.to_string(),
+        false => format!("{}.py", name),
+    };
+
+    let candidates = [
+        root.join(&file),
+        root.join(TESTS_DIR).join(&file),
+        root.join(name),
+        root.join(TESTS_DIR).join(name),
+    ];
+
+    candidates
+        .iter()
+        .find(|x| match x.extension() {
+            Some(ext) if ext == "py" => x.is_file(),
+            _ => x.is_dir(),
+        })
+        .cloned()
+}
+
+/// Split a dotted name, keeping the dots of pytest parameters
+/// (`test_math.test_round[1.5]`) in their segment
+fn split_dotted(name: &str) -> Vec<&str> {
+    let mut segments: Vec<&str> = Vec::new();
+    let mut start = 0;
+    let mut depth = 0;
+
+    for (i, c) in name.char_indices() {
+        match c {
+            '[' => depth += 1,
+            ']' => depth -= 1,
+            '.' if depth == 0 => {
+                segments.push(&name[start..i]);
+                start = i + 1;
+            },
+            _ => {},
+        }
+    }
+    segments.push(&name[start..]);
+
+    segments
+}
+
+/// Find the file configuring pytest, if there is one
+fn pytest_config(cfg: Option<&SetupCfg>) -> Option<String> {
+    let contains = |file: &str, text: &str| {
+        fs::read_to_string(file).map(|x| x.contains(text)).unwrap_or(false)
+    };
+
+    if Path::new("pytest.ini").is_file() {
+        Some(String::from("pytest.ini"))
+    } else if contains("pyproject.toml", "[tool.pytest.ini_options]") {
+        Some(String::from("pyproject.toml"))
+    } else if contains("tox.ini", "[pytest]") {
+        Some(String::from("tox.ini"))
+    } else if cfg.map(|x| !x.keys("tool:pytest").is_empty()).unwrap_or(false) {
+        Some(String::from(SETUP_CFG))
+    } else {
+        ["conftest.py", "tests/conftest.py"]
+            .iter()
+            .find(|x| Path::new(x).is_file())
+            .map(|x| x.to_string())
+    }
+}
+
+/// Arguments discovering every test in a folder
+fn discover(runner: &Runner, dir: &str) -> Vec<String> {
+    match runner {
+        Runner::Nose2 => vec![String::from("-s"), dir.to_string(), String::from("-t"), String::from(".")],
+        _ => vec![String::from("discover"), String::from("-s"), dir.to_string(), String::from("-t"), String::from(".")],
+    }
+}
+
+/// A path separated by `/`
+fn slashed(path: &Path) -> String {
+    path.to_string_lossy().replace('\\', "/")
+}
+
+/// The dotted module name of a file (`tests/unit/test_math.py` is
+/// `tests.unit.test_math`)
+fn dotted(path: &Path) -> String {
+    slashed(&path.with_extension(""))
+        .trim_start_matches("./")
+        .replace('/', ".")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn target(path: &str, names: &[&str]) -> Target {
+        Target {
+            path: PathBuf::from(path),
+            names: names.iter().map(|x| x.to_string()).collect(),
+        }
+    }
+
+    /// Parse a target in a project, with its path from the project root
+    fn parse(root: &Path, text: &str) -> Result<(String, Vec<String>), String> {
+        let target = Target::parse_in(root, text)?;
+        Ok((slashed(target.path.strip_prefix(root).unwrap()), target.names))
+    }
+
+    #[test]
+    fn targets_are_files_folders_or_dotted_names() {
+        let root = std::env::temp_dir().join(format!("pyproj-runner-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("tests/unit")).unwrap();
+        fs::write(root.join("tests/unit/test_math.py"), "").unwrap();
+        fs::write(root.join("tests/test_io.py"), "").unwrap();
+
+        let names = |x: &[&str]| -> Vec<String> { x.iter().map(|x| x.to_string()).collect() };
+
+        assert_eq!(parse(&root, "tests/unit/test_math.py"), Ok((String::from("tests/unit/test_math.py"), names(&[]))));
+        assert_eq!(parse(&root, "test_io"), Ok((String::from("tests/test_io.py"), names(&[]))));
+        assert_eq!(parse(&root, "unit"), Ok((String::from("tests/unit"), names(&[]))));
+        assert_eq!(
+            parse(&root, "test_io.py::TestRead::test_empty"),
+            Ok((String::from("tests/test_io.py"), names(&["TestRead", "test_empty"])))
+        );
+        assert_eq!(
+            parse(&root, "unit.test_math.TestAdd.test_one"),
+            Ok((String::from("tests/unit/test_math.py"), names(&["TestAdd", "test_one"])))
+        );
+        assert_eq!(
+            parse(&root, "tests/unit/test_math.py::test_round[1.5-2.0]"),
+            Ok((String::from("tests/unit/test_math.py"), names(&["test_round[1.5-2.0]"])))
+        );
+
+        assert_eq!(
+            parse(&root, "unit.test_math.test_round[1.5-2.0]"),
+            Ok((String::from("tests/unit/test_math.py"), names(&["test_round[1.5-2.0]"])))
+        );
+
+        assert!(parse(&root, "test_io.py::").is_err());
+        assert!(parse(&root, "test_missing.TestA").is_err());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn targets_are_translated_for_each_runner() {
+        let file = target("tests/unit/test_math.py", &["TestAdd", "test_one"]);
+
+        assert_eq!(Runner::Pytest.arguments(std::slice::from_ref(&file), None).unwrap(), ["tests/unit/test_math.py::TestAdd::test_one"]);
+        assert_eq!(Runner::Unittest.arguments(std::slice::from_ref(&file), None).unwrap(), ["tests.unit.test_math.TestAdd.test_one"]);
+        assert_eq!(Runner::Nose2.arguments(std::slice::from_ref(&file), None).unwrap(), ["tests.unit.test_math.TestAdd.test_one"]);
+
+        let function = target("tests/test_math.py", &["test_round[1.5-2.0]"]);
+        assert_eq!(
+            Runner::Pytest.arguments(&[function], Some("not slow")).unwrap(),
+            ["tests/test_math.py::test_round[1.5-2.0]", "-k", "not slow"]
+        );
+
+        assert_eq!(
+            Runner::Unittest.arguments(&[], Some("add")).unwrap(),
+            ["discover", "-s", "tests", "-t", ".", "-k", "add"]
+        );
+        assert!(Runner::Nose2.arguments(&[], Some("add")).is_err());
+
+        let folder = std::env::temp_dir();
+        let folder = Target { path: folder, names: vec![String::from("TestAdd")] };
+        assert!(Runner::Unittest.arguments(&[folder], None).is_err());
+    }
+}